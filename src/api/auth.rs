@@ -1,4 +1,4 @@
-use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use actix_web::{delete, get, post, put, web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use serde_json::json;
 
 use crate::db::{self, AppData, DefaultReturn, FullUser, UserFollow, UserMetadata};
@@ -9,6 +9,26 @@ pub struct OffsetQueryProps {
     pub offset: Option<i32>,
 }
 
+#[derive(Default, PartialEq, serde::Deserialize)]
+pub struct TimelineQueryProps {
+    /// Only return posts created before this timestamp
+    pub before: Option<u64>,
+    /// The ID of the last post of the previous page, see [`db::Database::get_user_timeline`]
+    pub before_id: Option<String>,
+}
+
+#[derive(Default, PartialEq, serde::Deserialize)]
+pub struct TrendingQueryProps {
+    pub hours: Option<u64>,
+}
+
+#[derive(Default, PartialEq, serde::Deserialize)]
+pub struct SearchQueryProps {
+    #[serde(default)]
+    pub q: String,
+    pub offset: Option<i32>,
+}
+
 #[derive(serde::Deserialize)]
 struct RegisterInfo {
     username: String,
@@ -17,7 +37,75 @@ struct RegisterInfo {
 
 #[derive(serde::Deserialize)]
 struct LoginInfo {
+    /// The account ID (or secondary token for `login-st`), not needed with `password`
+    #[serde(default)]
     uid: String,
+    /// Used with `password` instead of `uid` (`login` only)
+    #[serde(default)]
+    username: String,
+    #[serde(default)]
+    password: String,
+}
+
+#[derive(serde::Deserialize)]
+struct CreateApiTokenInfo {
+    name: String,
+    /// See [`db::API_TOKEN_SCOPES`]
+    scopes: Vec<String>,
+    /// How many days the token works for, it never expires if this isn't set
+    expires_in: Option<u64>,
+}
+
+#[derive(serde::Deserialize)]
+struct SetPasswordInfo {
+    password: String,
+    /// Required if the user already has a password
+    #[serde(default)]
+    current_password: String,
+}
+
+#[derive(serde::Deserialize)]
+struct RemovePasswordInfo {
+    current_password: String,
+}
+
+#[derive(serde::Deserialize)]
+struct LoginTotpInfo {
+    /// The challenge returned by `login` or `login-st`
+    challenge: String,
+    /// A code from the user's authenticator app, or a recovery code
+    code: String,
+}
+
+#[derive(serde::Deserialize)]
+struct TotpCodeInfo {
+    code: String,
+}
+
+/// Everything needed to add an account to an authenticator app
+#[derive(serde::Serialize)]
+struct TotpEnrollment {
+    /// The base32 secret, for entering manually
+    secret: String,
+    /// The `otpauth://` URI
+    uri: String,
+    /// The URI as a QR code SVG
+    qr: String,
+}
+
+#[derive(Default, serde::Deserialize)]
+struct MarkNotificationsInfo {
+    /// Every notification is marked as read if this is empty
+    #[serde(default)]
+    ids: Vec<String>,
+}
+
+#[derive(Default, serde::Deserialize)]
+struct CreateInviteInfo {
+    /// Defaults to `1`, only staff can change this
+    max_uses: Option<usize>,
+    /// How many hours the invite works for, only staff can change this
+    expires_in: Option<u64>,
 }
 
 #[derive(serde::Deserialize)]
@@ -39,42 +127,53 @@ pub async fn callback_request() -> impl Responder {
 }
 
 #[post("/api/v1/auth/register")]
-pub async fn register(body: web::Json<RegisterInfo>, data: web::Data<AppData>) -> impl Responder {
+pub async fn register(
+    req: HttpRequest,
+    body: web::Json<RegisterInfo>,
+    data: web::Data<AppData>,
+) -> impl Responder {
     // if server disabled registration, return
-    let disabled = crate::config::get_var("REGISTRATION_DISABLED");
-
-    if disabled.is_some() {
+    if data.config.registration_disabled {
         return HttpResponse::NotAcceptable()
             .body("This server requires has registration disabled.");
     }
 
     // check invite codes
-    let invite_codes = crate::config::get_var("INVITE_CODES");
-
-    if invite_codes.is_some() {
-        let invite_codes = invite_codes.unwrap();
-        let codes: Vec<&str> = invite_codes.split(",").collect();
+    let username = body.username.trim().to_string();
 
+    let res = if data.config.requires_invite() {
         // check body for invite code
-        if body.invite_code.is_none() {
-            return HttpResponse::NotAcceptable()
-                .body("This server requires an invite code to register.");
-        }
-
-        let invite_code = body.invite_code.clone().unwrap();
-
-        if codes.contains(&invite_code.as_str()) == false {
-            return HttpResponse::NotAcceptable().body("Invalid invite code.");
+        let invite_code = match body.invite_code.clone() {
+            Some(c) => c.trim().to_string(),
+            None => {
+                return HttpResponse::NotAcceptable()
+                    .body("This server requires an invite code to register.")
+            }
+        };
+
+        if data.config.invite_codes.contains(&invite_code) {
+            data.db.create_user(username).await
+        } else {
+            data.db.create_user_with_invite(username, invite_code).await
         }
-    }
-
-    // ...
-    let username = &body.username.trim();
-    let res = data.db.create_user(username.to_string()).await;
+    } else {
+        data.db.create_user(username).await
+    };
 
-    let c = res.clone();
+    // sign in
     let set_cookie = if res.success && res.payload.is_some() {
-        format!("__Secure-Token={}; SameSite=Lax; Secure; Path=/; HostOnly=true; HttpOnly=true; Max-Age={}", c.message, 60 * 60 * 24 * 365)
+        let session = data
+            .db
+            .create_session(
+                body.username.trim().to_string(),
+                crate::pages::base::user_agent(&req),
+            )
+            .await;
+
+        match session.payload {
+            Some(token) => crate::pages::base::session_cookie(&token),
+            None => String::new(),
+        }
     } else {
         String::new()
     };
@@ -87,96 +186,195 @@ pub async fn register(body: web::Json<RegisterInfo>, data: web::Data<AppData>) -
 }
 
 #[post("/api/v1/auth/login")]
-pub async fn login(body: web::Json<LoginInfo>, data: web::Data<AppData>) -> impl Responder {
+pub async fn login(
+    req: HttpRequest,
+    body: web::Json<LoginInfo>,
+    data: web::Data<AppData>,
+) -> impl Responder {
+    let res = if !body.password.is_empty() {
+        data.db
+            .get_user_by_password(body.username.trim().to_string(), body.password.clone())
+            .await
+    } else {
+        let id = body.uid.trim();
+        let id_hashed = utility::hash(id.to_string());
+
+        data.db
+            .get_user_by_hashed(id_hashed) // if the user is returned, that means the ID is valid
+            .await
+    };
+
+    sign_in(&req, &data, res, &body.uid).await
+}
+
+#[post("/api/v1/auth/login-st")]
+pub async fn login_secondary_token(
+    req: HttpRequest,
+    body: web::Json<LoginInfo>,
+    data: web::Data<AppData>,
+) -> impl Responder {
     let id = body.uid.trim();
-    let id_hashed = utility::hash(id.to_string());
+    let id_unhashed = id.to_string();
 
     let res = data
         .db
-        .get_user_by_hashed(id_hashed) // if the user is returned, that means the ID is valid
+        .get_user_by_unhashed_st(id_unhashed) // if the user is returned, that means the token is valid
         .await;
 
-    let set_cookie = if res.is_ok() {
-        format!("__Secure-Token={}; SameSite=Lax; Secure; Path=/; HostOnly=true; HttpOnly=true; Max-Age={}", body.uid, 60 * 60 * 24 * 365)
-    } else {
-        String::new()
+    sign_in(&req, &data, res, &body.uid).await
+}
+
+/// Give the user of a successful sign in a session, or a challenge if they have two-factor
+/// authentication enabled
+///
+/// # Arguments:
+/// * `req` - the sign in request
+/// * `data` - the app data
+/// * `res` - the user that signed in, an error if the sign in failed
+/// * `uid` - the ID the user signed in with, returned as the message
+async fn sign_in(
+    req: &HttpRequest,
+    data: &AppData,
+    res: dorsal::db::special::auth_db::Result<FullUser<UserMetadata>>,
+    uid: &str,
+) -> HttpResponse {
+    let ua = match res {
+        Ok(ua) => ua,
+        Err(_) => {
+            return HttpResponse::NotAcceptable()
+                .append_header(("Content-Type", "application/json"))
+                .body(
+                    serde_json::to_string::<DefaultReturn<Option<FullUser<UserMetadata>>>>(
+                        &DefaultReturn {
+                            success: true,
+                            message: String::new(),
+                            payload: None,
+                        },
+                    )
+                    .unwrap(),
+                );
+        }
     };
 
-    if res.is_ok() == false {
-        return HttpResponse::NotAcceptable()
-            .append_header(("Set-Cookie", if res.is_ok() { &set_cookie } else { "" }))
-            .append_header(("Content-Type", "application/json"))
-            .body(
-                serde_json::to_string::<DefaultReturn<Option<FullUser<UserMetadata>>>>(
-                    &DefaultReturn {
-                        success: true,
-                        message: String::new(),
-                        payload: None,
-                    },
-                )
-                .unwrap(),
-            );
+    // users with two-factor authentication need to enter a code first
+    if data.db.is_totp_enabled(ua.user.username.clone()).await {
+        return totp_challenge(data, ua.user.username).await;
     }
 
+    let session = data
+        .db
+        .create_session(ua.user.username, crate::pages::base::user_agent(req))
+        .await;
+
+    let set_cookie = match session.payload {
+        Some(token) => crate::pages::base::session_cookie(&token),
+        None => {
+            return HttpResponse::InternalServerError()
+                .append_header(("Content-Type", "text/plain"))
+                .body(session.message);
+        }
+    };
+
     // return
-    return HttpResponse::Ok()
-        .append_header(("Set-Cookie", if res.is_ok() { &set_cookie } else { "" }))
+    HttpResponse::Ok()
+        .append_header(("Set-Cookie", set_cookie))
         .append_header(("Content-Type", "application/json"))
         .body(
             serde_json::to_string(&json! ({
                 "success": true,
-                "message": body.uid,
+                "message": uid,
             }))
             .unwrap(),
-        );
+        )
 }
 
-#[post("/api/v1/auth/login-st")]
-pub async fn login_secondary_token(
-    body: web::Json<LoginInfo>,
+/// Respond with a challenge the user exchanges for a session at [`login_totp`]
+async fn totp_challenge(data: &AppData, username: String) -> HttpResponse {
+    let res = data.db.create_totp_challenge(username).await;
+
+    if !res.success {
+        return HttpResponse::InternalServerError()
+            .append_header(("Content-Type", "text/plain"))
+            .body(res.message);
+    }
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(
+            serde_json::to_string(&json! ({
+                "success": true,
+                "message": "",
+                "totp_required": true,
+                "challenge": res.payload.unwrap(),
+            }))
+            .unwrap(),
+        )
+}
+
+#[post("/api/v1/auth/login/totp")]
+/// Finish signing in with a code, for users with two-factor authentication enabled
+pub async fn login_totp(
+    req: HttpRequest,
+    body: web::Json<LoginTotpInfo>,
     data: web::Data<AppData>,
 ) -> impl Responder {
-    let id = body.uid.trim();
-    let id_unhashed = id.to_string();
+    let username = match data
+        .db
+        .get_totp_challenge(body.challenge.clone())
+        .await
+        .payload
+    {
+        Some(u) => u,
+        None => {
+            return HttpResponse::NotAcceptable()
+                .append_header(("Content-Type", "text/plain"))
+                .body("This sign in has expired, please sign in again");
+        }
+    };
 
+    // check code
     let res = data
         .db
-        .get_user_by_unhashed_st(id_unhashed) // if the user is returned, that means the token is valid
+        .check_totp(username.clone(), &data.config.secret_key, body.code.clone())
         .await;
 
-    let set_cookie = if res.is_ok() {
-        format!("__Secure-Token={}; SameSite=Lax; Secure; Path=/; HostOnly=true; HttpOnly=true; Max-Age={}", body.uid, 60 * 60 * 24 * 365)
-    } else {
-        String::new()
-    };
+    if !res.success {
+        data.db.fail_totp_challenge(body.challenge.clone()).await;
 
-    if res.is_ok() == false {
         return HttpResponse::NotAcceptable()
-            .append_header(("Set-Cookie", if res.is_ok() { &set_cookie } else { "" }))
-            .append_header(("Content-Type", "application/json"))
-            .body(
-                serde_json::to_string::<DefaultReturn<Option<FullUser<UserMetadata>>>>(
-                    &DefaultReturn {
-                        success: true,
-                        message: String::new(),
-                        payload: None,
-                    },
-                )
-                .unwrap(),
-            );
+            .append_header(("Content-Type", "text/plain"))
+            .body(res.message);
     }
 
+    data.db.remove_totp_challenge(body.challenge.clone()).await;
+
+    // create session
+    let session = data
+        .db
+        .create_session(username, crate::pages::base::user_agent(&req))
+        .await;
+
+    let set_cookie = match session.payload {
+        Some(token) => crate::pages::base::session_cookie(&token),
+        None => {
+            return HttpResponse::InternalServerError()
+                .append_header(("Content-Type", "text/plain"))
+                .body(session.message);
+        }
+    };
+
     // return
-    return HttpResponse::Ok()
-        .append_header(("Set-Cookie", if res.is_ok() { &set_cookie } else { "" }))
+    HttpResponse::Ok()
+        .append_header(("Set-Cookie", set_cookie))
         .append_header(("Content-Type", "application/json"))
         .body(
             serde_json::to_string(&json! ({
                 "success": true,
-                "message": body.uid,
+                "message": "",
             }))
             .unwrap(),
-        );
+        )
 }
 
 #[get("/api/v1/auth/logout")]
@@ -187,44 +385,38 @@ pub async fn logout(req: HttpRequest, data: web::Data<AppData>) -> impl Responde
         return HttpResponse::NotAcceptable().body("Missing token");
     }
 
-    let res = data
-        .db
-        .get_user_by_unhashed(cookie.unwrap().value().to_string()) // if the user is returned, that means the ID is valid
-        .await;
+    // invalidate session
+    let token = cookie.unwrap().value().to_string();
+    let res = data.db.delete_session(token.clone()).await;
 
-    if !res.is_ok() {
-        return HttpResponse::NotAcceptable().body("Invalid token");
+    if !res.success {
+        // older versions stored the user's ID in the cookie, those can just be removed
+        let legacy =
+            data.config.legacy_id_cookies && data.db.get_user_by_unhashed(token).await.is_ok();
+
+        if !legacy {
+            return HttpResponse::NotAcceptable().body("Invalid token");
+        }
     }
 
     // return
-    return HttpResponse::Ok()
+    HttpResponse::Ok()
         .append_header(("Set-Cookie", "__Secure-Token=refresh; SameSite=Strict; Secure; Path=/; HostOnly=true; HttpOnly=true; Max-Age=0"))
         .append_header(("Content-Type", "text/plain"))
-        .body("You have been signed out. You can now close this tab.");
+        .body("You have been signed out. You can now close this tab.")
 }
 
 #[get("/api/v1/auth/whoami")]
 pub async fn whoami(req: HttpRequest, data: web::Data<AppData>) -> impl Responder {
-    let cookie = req.cookie("__Secure-Token");
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data, Some("read")).await;
 
-    if cookie.is_none() {
+    match token_user {
+        Some(Ok(ua)) => HttpResponse::Ok()
+            .append_header(("Content-Type", "text/plain"))
+            .body(ua.user.username),
         // just return nothing on error
-        return HttpResponse::Ok().body("");
-    }
-
-    let res = data
-        .db
-        .get_user_by_unhashed(cookie.unwrap().value().to_string()) // if the user is returned, that means the ID is valid
-        .await;
-
-    if !res.is_ok() {
-        return HttpResponse::Ok().body("");
+        _ => HttpResponse::Ok().body(""),
     }
-
-    // return
-    return HttpResponse::Ok()
-        .append_header(("Content-Type", "text/plain"))
-        .body(res.ok().unwrap().user.username);
 }
 
 #[post("/api/v1/auth/users/{name:.*}/about")]
@@ -236,7 +428,8 @@ pub async fn edit_about_request(
     let name: String = req.match_info().get("name").unwrap().to_string();
 
     // get token user
-    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone()).await;
+    let (_, _, token_user) =
+        crate::pages::base::check_auth_status(req, data.clone(), Some("profile:write")).await;
 
     if token_user.is_none() {
         return HttpResponse::NotAcceptable()
@@ -312,7 +505,7 @@ pub async fn refresh_secondary_token_request(
     let name: String = req.match_info().get("name").unwrap().to_string();
 
     // get token user
-    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone()).await;
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
 
     if token_user.is_none() {
         return HttpResponse::NotAcceptable()
@@ -374,12 +567,69 @@ pub async fn refresh_secondary_token_request(
         );
 }
 
+#[post("/api/v1/auth/users/{name:.*}/rotate-id")]
+/// Give a user a new ID, signing out all of their sessions and revoking their API tokens
+pub async fn rotate_id_request(req: HttpRequest, data: web::Data<AppData>) -> impl Responder {
+    let name: String = req.match_info().get("name").unwrap().to_string();
+
+    // get token user
+    let (_, _, token_user) =
+        crate::pages::base::check_auth_status(req.clone(), data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // check if we can update this user
+    // must be authenticated AND same user OR staff
+    let is_self = token_user.user.username == name;
+    let can_update: bool = is_self
+        | (token_user
+            .level
+            .permissions
+            .contains(&String::from("ManageUsers")));
+
+    if !can_update {
+        return HttpResponse::NotFound()
+            .body("You do not have permission to manage this user's contents.");
+    }
+
+    // rotate id
+    let res = data.db.rotate_user_id(name.clone()).await;
+
+    // keep the user signed in on this device if they rotated their own ID
+    let set_cookie = if res.success && is_self {
+        let session = data
+            .db
+            .create_session(name, crate::pages::base::user_agent(&req))
+            .await;
+
+        match session.payload {
+            Some(token) => crate::pages::base::session_cookie(&token),
+            None => String::new(),
+        }
+    } else {
+        String::new()
+    };
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Set-Cookie", set_cookie))
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
 #[post("/api/v1/auth/users/{name:.*}/follow")]
 pub async fn follow_request(req: HttpRequest, data: web::Data<AppData>) -> impl Responder {
     let name: String = req.match_info().get("name").unwrap().to_string();
 
     // get token user
-    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone()).await;
+    let (_, _, token_user) =
+        crate::pages::base::check_auth_status(req, data.clone(), Some("follows:write")).await;
 
     if token_user.is_none() {
         return HttpResponse::NotAcceptable()
@@ -413,7 +663,8 @@ pub async fn update_request(
     let name: String = req.match_info().get("name").unwrap().to_string();
 
     // get token user
-    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone()).await;
+    let (_, _, token_user) =
+        crate::pages::base::check_auth_status(req, data.clone(), Some("profile:write")).await;
 
     if token_user.is_none() {
         return HttpResponse::NotAcceptable()
@@ -474,7 +725,7 @@ pub async fn ban_request(req: HttpRequest, data: web::Data<db::AppData>) -> impl
     let name: String = req.match_info().get("name").unwrap().to_string();
 
     // get token user
-    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone()).await;
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
 
     if token_user.is_none() {
         return HttpResponse::NotAcceptable()
@@ -512,7 +763,7 @@ pub async fn followers_request(
     let name: String = req.match_info().get("name").unwrap().to_string();
 
     // get followers
-    let res: DefaultReturn<Option<Vec<db::Log>>> = data
+    let res: DefaultReturn<Option<Vec<db::Follow>>> = data
         .db
         .get_user_followers(name.to_owned(), info.offset)
         .await;
@@ -520,7 +771,7 @@ pub async fn followers_request(
     // return
     return HttpResponse::Ok()
         .append_header(("Content-Type", "application/json"))
-        .body(serde_json::to_string::<DefaultReturn<Option<Vec<db::Log>>>>(&res).unwrap());
+        .body(serde_json::to_string::<DefaultReturn<Option<Vec<db::Follow>>>>(&res).unwrap());
 }
 
 #[get("/api/v1/auth/users/{name:.*}/following")]
@@ -532,7 +783,7 @@ pub async fn following_request(
     let name: String = req.match_info().get("name").unwrap().to_string();
 
     // get following
-    let res: DefaultReturn<Option<Vec<db::Log>>> = data
+    let res: DefaultReturn<Option<Vec<db::Follow>>> = data
         .db
         .get_user_following(name.to_owned(), info.offset)
         .await;
@@ -540,7 +791,7 @@ pub async fn following_request(
     // return
     return HttpResponse::Ok()
         .append_header(("Content-Type", "application/json"))
-        .body(serde_json::to_string::<DefaultReturn<Option<Vec<db::Log>>>>(&res).unwrap());
+        .body(serde_json::to_string::<DefaultReturn<Option<Vec<db::Follow>>>>(&res).unwrap());
 }
 
 #[get("/api/v1/auth/users/{name:.*}/avatar")]
@@ -640,7 +891,8 @@ pub async fn post_activity_request(
     data: web::Data<AppData>,
 ) -> impl Responder {
     // get token user
-    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone()).await;
+    let (_, _, token_user) =
+        crate::pages::base::check_auth_status(req, data.clone(), Some("activity:write")).await;
 
     if token_user.is_none() {
         return HttpResponse::NotAcceptable()
@@ -663,63 +915,938 @@ pub async fn post_activity_request(
         .body(serde_json::to_string(&res).unwrap());
 }
 
-#[post("/api/v1/activity/{id:.*}/favorite")]
-/// Toggle a post favorite
-pub async fn favorite_request(req: HttpRequest, data: web::Data<db::AppData>) -> impl Responder {
-    let post_id = req.match_info().get("id").unwrap();
-
-    // verify auth status
-    let (set_cookie, _, token_user) =
-        crate::pages::base::check_auth_status(req.clone(), data.clone()).await;
+#[get("/api/v1/activity/timeline")]
+/// Get the posts of every user the current user is following
+pub async fn timeline_request(
+    req: HttpRequest,
+    data: web::Data<AppData>,
+    info: web::Query<TimelineQueryProps>,
+) -> impl Responder {
+    // get token user
+    let (_, _, token_user) =
+        crate::pages::base::check_auth_status(req, data.clone(), Some("read")).await;
 
     if token_user.is_none() {
-        return HttpResponse::NotAcceptable().body("An account is required to favorite posts.");
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
     }
 
-    // ...
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // get timeline
     let res = data
         .db
-        .toggle_user_post_favorite(
-            token_user.unwrap().ok().unwrap().user.username,
-            post_id.to_string(),
+        .get_user_timeline(
+            token_user.user.username,
+            info.before,
+            info.before_id.clone(),
         )
         .await;
 
     // return
-    return HttpResponse::Ok()
+    HttpResponse::Ok()
         .append_header(("Content-Type", "application/json"))
-        .append_header(("Set-Cookie", set_cookie))
-        .body(serde_json::to_string(&res).unwrap());
+        .body(serde_json::to_string(&res).unwrap())
 }
 
-#[delete("/api/v1/activity/{id:.*}")]
-/// Delete an activity post
-pub async fn delete_activity_request(
+#[get("/api/v1/activity/mentions")]
+/// Get the posts the current user has been mentioned in
+pub async fn mentions_request(
     req: HttpRequest,
-    data: web::Data<db::AppData>,
+    data: web::Data<AppData>,
+    info: web::Query<OffsetQueryProps>,
 ) -> impl Responder {
-    let post_id = req.match_info().get("id").unwrap();
-
-    // verify auth status
-    let (set_cookie, _, token_user) =
-        crate::pages::base::check_auth_status(req.clone(), data.clone()).await;
+    // get token user
+    let (_, _, token_user) =
+        crate::pages::base::check_auth_status(req, data.clone(), Some("read")).await;
 
     if token_user.is_none() {
-        return HttpResponse::NotAcceptable().body("An account is required to manage posts.");
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
     }
 
-    // ...
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // get mentions
     let res = data
         .db
-        .delete_activity_post(
-            post_id.to_string(),
-            Option::Some(token_user.unwrap().ok().unwrap().user.username),
-        )
+        .get_user_mentions(token_user.user.username, info.offset)
         .await;
 
     // return
-    return HttpResponse::Ok()
+    HttpResponse::Ok()
         .append_header(("Content-Type", "application/json"))
-        .append_header(("Set-Cookie", set_cookie))
-        .body(serde_json::to_string(&res).unwrap());
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[get("/api/v1/activity/public")]
+/// Get the latest posts from every user
+pub async fn public_activity_request(
+    data: web::Data<AppData>,
+    info: web::Query<OffsetQueryProps>,
+) -> impl Responder {
+    let res = data.db.get_public_activity(info.offset).await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[get("/api/v1/search")]
+/// Search users and posts
+pub async fn search_request(
+    data: web::Data<AppData>,
+    info: web::Query<SearchQueryProps>,
+) -> impl Responder {
+    let res = data.db.search(info.q.clone(), info.offset).await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[get("/api/v1/tags/{name:.*}")]
+/// Get the posts that used a tag
+pub async fn tag_request(
+    req: HttpRequest,
+    data: web::Data<AppData>,
+    info: web::Query<OffsetQueryProps>,
+) -> impl Responder {
+    let name = req.match_info().get("name").unwrap();
+    let res = data
+        .db
+        .get_tagged_posts(name.to_string(), info.offset)
+        .await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[get("/api/v1/trending/tags")]
+/// Get the most used tags of the last `hours` hours (24 by default, at most a week)
+pub async fn trending_tags_request(
+    data: web::Data<AppData>,
+    info: web::Query<TrendingQueryProps>,
+) -> impl Responder {
+    let res = data
+        .db
+        .get_trending_tags(info.hours.unwrap_or(24).min(24 * 7))
+        .await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[post("/api/v1/activity/{id:.*}/favorite")]
+/// Toggle a post favorite
+pub async fn favorite_request(req: HttpRequest, data: web::Data<db::AppData>) -> impl Responder {
+    let post_id = req.match_info().get("id").unwrap();
+
+    // verify auth status
+    let (set_cookie, _, token_user) =
+        crate::pages::base::check_auth_status(req.clone(), data.clone(), Some("activity:write"))
+            .await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable().body("An account is required to favorite posts.");
+    }
+
+    // ...
+    let res = data
+        .db
+        .toggle_user_post_favorite(
+            token_user.unwrap().ok().unwrap().user.username,
+            post_id.to_string(),
+        )
+        .await;
+
+    // return
+    return HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .append_header(("Set-Cookie", set_cookie))
+        .body(serde_json::to_string(&res).unwrap());
+}
+
+#[put("/api/v1/activity/{id:.*}")]
+/// Edit an activity post
+pub async fn edit_activity_request(
+    req: HttpRequest,
+    body: web::Json<db::PEditPost>,
+    data: web::Data<db::AppData>,
+) -> impl Responder {
+    let post_id = req.match_info().get("id").unwrap();
+
+    // verify auth status
+    let (set_cookie, _, token_user) =
+        crate::pages::base::check_auth_status(req.clone(), data.clone(), Some("activity:write"))
+            .await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable().body("An account is required to manage posts.");
+    }
+
+    // ...
+    let res = data
+        .db
+        .edit_activity_post(
+            post_id.to_string(),
+            body.into_inner(),
+            Option::Some(token_user.unwrap().ok().unwrap().user.username),
+        )
+        .await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .append_header(("Set-Cookie", set_cookie))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[delete("/api/v1/activity/{id:.*}")]
+/// Delete an activity post
+pub async fn delete_activity_request(
+    req: HttpRequest,
+    data: web::Data<db::AppData>,
+) -> impl Responder {
+    let post_id = req.match_info().get("id").unwrap();
+
+    // verify auth status
+    let (set_cookie, _, token_user) =
+        crate::pages::base::check_auth_status(req.clone(), data.clone(), Some("activity:write"))
+            .await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable().body("An account is required to manage posts.");
+    }
+
+    // ...
+    let res = data
+        .db
+        .delete_activity_post(
+            post_id.to_string(),
+            Option::Some(token_user.unwrap().ok().unwrap().user.username),
+        )
+        .await;
+
+    // return
+    return HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .append_header(("Set-Cookie", set_cookie))
+        .body(serde_json::to_string(&res).unwrap());
+}
+
+#[get("/api/v1/notifications")]
+/// Get the current user's notifications
+pub async fn notifications_request(
+    req: HttpRequest,
+    data: web::Data<AppData>,
+    info: web::Query<OffsetQueryProps>,
+) -> impl Responder {
+    // get token user
+    let (_, _, token_user) =
+        crate::pages::base::check_auth_status(req, data.clone(), Some("read")).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // get notifications
+    let res = data
+        .db
+        .get_user_notifications(token_user.user.username, info.offset)
+        .await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[get("/api/v1/notifications/count")]
+/// Get the number of unread notifications the current user has
+pub async fn notification_count_request(
+    req: HttpRequest,
+    data: web::Data<AppData>,
+) -> impl Responder {
+    // get token user
+    let (_, _, token_user) =
+        crate::pages::base::check_auth_status(req, data.clone(), Some("read")).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // count
+    let res = data
+        .db
+        .get_unread_notification_count(token_user.user.username)
+        .await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[post("/api/v1/notifications")]
+/// Mark the current user's notifications as read
+pub async fn mark_notifications_request(
+    req: HttpRequest,
+    body: Option<web::Json<MarkNotificationsInfo>>,
+    data: web::Data<AppData>,
+) -> impl Responder {
+    // get token user
+    let (_, _, token_user) =
+        crate::pages::base::check_auth_status(req, data.clone(), Some("notifications:write")).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // mark as read
+    let res = data
+        .db
+        .mark_notifications_read(
+            token_user.user.username,
+            body.map(|b| b.into_inner()).unwrap_or_default().ids,
+        )
+        .await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[get("/api/v1/invites")]
+/// Get the invites created by the current user (or every invite, for staff)
+pub async fn invites_request(
+    req: HttpRequest,
+    data: web::Data<AppData>,
+    info: web::Query<OffsetQueryProps>,
+) -> impl Responder {
+    // get token user
+    let (_, _, token_user) =
+        crate::pages::base::check_auth_status(req, data.clone(), Some("read")).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+    let is_staff = token_user
+        .level
+        .permissions
+        .contains(&String::from("ManageInvites"));
+
+    // get invites
+    let res = data
+        .db
+        .get_invites(
+            if is_staff {
+                Option::None
+            } else {
+                Option::Some(token_user.user.username)
+            },
+            info.offset,
+        )
+        .await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[post("/api/v1/invites")]
+/// Create a new invite
+///
+/// Staff (`ManageInvites`) can create any invite, other users can create up to `user_invites`
+/// single-use invites if their role level allows it.
+pub async fn create_invite_request(
+    req: HttpRequest,
+    body: Option<web::Json<CreateInviteInfo>>,
+    data: web::Data<AppData>,
+) -> impl Responder {
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+    let body = body.map(|b| b.into_inner()).unwrap_or_default();
+
+    let res = if token_user
+        .level
+        .permissions
+        .contains(&String::from("ManageInvites"))
+    {
+        let expires = match body.expires_in {
+            Some(hours) => utility::unix_epoch_timestamp() + (hours as u128 * 60 * 60 * 1000),
+            None => 0,
+        };
+
+        data.db
+            .create_invite(
+                token_user.user.username,
+                body.max_uses.unwrap_or(1),
+                expires,
+            )
+            .await
+    } else {
+        // users can only create a limited number of single-use invites
+        if (data.config.user_invites == 0)
+            | (token_user.level.elevation < data.config.user_invite_elevation)
+        {
+            return HttpResponse::NotAcceptable().body("You can't create invites");
+        }
+
+        let count = data
+            .db
+            .get_user_invite_count(token_user.user.username.clone())
+            .await
+            .payload;
+
+        if count >= data.config.user_invites {
+            return HttpResponse::NotAcceptable().body("You can't create any more invites");
+        }
+
+        data.db.create_invite(token_user.user.username, 1, 0).await
+    };
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[get("/api/v1/invites/{code:.*}")]
+/// Get an invite and the accounts registered with it
+pub async fn invite_request(req: HttpRequest, data: web::Data<AppData>) -> impl Responder {
+    let code: String = req.match_info().get("code").unwrap().to_string();
+
+    // get token user
+    let (_, _, token_user) =
+        crate::pages::base::check_auth_status(req, data.clone(), Some("read")).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // get invite
+    let invite = data.db.get_invite(code.clone()).await;
+
+    let invite = match invite.payload {
+        Some(i) => i,
+        None => {
+            return HttpResponse::NotFound()
+                .append_header(("Content-Type", "application/json"))
+                .body(serde_json::to_string(&invite).unwrap())
+        }
+    };
+
+    if (invite.author != token_user.user.username)
+        && !token_user
+            .level
+            .permissions
+            .contains(&String::from("ManageInvites"))
+    {
+        return HttpResponse::NotAcceptable().body("You do not have permission to do this.");
+    }
+
+    let uses = data
+        .db
+        .get_invite_uses(code)
+        .await
+        .payload
+        .unwrap_or_default();
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(
+            serde_json::to_string(&DefaultReturn {
+                success: true,
+                message: String::from("Invite exists"),
+                payload: Option::Some((invite, uses)),
+            })
+            .unwrap(),
+        )
+}
+
+#[delete("/api/v1/invites/{code:.*}")]
+/// Revoke an invite
+pub async fn revoke_invite_request(req: HttpRequest, data: web::Data<AppData>) -> impl Responder {
+    let code: String = req.match_info().get("code").unwrap().to_string();
+
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // make sure the user created the invite or is staff
+    let invite = data.db.get_invite(code.clone()).await;
+
+    if invite
+        .payload
+        .is_some_and(|i| i.author != token_user.user.username)
+        && !token_user
+            .level
+            .permissions
+            .contains(&String::from("ManageInvites"))
+    {
+        return HttpResponse::NotAcceptable().body("You do not have permission to do this.");
+    }
+
+    // revoke invite
+    let res = data.db.revoke_invite(code).await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[get("/api/v1/auth/sessions")]
+/// Get every session signed in to the current user's account
+pub async fn sessions_request(req: HttpRequest, data: web::Data<AppData>) -> impl Responder {
+    // get token user
+    let (_, token_cookie, token_user) =
+        crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // get sessions
+    let current = data
+        .db
+        .get_session(token_cookie.unwrap().value().to_string())
+        .await
+        .payload
+        .map(|s| s.id);
+
+    let mut res = data.db.get_user_sessions(token_user.user.username).await;
+
+    if let Some(ref mut sessions) = res.payload {
+        for session in sessions {
+            session.current = current.as_ref() == Some(&session.id);
+        }
+    }
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[delete("/api/v1/auth/sessions")]
+/// Sign out every session of the current user except the one making the request
+pub async fn delete_sessions_request(req: HttpRequest, data: web::Data<AppData>) -> impl Responder {
+    // get token user
+    let (_, token_cookie, token_user) =
+        crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // delete sessions
+    let current = data
+        .db
+        .get_session(token_cookie.unwrap().value().to_string())
+        .await
+        .payload
+        .map(|s| s.id);
+
+    let res = data
+        .db
+        .delete_other_sessions(token_user.user.username, current)
+        .await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[delete("/api/v1/auth/sessions/{id:.*}")]
+/// Sign out one of the current user's sessions
+pub async fn delete_session_request(req: HttpRequest, data: web::Data<AppData>) -> impl Responder {
+    let id: String = req.match_info().get("id").unwrap().to_string();
+
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // delete session
+    let res = data
+        .db
+        .delete_user_session(token_user.user.username, id)
+        .await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[post("/api/v1/auth/totp")]
+/// Start enabling two-factor authentication for the current user
+pub async fn begin_totp_request(req: HttpRequest, data: web::Data<AppData>) -> impl Responder {
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    if data.config.secret_key.is_empty() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("Two-factor authentication is not available on this instance");
+    }
+
+    // begin
+    let res = data
+        .db
+        .begin_totp(token_user.user.username.clone(), &data.config.secret_key)
+        .await;
+
+    let payload = res.payload.map(|secret| {
+        let uri =
+            crate::totp::otpauth_uri(&data.config.site_name, &token_user.user.username, &secret);
+
+        TotpEnrollment {
+            qr: crate::totp::qr_svg(&uri),
+            secret,
+            uri,
+        }
+    });
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(
+            serde_json::to_string(&DefaultReturn {
+                success: res.success,
+                message: res.message,
+                payload,
+            })
+            .unwrap(),
+        )
+}
+
+#[post("/api/v1/auth/totp/confirm")]
+/// Finish enabling two-factor authentication for the current user, returning their recovery codes
+pub async fn confirm_totp_request(
+    req: HttpRequest,
+    body: web::Json<TotpCodeInfo>,
+    data: web::Data<AppData>,
+) -> impl Responder {
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // confirm
+    let res = data
+        .db
+        .confirm_totp(
+            token_user.user.username,
+            &data.config.secret_key,
+            body.code.clone(),
+        )
+        .await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[delete("/api/v1/auth/totp")]
+/// Disable two-factor authentication for the current user, this needs a valid code
+pub async fn disable_totp_request(
+    req: HttpRequest,
+    body: web::Json<TotpCodeInfo>,
+    data: web::Data<AppData>,
+) -> impl Responder {
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // check code
+    let check = data
+        .db
+        .check_totp(
+            token_user.user.username.clone(),
+            &data.config.secret_key,
+            body.code.clone(),
+        )
+        .await;
+
+    if !check.success {
+        return HttpResponse::Ok()
+            .append_header(("Content-Type", "application/json"))
+            .body(serde_json::to_string(&check).unwrap());
+    }
+
+    // disable
+    let res = data.db.disable_totp(token_user.user.username).await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[post("/api/v1/auth/password")]
+/// Set the password of the current user
+pub async fn set_password_request(
+    req: HttpRequest,
+    body: web::Json<SetPasswordInfo>,
+    data: web::Data<AppData>,
+) -> impl Responder {
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // changing a password needs the current one
+    if data.db.has_password(token_user.user.username.clone()).await
+        && data
+            .db
+            .get_user_by_password(
+                token_user.user.username.clone(),
+                body.current_password.clone(),
+            )
+            .await
+            .is_err()
+    {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("Current password is incorrect");
+    }
+
+    // set password
+    let res = data
+        .db
+        .set_password(token_user.user.username, body.password.clone())
+        .await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[delete("/api/v1/auth/password")]
+/// Remove the password of the current user
+pub async fn remove_password_request(
+    req: HttpRequest,
+    body: web::Json<RemovePasswordInfo>,
+    data: web::Data<AppData>,
+) -> impl Responder {
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // check password
+    if data
+        .db
+        .get_user_by_password(
+            token_user.user.username.clone(),
+            body.current_password.clone(),
+        )
+        .await
+        .is_err()
+    {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("Current password is incorrect");
+    }
+
+    // remove password
+    let res = data.db.remove_password(token_user.user.username).await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[get("/api/v1/auth/tokens")]
+/// Get the API tokens of the current user
+pub async fn api_tokens_request(req: HttpRequest, data: web::Data<AppData>) -> impl Responder {
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // get tokens
+    let res = data.db.get_user_api_tokens(token_user.user.username).await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[post("/api/v1/auth/tokens")]
+/// Create an API token for the current user, the token is only returned here
+pub async fn create_api_token_request(
+    req: HttpRequest,
+    body: web::Json<CreateApiTokenInfo>,
+    data: web::Data<AppData>,
+) -> impl Responder {
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // create token
+    let expires = match body.expires_in {
+        Some(days) => utility::unix_epoch_timestamp() + days as u128 * 24 * 60 * 60 * 1000,
+        None => 0,
+    };
+
+    let res = data
+        .db
+        .create_api_token(
+            token_user.user.username,
+            body.name.clone(),
+            body.scopes.clone(),
+            expires,
+        )
+        .await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[delete("/api/v1/auth/tokens/{id:.*}")]
+/// Revoke one of the current user's API tokens
+pub async fn delete_api_token_request(
+    req: HttpRequest,
+    data: web::Data<AppData>,
+) -> impl Responder {
+    let id: String = req.match_info().get("id").unwrap().to_string();
+
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // delete token
+    let res = data
+        .db
+        .delete_user_api_token(token_user.user.username, id)
+        .await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
 }
@@ -0,0 +1,616 @@
+//! OAuth 2.0 and OpenID Connect endpoints, see [`crate::oauth`]
+use actix_web::{delete, get, post, route, web, HttpRequest, HttpResponse, Responder};
+use data_encoding::BASE64;
+use serde_json::json;
+
+use crate::db::{self, AppData, DefaultReturn, OAuthAccessToken, OAuthCode};
+use dorsal::utility;
+
+/// The parameters of an authorization request, sent to `/oauth/authorize` as a query (to show
+/// the consent page) or a form (when the user answers it)
+#[derive(Default, serde::Deserialize)]
+pub struct AuthorizeProps {
+    #[serde(default)]
+    pub response_type: String,
+    #[serde(default)]
+    pub client_id: String,
+    #[serde(default)]
+    pub redirect_uri: String,
+    #[serde(default)]
+    pub scope: String,
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub nonce: String,
+    #[serde(default)]
+    pub code_challenge: String,
+    #[serde(default)]
+    pub code_challenge_method: String,
+    /// `allow` or `deny`, only sent from the consent page
+    #[serde(default)]
+    pub decision: String,
+}
+
+#[derive(Default, serde::Deserialize)]
+struct TokenProps {
+    #[serde(default)]
+    grant_type: String,
+    #[serde(default)]
+    code: String,
+    #[serde(default)]
+    redirect_uri: String,
+    #[serde(default)]
+    client_id: String,
+    #[serde(default)]
+    client_secret: String,
+    #[serde(default)]
+    code_verifier: String,
+}
+
+#[derive(serde::Deserialize)]
+struct CreateClientInfo {
+    name: String,
+    redirect_uris: Vec<String>,
+    /// Public clients have no secret and have to use PKCE
+    #[serde(default)]
+    public: bool,
+}
+
+/// Why an authorization request was rejected
+pub enum AuthorizeError {
+    /// The client or redirect URI is invalid, so the user can't be sent back to the client
+    Show(String),
+    /// Sent back to the client's redirect URI as `error` and `error_description`
+    Redirect(&'static str, String),
+}
+
+impl AuthorizeError {
+    /// Build the response for this error
+    pub fn response(self, props: &AuthorizeProps) -> HttpResponse {
+        match self {
+            AuthorizeError::Show(message) => HttpResponse::BadRequest()
+                .append_header(("Content-Type", "text/plain"))
+                .body(message),
+            AuthorizeError::Redirect(error, description) => HttpResponse::Found()
+                .append_header((
+                    "Location",
+                    crate::oauth::redirect_with(
+                        &props.redirect_uri,
+                        &[
+                            ("error", error),
+                            ("error_description", &description),
+                            ("state", &props.state),
+                        ],
+                    ),
+                ))
+                .finish(),
+        }
+    }
+}
+
+/// Check an authorization request, returning the client it's for
+///
+/// # Arguments:
+/// * `data` - the app data
+/// * `props` - the parameters of the request
+pub async fn check_authorize(
+    data: &AppData,
+    props: &AuthorizeProps,
+) -> Result<db::OAuthClient, AuthorizeError> {
+    if !data.config.oauth_enabled() {
+        return Err(AuthorizeError::Show(String::from(
+            "OAuth is not enabled on this instance",
+        )));
+    }
+
+    let client = match data
+        .db
+        .get_oauth_client(props.client_id.clone())
+        .await
+        .payload
+    {
+        Some(c) => c,
+        None => return Err(AuthorizeError::Show(String::from("Unknown client"))),
+    };
+
+    // redirect uris must match exactly
+    if !crate::oauth::redirect_uri_registered(&client.redirect_uris, &props.redirect_uri) {
+        return Err(AuthorizeError::Show(String::from(
+            "This redirect URI is not registered for this client",
+        )));
+    }
+
+    if props.response_type != "code" {
+        return Err(AuthorizeError::Redirect(
+            "unsupported_response_type",
+            String::from("Only the \"code\" response type is supported"),
+        ));
+    }
+
+    if let Some(scope) = props
+        .scope
+        .split_whitespace()
+        .find(|s| !crate::oauth::SCOPES.contains(s))
+    {
+        return Err(AuthorizeError::Redirect(
+            "invalid_scope",
+            format!("Unknown scope \"{scope}\""),
+        ));
+    }
+
+    if !props.code_challenge.is_empty() && (props.code_challenge_method != "S256") {
+        return Err(AuthorizeError::Redirect(
+            "invalid_request",
+            String::from("Only the \"S256\" code challenge method is supported"),
+        ));
+    }
+
+    if client.public && props.code_challenge.is_empty() {
+        return Err(AuthorizeError::Redirect(
+            "invalid_request",
+            String::from("Public clients must use PKCE"),
+        ));
+    }
+
+    Ok(client)
+}
+
+/// An error response from the token endpoint
+fn token_error(error: &str, description: &str) -> HttpResponse {
+    let mut res = if error == "invalid_client" {
+        HttpResponse::Unauthorized()
+    } else {
+        HttpResponse::BadRequest()
+    };
+
+    res.append_header(("Content-Type", "application/json"))
+        .append_header(("Cache-Control", "no-store"))
+        .body(
+            json!({
+                "error": error,
+                "error_description": description,
+            })
+            .to_string(),
+        )
+}
+
+/// Get the client credentials sent with `Authorization: Basic`
+fn basic_credentials(req: &HttpRequest) -> Option<(String, String)> {
+    let header = req.headers().get("Authorization")?.to_str().ok()?;
+    let decoded = BASE64
+        .decode(header.strip_prefix("Basic ")?.trim().as_bytes())
+        .ok()?;
+
+    let (id, secret) = std::str::from_utf8(&decoded).ok()?.split_once(':')?;
+    Some((id.to_string(), secret.to_string()))
+}
+
+#[get("/.well-known/openid-configuration")]
+/// OpenID Connect discovery document
+pub async fn discovery_request(data: web::Data<AppData>) -> impl Responder {
+    if !data.config.oauth_enabled() {
+        return HttpResponse::NotFound().body("OAuth is not enabled on this instance");
+    }
+
+    let root = &data.config.public_url;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(
+            json!({
+                "issuer": root,
+                "authorization_endpoint": format!("{root}/oauth/authorize"),
+                "token_endpoint": format!("{root}/oauth/token"),
+                "userinfo_endpoint": format!("{root}/oauth/userinfo"),
+                "jwks_uri": format!("{root}/oauth/jwks"),
+                "scopes_supported": crate::oauth::SCOPES,
+                "response_types_supported": ["code"],
+                "grant_types_supported": ["authorization_code"],
+                "subject_types_supported": ["public"],
+                "id_token_signing_alg_values_supported": ["RS256"],
+                "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post", "none"],
+                "code_challenge_methods_supported": ["S256"],
+                "claims_supported": ["iss", "sub", "aud", "exp", "iat", "auth_time", "nonce", "preferred_username", "picture", "profile"],
+            })
+            .to_string(),
+        )
+}
+
+#[get("/oauth/jwks")]
+/// The public keys ID tokens are signed with
+pub async fn jwks_request(data: web::Data<AppData>) -> impl Responder {
+    if !data.config.oauth_enabled() {
+        return HttpResponse::NotFound().body("OAuth is not enabled on this instance");
+    }
+
+    let keys = data.db.get_oauth_keys(&data.config.secret_key).await;
+
+    if !keys.success {
+        return HttpResponse::InternalServerError().body(keys.message);
+    }
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(
+            json!({
+                "keys": keys
+                    .payload
+                    .iter()
+                    .filter_map(|(kid, pem)| crate::oauth::jwk(kid, pem))
+                    .collect::<Vec<serde_json::Value>>(),
+            })
+            .to_string(),
+        )
+}
+
+#[post("/oauth/authorize")]
+/// Answer an authorization request from the consent page
+pub async fn authorize_request(
+    req: HttpRequest,
+    body: web::Form<AuthorizeProps>,
+    data: web::Data<AppData>,
+) -> impl Responder {
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    // check request
+    let client = match check_authorize(&data, &body).await {
+        Ok(c) => c,
+        Err(e) => return e.response(&body),
+    };
+
+    if body.decision != "allow" {
+        return AuthorizeError::Redirect(
+            "access_denied",
+            String::from("The user denied the request"),
+        )
+        .response(&body);
+    }
+
+    // the user's subject is created the first time they authorize a client
+    let subject = data
+        .db
+        .get_oauth_subject(token_user.user.username.clone())
+        .await;
+
+    if !subject.success {
+        return AuthorizeError::Redirect("server_error", subject.message).response(&body);
+    }
+
+    // create code
+    let res = data
+        .db
+        .create_oauth_code(OAuthCode {
+            client: client.id,
+            username: token_user.user.username,
+            redirect_uri: body.redirect_uri.clone(),
+            scope: body.scope.clone(),
+            nonce: body.nonce.clone(),
+            code_challenge: body.code_challenge.clone(),
+            auth_time: utility::unix_epoch_timestamp(),
+        })
+        .await;
+
+    let code = match res.payload {
+        Some(c) => c,
+        None => {
+            return AuthorizeError::Redirect("server_error", res.message).response(&body);
+        }
+    };
+
+    // return
+    HttpResponse::Found()
+        .append_header((
+            "Location",
+            crate::oauth::redirect_with(
+                &body.redirect_uri,
+                &[("code", &code), ("state", &body.state)],
+            ),
+        ))
+        .finish()
+}
+
+#[post("/oauth/token")]
+/// Exchange an authorization code for an access token and ID token
+pub async fn token_request(
+    req: HttpRequest,
+    body: web::Form<TokenProps>,
+    data: web::Data<AppData>,
+) -> impl Responder {
+    if !data.config.oauth_enabled() {
+        return token_error("invalid_request", "OAuth is not enabled on this instance");
+    }
+
+    if body.grant_type != "authorization_code" {
+        return token_error(
+            "unsupported_grant_type",
+            "Only the \"authorization_code\" grant type is supported",
+        );
+    }
+
+    // authenticate client
+    let (client_id, client_secret) = match basic_credentials(&req) {
+        Some(c) => c,
+        None => (body.client_id.clone(), body.client_secret.clone()),
+    };
+
+    let client = match data.db.get_oauth_client(client_id.clone()).await.payload {
+        Some(c) => c,
+        None => return token_error("invalid_client", "Unknown client"),
+    };
+
+    if !client.public
+        && !data
+            .db
+            .check_oauth_client_secret(client_id, client_secret)
+            .await
+    {
+        return token_error("invalid_client", "Invalid client secret");
+    }
+
+    // check code
+    let code = match data.db.take_oauth_code(body.code.clone()).await.payload {
+        Some(c) => c,
+        None => return token_error("invalid_grant", "Invalid or expired code"),
+    };
+
+    if (code.client != client.id) | (code.redirect_uri != body.redirect_uri) {
+        return token_error("invalid_grant", "Invalid or expired code");
+    }
+
+    if !code.code_challenge.is_empty()
+        && !crate::oauth::verify_pkce(&body.code_verifier, &code.code_challenge)
+    {
+        return token_error("invalid_grant", "Invalid code verifier");
+    }
+
+    // create tokens
+    let scopes: Vec<&str> = code.scope.split_whitespace().collect();
+
+    let access_token = match data
+        .db
+        .create_oauth_access_token(OAuthAccessToken {
+            client: client.id.clone(),
+            username: code.username.clone(),
+            scope: code.scope.clone(),
+        })
+        .await
+        .payload
+    {
+        Some(t) => t,
+        None => return token_error("server_error", "Failed to create access token"),
+    };
+
+    let mut res = json!({
+        "access_token": access_token,
+        "token_type": "Bearer",
+        "expires_in": crate::oauth::TOKEN_MAX_AGE,
+        "scope": code.scope,
+    });
+
+    if scopes.contains(&"openid") {
+        let subject = match data
+            .db
+            .get_oauth_subject(code.username.clone())
+            .await
+            .payload
+        {
+            Some(s) => s,
+            None => return token_error("server_error", "Failed to get subject"),
+        };
+
+        let now = (utility::unix_epoch_timestamp() / 1000) as u64;
+        let mut claims = json!({
+            "iss": data.config.public_url,
+            "sub": subject,
+            "aud": client.id,
+            "iat": now,
+            "exp": now + crate::oauth::TOKEN_MAX_AGE,
+            "auth_time": (code.auth_time / 1000) as u64,
+        });
+
+        if !code.nonce.is_empty() {
+            claims["nonce"] = json!(code.nonce);
+        }
+
+        if scopes.contains(&"profile") {
+            claims["preferred_username"] = json!(code.username);
+        }
+
+        let keys = data.db.get_oauth_keys(&data.config.secret_key).await;
+        let id_token = keys
+            .payload
+            .first()
+            .and_then(|(kid, pem)| crate::oauth::sign_jwt(kid, pem, &claims));
+
+        match id_token {
+            Some(t) => res["id_token"] = json!(t),
+            None => return token_error("server_error", "Failed to sign ID token"),
+        }
+    }
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .append_header(("Cache-Control", "no-store"))
+        .body(res.to_string())
+}
+
+#[route("/oauth/userinfo", method = "GET", method = "POST")]
+/// Get the claims about the user an access token belongs to
+pub async fn userinfo_request(req: HttpRequest, data: web::Data<AppData>) -> impl Responder {
+    let token = match crate::pages::base::bearer_token(&req) {
+        Some(t) => data.db.get_oauth_access_token(t).await.payload,
+        None => None,
+    };
+
+    let token = match token {
+        Some(t) if t.scope.split_whitespace().any(|s| s == "openid") => t,
+        _ => {
+            return HttpResponse::Unauthorized()
+                .append_header(("WWW-Authenticate", "Bearer error=\"invalid_token\""))
+                .finish();
+        }
+    };
+
+    let subject = match data
+        .db
+        .get_oauth_subject(token.username.clone())
+        .await
+        .payload
+    {
+        Some(s) => s,
+        None => return HttpResponse::InternalServerError().finish(),
+    };
+
+    let root = &data.config.public_url;
+    let mut claims = json!({
+        "sub": subject,
+    });
+
+    if token.scope.split_whitespace().any(|s| s == "profile") {
+        claims["preferred_username"] = json!(token.username);
+        claims["profile"] = json!(format!("{root}/{}", token.username));
+        claims["picture"] = json!(format!(
+            "{root}/api/v1/auth/users/{}/avatar",
+            token.username
+        ));
+    }
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(claims.to_string())
+}
+
+#[get("/api/v1/oauth/clients")]
+/// Get every OAuth client (`ManageOAuthClients` only)
+pub async fn clients_request(req: HttpRequest, data: web::Data<AppData>) -> impl Responder {
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    if !token_user
+        .level
+        .permissions
+        .contains(&String::from("ManageOAuthClients"))
+    {
+        return HttpResponse::NotFound()
+            .body("You do not have permission to manage OAuth clients.");
+    }
+
+    // get clients
+    let res = data.db.get_oauth_clients(Option::None).await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
+
+#[post("/api/v1/oauth/clients")]
+/// Register an OAuth client (`ManageOAuthClients` only), the secret is only returned here
+pub async fn create_client_request(
+    req: HttpRequest,
+    body: web::Json<CreateClientInfo>,
+    data: web::Data<AppData>,
+) -> impl Responder {
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    if !token_user
+        .level
+        .permissions
+        .contains(&String::from("ManageOAuthClients"))
+    {
+        return HttpResponse::NotFound()
+            .body("You do not have permission to manage OAuth clients.");
+    }
+
+    // create client
+    let res = data
+        .db
+        .create_oauth_client(
+            token_user.user.username,
+            body.name.clone(),
+            body.redirect_uris.clone(),
+            body.public,
+        )
+        .await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(
+            serde_json::to_string(&DefaultReturn {
+                success: res.success,
+                message: res.message,
+                payload: res.payload.map(|(id, secret)| {
+                    json!({
+                        "client_id": id,
+                        "client_secret": secret,
+                    })
+                }),
+            })
+            .unwrap(),
+        )
+}
+
+#[delete("/api/v1/oauth/clients/{id:.*}")]
+/// Delete an OAuth client (`ManageOAuthClients` only)
+pub async fn delete_client_request(req: HttpRequest, data: web::Data<AppData>) -> impl Responder {
+    let id: String = req.match_info().get("id").unwrap().to_string();
+
+    // get token user
+    let (_, _, token_user) = crate::pages::base::check_auth_status(req, data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable()
+            .append_header(("Content-Type", "text/plain"))
+            .body("An account is required to do this");
+    }
+
+    let token_user = token_user.unwrap().ok().unwrap();
+
+    if !token_user
+        .level
+        .permissions
+        .contains(&String::from("ManageOAuthClients"))
+    {
+        return HttpResponse::NotFound()
+            .body("You do not have permission to manage OAuth clients.");
+    }
+
+    // delete client
+    let res = data.db.delete_oauth_client(id).await;
+
+    // return
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(serde_json::to_string(&res).unwrap())
+}
@@ -0,0 +1,255 @@
+//! Admin commands that run against the database without starting the server
+use crate::config;
+use crate::db::Database;
+
+/// Usage shown for unknown commands
+const USAGE: &str = "Usage: guppy [COMMAND]
+
+Commands:
+    migrate                         Apply pending schema migrations
+    repair-counts                   Recompute cached follower and following counts
+    user create <username>          Create a user and print their ID
+    user ban <username>             Ban a user
+    user unban <username>           Give a banned user the \"member\" role again
+    user set-role <username> <role> Set the role of a user
+    user delete <username>          Delete a user and everything they've created
+    user rotate-id <username>       Give a user a new ID and revoke their sessions and tokens
+    user disable-totp <username>    Disable two-factor authentication for a user
+    post delete <id>                Delete a post
+    invite create                   Create an invite and print its code
+        --uses <n>                  How many accounts can register with it (default 1)
+        --expires-in <hours>        When it stops working (default never)
+        --author <username>         Who it's shown as created by
+    invite revoke <code>            Revoke an invite
+    oauth create-client <name> <redirect-uris>
+                                    Register an OAuth client and print its credentials,
+                                    redirect URIs are separated by commas
+        --type <type>               \"confidential\" (default) or \"public\" (no secret, PKCE only)
+        --owner <username>          Who it's shown as registered by
+    oauth delete-client <id>        Delete an OAuth client
+    cache flush                     Remove everything guppy stores in the cache
+
+Running guppy without a command starts the server.";
+
+/// Print an error and exit with a non-zero status
+fn fail(message: &str) -> ! {
+    eprintln!("error: {message}");
+    std::process::exit(1);
+}
+
+/// Get the argument at `index`, or exit with the usage if it's missing
+fn required(args: &[String], index: usize) -> &String {
+    match args.get(index) {
+        Some(arg) => arg,
+        None => {
+            eprintln!("{USAGE}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Run the command in `args` (the positional arguments, see [`crate::config::get_positional_arguments`])
+///
+/// Exits the process with a non-zero status if the command fails.
+///
+/// # Arguments:
+/// * `db` - the [`Database`], already initialized
+/// * `args` - the positional arguments, the first one is the command
+/// * `flags` - every argument, for named arguments like `--uses`
+pub async fn run(db: &Database, args: &[String], flags: &[String]) {
+    let command = args
+        .iter()
+        .map(|x| x.as_str())
+        .take(2)
+        .collect::<Vec<&str>>();
+
+    match command[..] {
+        ["repair-counts", ..] => {
+            let res = db.repair_follow_counts().await;
+
+            if !res.success {
+                fail(&format!("Failed to repair follow counts: {}", res.message));
+            }
+
+            println!("Repaired {} follow counters", res.payload);
+        }
+        // users
+        ["user", "create"] => {
+            let username = required(args, 2);
+            let res = db.create_user(username.to_owned()).await;
+
+            if !res.success {
+                fail(&res.message);
+            }
+
+            // the unhashed ID is only ever shown here
+            println!("Created user {username}, their ID is: {}", res.message);
+        }
+        ["user", "ban"] => {
+            let res = db.ban_user_by_name(required(args, 2).to_owned()).await;
+
+            if !res.success {
+                fail(&res.message);
+            }
+
+            println!("{}", res.message);
+        }
+        ["user", "unban"] => {
+            let res = db
+                .set_user_role(required(args, 2).to_owned(), String::from("member"))
+                .await;
+
+            if !res.success {
+                fail(&res.message);
+            }
+
+            println!("{}", res.message);
+        }
+        ["user", "set-role"] => {
+            let res = db
+                .set_user_role(required(args, 2).to_owned(), required(args, 3).to_owned())
+                .await;
+
+            if !res.success {
+                fail(&res.message);
+            }
+
+            println!("{}", res.message);
+        }
+        ["user", "delete"] => {
+            let res = db.delete_user_by_name(required(args, 2).to_owned()).await;
+
+            if !res.success {
+                fail(&res.message);
+            }
+
+            println!("{}", res.message);
+        }
+        ["user", "rotate-id"] => {
+            let res = db.rotate_user_id(required(args, 2).to_owned()).await;
+
+            if !res.success {
+                fail(&res.message);
+            }
+
+            // the unhashed ID is only ever shown here
+            println!("{}", res.payload.unwrap());
+        }
+        ["user", "disable-totp"] => {
+            let res = db.disable_totp(required(args, 2).to_owned()).await;
+
+            if !res.success {
+                fail(&res.message);
+            }
+
+            println!("{}", res.message);
+        }
+        // posts
+        ["post", "delete"] => {
+            let post = db.get_post_by_id(required(args, 2).to_owned()).await;
+
+            if !post.success {
+                fail(&post.message);
+            }
+
+            let res = db.remove_activity_post(post.payload.unwrap()).await;
+
+            if !res.success {
+                fail(&res.message);
+            }
+
+            println!("{}", res.message);
+        }
+        // invites
+        ["invite", "create"] => {
+            let uses = match config::get_named_argument(flags, "uses") {
+                Some(v) => v
+                    .parse::<usize>()
+                    .unwrap_or_else(|_| fail(&format!("Invalid number of uses \"{v}\""))),
+                None => 1,
+            };
+
+            let expires = match config::get_named_argument(flags, "expires-in") {
+                Some(v) => {
+                    let hours = v
+                        .parse::<u128>()
+                        .unwrap_or_else(|_| fail(&format!("Invalid number of hours \"{v}\"")));
+
+                    dorsal::utility::unix_epoch_timestamp() + hours * 60 * 60 * 1000
+                }
+                None => 0,
+            };
+
+            let author = config::get_named_argument(flags, "author").unwrap_or_default();
+            let res = db.create_invite(author, uses, expires).await;
+
+            if !res.success {
+                fail(&res.message);
+            }
+
+            println!("{}", res.message);
+        }
+        ["invite", "revoke"] => {
+            let res = db.revoke_invite(required(args, 2).to_owned()).await;
+
+            if !res.success {
+                fail(&res.message);
+            }
+
+            println!("{}", res.message);
+        }
+        // oauth
+        ["oauth", "create-client"] => {
+            let public = match config::get_named_argument(flags, "type").as_deref() {
+                Some("public") => true,
+                Some("confidential") | None => false,
+                Some(v) => fail(&format!(
+                    "Invalid client type \"{v}\": expected \"confidential\" or \"public\""
+                )),
+            };
+
+            let res = db
+                .create_oauth_client(
+                    config::get_named_argument(flags, "owner").unwrap_or_default(),
+                    required(args, 2).to_owned(),
+                    required(args, 3)
+                        .split(',')
+                        .map(|x| x.trim().to_string())
+                        .filter(|x| !x.is_empty())
+                        .collect(),
+                    public,
+                )
+                .await;
+
+            if !res.success {
+                fail(&res.message);
+            }
+
+            // the secret is only ever shown here
+            let (id, secret) = res.payload.unwrap();
+            println!("client_id: {id}");
+
+            if !secret.is_empty() {
+                println!("client_secret: {secret}");
+            }
+        }
+        ["oauth", "delete-client"] => {
+            let res = db.delete_oauth_client(required(args, 2).to_owned()).await;
+
+            if !res.success {
+                fail(&res.message);
+            }
+
+            println!("{}", res.message);
+        }
+        // cache
+        ["cache", "flush"] => {
+            db.flush_cache().await;
+            println!("Cache flushed!");
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            std::process::exit(1);
+        }
+    }
+}
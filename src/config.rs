@@ -1,4 +1,5 @@
-use std::{env, ops::Index};
+use std::collections::HashMap;
+use std::env;
 
 #[allow(dead_code)]
 pub fn collect_arguments() -> Vec<String> {
@@ -6,7 +7,7 @@ pub fn collect_arguments() -> Vec<String> {
 }
 
 #[allow(dead_code)]
-pub fn get_named_argument(args: &Vec<String>, name: &str) -> Option<String> {
+pub fn get_named_argument(args: &[String], name: &str) -> Option<String> {
     for (i, v) in args.iter().enumerate() {
         // if name does not match, continue
         if v != &format!("--{}", name) {
@@ -14,7 +15,7 @@ pub fn get_named_argument(args: &Vec<String>, name: &str) -> Option<String> {
         };
 
         // return value
-        let val: &String = args.index(i + 1);
+        let val = args.get(i + 1)?;
 
         // ...make sure val exists (return None if it doesn't!)
         if val.is_empty() {
@@ -36,3 +37,426 @@ pub fn get_var(var: &str) -> Option<String> {
         Option::None
     }
 }
+
+/// Get every argument that isn't the program name, a `--name` flag or a flag's value
+pub fn get_positional_arguments(args: &[String]) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut skip_next: bool = false;
+
+    for arg in args.iter().skip(1) {
+        if skip_next {
+            skip_next = false;
+            continue;
+        }
+
+        // every named argument takes a value
+        if arg.starts_with("--") {
+            skip_next = true;
+            continue;
+        }
+
+        out.push(arg.to_owned());
+    }
+
+    out
+}
+
+/// Database connection settings, the `[database]` table of `guppy.toml`
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    /// `sqlite`, `postgres` or `mysql` (`--db-type`)
+    #[serde(rename = "type")]
+    pub _type: Option<String>,
+    /// `DB_HOST`
+    pub host: Option<String>,
+    /// `DB_USER`
+    pub user: Option<String>,
+    /// `DB_PASS`
+    pub pass: Option<String>,
+    /// `DB_NAME`
+    pub name: Option<String>,
+}
+
+/// A token bucket: requests take a token from the bucket, which holds up to `capacity` tokens
+/// and gets `per_minute` new ones every minute
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    /// How many requests can be made at once, `0` for no limit
+    pub capacity: u64,
+    /// How many requests can be made every minute once the bucket is empty
+    pub per_minute: u64,
+}
+
+/// The rate limits of each route group, the `[rate_limits]` table of `guppy.toml`
+///
+/// See [`crate::ratelimit::route_group`] for the routes in each group.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitsConfig {
+    /// Creating, editing and deleting posts
+    pub activity: RateLimit,
+    /// Favoriting posts
+    pub favorites: RateLimit,
+    /// Following users
+    pub follows: RateLimit,
+    /// Every other `POST`, `PUT` and `DELETE` request
+    pub writes: RateLimit,
+}
+
+impl Default for RateLimitsConfig {
+    fn default() -> Self {
+        Self {
+            activity: RateLimit {
+                capacity: 10,
+                per_minute: 5,
+            },
+            favorites: RateLimit {
+                capacity: 30,
+                per_minute: 30,
+            },
+            follows: RateLimit {
+                capacity: 20,
+                per_minute: 10,
+            },
+            writes: RateLimit {
+                capacity: 60,
+                per_minute: 60,
+            },
+        }
+    }
+}
+
+impl RateLimitsConfig {
+    /// Get the limit of a route group, `None` if the group doesn't exist
+    pub fn get(&self, group: &str) -> Option<RateLimit> {
+        match group {
+            "activity" => Some(self.activity),
+            "favorites" => Some(self.favorites),
+            "follows" => Some(self.follows),
+            "writes" => Some(self.writes),
+            _ => None,
+        }
+    }
+}
+
+/// Server configuration, loaded once from `guppy.toml` when the server starts
+///
+/// Settings can be overridden by the environment variable (or command line flag) documented on
+/// their field.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The port the server listens on (`--port`)
+    pub port: u16,
+    /// The directory served at `/static` (`--static-dir`)
+    pub static_dir: String,
+    pub database: DatabaseConfig,
+    /// If new accounts can't be registered (`REGISTRATION_DISABLED`)
+    pub registration_disabled: bool,
+    /// If an invite is needed to register (`INVITES_REQUIRED`)
+    pub invites_required: bool,
+    /// Codes that can be used to register any number of times, invites are required if this
+    /// isn't empty (`INVITE_CODES`)
+    pub invite_codes: Vec<String>,
+    /// How many invites users without the `ManageInvites` permission can create
+    pub user_invites: usize,
+    /// The lowest role level elevation that can create [`user_invites`](Config::user_invites)
+    pub user_invite_elevation: i32,
+    /// The page linked as the instance's info page (`INFO`)
+    pub info: String,
+    /// HTML added to the end of every page (`BODY_EMBED`)
+    pub body_embed: String,
+    /// The root URL of the bundlrs instance used for user pages (`BUNDLRS_ROOT`)
+    pub bundlrs_root: String,
+    /// The root URL of the deducktive instance (`DEDUCKTIVE_ROOT`)
+    pub deducktive_root: String,
+    /// The name of the instance (`SITE_NAME`)
+    pub site_name: String,
+    /// The key used to encrypt two-factor authentication secrets, two-factor authentication
+    /// can't be enabled if this isn't set (`SECRET_KEY`)
+    pub secret_key: String,
+    /// The URL this instance is served at, used as the OpenID Connect issuer. Guppy only acts
+    /// as an OAuth provider if this and [`secret_key`](Config::secret_key) are set (`PUBLIC_URL`)
+    pub public_url: String,
+    /// If the client's address is read from the `Forwarded` or `X-Forwarded-For` header, only
+    /// set this when Guppy is behind a reverse proxy that sets it (`TRUST_PROXY`)
+    pub trust_proxy: bool,
+    /// If pages accept an account ID in the session cookie (stored by versions before sessions)
+    /// and replace it with a session. Support for these cookies will be removed after
+    /// 2027-04-01, turn this off once users have signed in again (`LEGACY_ID_COOKIES`, `true`
+    /// or `false`)
+    pub legacy_id_cookies: bool,
+    /// Failed sign ins from one IP address before it's locked out, `0` to disable
+    pub login_ip_attempts: u64,
+    /// Failed sign ins to one account before it's locked out, `0` to disable
+    pub login_user_attempts: u64,
+    /// How long (in seconds) failed sign ins are remembered
+    pub login_window: u64,
+    /// How long (in seconds) the first lockout lasts, every failure after that doubles it
+    pub login_lockout: u64,
+    /// The longest (in seconds) a lockout can last
+    pub login_max_lockout: u64,
+    /// The rate limits of signed in users and (per IP address) everyone else
+    pub rate_limits: RateLimitsConfig,
+    /// Rate limits for users with a role, replacing [`rate_limits`](Config::rate_limits) for
+    /// the route groups they set (the `[role_rate_limits.<role>]` tables of `guppy.toml`)
+    pub role_rate_limits: HashMap<String, HashMap<String, RateLimit>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            port: 8080,
+            static_dir: String::from("./static"),
+            database: DatabaseConfig::default(),
+            registration_disabled: false,
+            invites_required: false,
+            invite_codes: Vec::new(),
+            user_invites: 0,
+            user_invite_elevation: 0,
+            info: String::from("/pub/info"),
+            body_embed: String::new(),
+            bundlrs_root: String::new(),
+            deducktive_root: String::new(),
+            site_name: String::from("Guppy"),
+            secret_key: String::new(),
+            public_url: String::new(),
+            trust_proxy: false,
+            legacy_id_cookies: true,
+            login_ip_attempts: 20,
+            login_user_attempts: 5,
+            login_window: 60 * 15,
+            login_lockout: 60,
+            login_max_lockout: 60 * 60,
+            rate_limits: RateLimitsConfig::default(),
+            role_rate_limits: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the configuration file (`--config`, defaults to `guppy.toml`), apply environment
+    /// variable and command line overrides, then validate the result
+    ///
+    /// The default file is optional, a file given with `--config` must exist.
+    ///
+    /// # Arguments:
+    /// * `args` - the command line arguments, from [`collect_arguments`]
+    pub fn load(args: &[String]) -> std::result::Result<Config, String> {
+        let path_flag = get_named_argument(args, "config");
+        let path = path_flag.clone().unwrap_or(String::from("guppy.toml"));
+
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str::<Config>(&content)
+                .map_err(|e| format!("Invalid configuration file \"{path}\": {e}"))?,
+            Err(e) => {
+                if path_flag.is_some() {
+                    return Err(format!("Failed to read configuration file \"{path}\": {e}"));
+                }
+
+                Config::default()
+            }
+        };
+
+        // environment
+        if let Some(v) = get_var("DB_HOST") {
+            config.database.host = Some(v);
+        }
+
+        if let Some(v) = get_var("DB_USER") {
+            config.database.user = Some(v);
+        }
+
+        if let Some(v) = get_var("DB_PASS") {
+            config.database.pass = Some(v);
+        }
+
+        if let Some(v) = get_var("DB_NAME") {
+            config.database.name = Some(v);
+        }
+
+        if get_var("REGISTRATION_DISABLED").is_some() {
+            config.registration_disabled = true;
+        }
+
+        if get_var("INVITES_REQUIRED").is_some() {
+            config.invites_required = true;
+        }
+
+        if let Some(v) = get_var("INVITE_CODES") {
+            config.invite_codes = v.split(',').map(|x| x.to_string()).collect();
+        }
+
+        if let Some(v) = get_var("INFO") {
+            config.info = v;
+        }
+
+        if let Some(v) = get_var("BODY_EMBED") {
+            config.body_embed = v;
+        }
+
+        if let Some(v) = get_var("BUNDLRS_ROOT") {
+            config.bundlrs_root = v;
+        }
+
+        if let Some(v) = get_var("DEDUCKTIVE_ROOT") {
+            config.deducktive_root = v;
+        }
+
+        if let Some(v) = get_var("SITE_NAME") {
+            config.site_name = v;
+        }
+
+        if let Some(v) = get_var("SECRET_KEY") {
+            config.secret_key = v;
+        }
+
+        if let Some(v) = get_var("PUBLIC_URL") {
+            config.public_url = v;
+        }
+
+        if get_var("TRUST_PROXY").is_some() {
+            config.trust_proxy = true;
+        }
+
+        if let Some(v) = get_var("LEGACY_ID_COOKIES") {
+            config.legacy_id_cookies = v.parse::<bool>().map_err(|_| {
+                format!("Invalid LEGACY_ID_COOKIES \"{v}\": expected true or false")
+            })?;
+        }
+
+        // command line
+        if let Some(v) = get_named_argument(args, "port") {
+            config.port = v
+                .parse::<u16>()
+                .map_err(|_| format!("Invalid port \"{v}\": expected a number from 0 to 65535"))?;
+        }
+
+        if let Some(v) = get_named_argument(args, "static-dir") {
+            config.static_dir = v;
+        }
+
+        if let Some(v) = get_named_argument(args, "db-type") {
+            config.database._type = Some(v);
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// If an invite code is needed to register
+    pub fn requires_invite(&self) -> bool {
+        self.invites_required | !self.invite_codes.is_empty()
+    }
+
+    /// If Guppy can act as an OAuth provider
+    pub fn oauth_enabled(&self) -> bool {
+        !self.public_url.is_empty() & !self.secret_key.is_empty()
+    }
+
+    /// Get the rate limit of a route group
+    ///
+    /// # Arguments:
+    /// * `group` - the route group, see [`crate::ratelimit::route_group`]
+    /// * `role` - the role of the user making the request, `None` for anonymous requests
+    pub fn rate_limit(&self, group: &str, role: Option<&str>) -> Option<RateLimit> {
+        role.and_then(|r| self.role_rate_limits.get(r))
+            .and_then(|limits| limits.get(group).copied())
+            .or_else(|| self.rate_limits.get(group))
+    }
+
+    /// Make sure every setting is usable, cleaning up values where that's unambiguous
+    fn validate(&mut self) -> std::result::Result<(), String> {
+        let db_type = self.database._type.as_deref().unwrap_or("sqlite");
+
+        if !["sqlite", "postgres", "mysql"].contains(&db_type) {
+            return Err(format!(
+                "Invalid database type \"{db_type}\": expected \"sqlite\", \"postgres\" or \"mysql\""
+            ));
+        }
+
+        if db_type != "sqlite" {
+            for (setting, value) in [
+                ("user (DB_USER)", &self.database.user),
+                ("pass (DB_PASS)", &self.database.pass),
+                ("name (DB_NAME)", &self.database.name),
+            ] {
+                if value.is_none() {
+                    return Err(format!(
+                        "Missing required database setting for {db_type}: {setting}"
+                    ));
+                }
+            }
+        }
+
+        if self.site_name.trim().is_empty() {
+            return Err(String::from("Invalid site name: it can't be empty"));
+        }
+
+        if !self.secret_key.is_empty() && (self.secret_key.len() < 16) {
+            return Err(String::from(
+                "Invalid secret key: it must be at least 16 characters",
+            ));
+        }
+
+        if !self.public_url.is_empty() {
+            if !(self.public_url.starts_with("https://") | self.public_url.starts_with("http://")) {
+                return Err(format!(
+                    "Invalid public URL \"{}\": it must start with \"https://\" or \"http://\"",
+                    self.public_url
+                ));
+            }
+
+            self.public_url = self.public_url.trim_end_matches('/').to_string();
+        }
+
+        if (self.login_window == 0) | (self.login_lockout == 0) {
+            return Err(String::from(
+                "Invalid login limits: login_window and login_lockout can't be 0",
+            ));
+        }
+
+        if self.login_lockout > self.login_max_lockout {
+            return Err(String::from(
+                "Invalid login limits: login_lockout can't be longer than login_max_lockout",
+            ));
+        }
+
+        for group in ["activity", "favorites", "follows", "writes"] {
+            let limit = self.rate_limits.get(group).unwrap();
+
+            if (limit.capacity > 0) & (limit.per_minute == 0) {
+                return Err(format!(
+                    "Invalid rate limit for \"{group}\": per_minute can't be 0"
+                ));
+            }
+        }
+
+        for (role, limits) in &self.role_rate_limits {
+            for (group, limit) in limits {
+                if self.rate_limits.get(group).is_none() {
+                    return Err(format!(
+                        "Invalid rate limits for role \"{role}\": unknown route group \"{group}\""
+                    ));
+                }
+
+                if (limit.capacity > 0) & (limit.per_minute == 0) {
+                    return Err(format!(
+                        "Invalid rate limit for role \"{role}\" and \"{group}\": per_minute can't be 0"
+                    ));
+                }
+            }
+        }
+
+        self.invite_codes = self
+            .invite_codes
+            .iter()
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+//! Encryption of secrets stored in the database, and comparisons that don't leak timing
+//!
+//! Secrets are encrypted with AES-256-GCM, using a key derived from the `secret_key` setting.
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use data_encoding::BASE64;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+/// Compare two byte strings without returning early
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Derive the encryption key from the `secret_key` setting
+fn cipher(key: &str) -> Aes256Gcm {
+    Aes256Gcm::new_from_slice(&Sha256::digest(key.as_bytes())).unwrap()
+}
+
+/// Encrypt a secret. Returns the nonce and ciphertext, encoded as base64
+///
+/// # Arguments:
+/// * `key` - the `secret_key` setting
+/// * `plaintext` - the secret to encrypt
+pub fn encrypt(key: &str, plaintext: &str) -> String {
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut out = nonce.to_vec();
+    out.extend(
+        cipher(key)
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+            .unwrap(),
+    );
+
+    BASE64.encode(&out)
+}
+
+/// Decrypt a secret created by [`encrypt`], `None` if the key is wrong or the input is invalid
+///
+/// # Arguments:
+/// * `key` - the `secret_key` setting
+/// * `ciphertext` - the output of [`encrypt`]
+pub fn decrypt(key: &str, ciphertext: &str) -> Option<String> {
+    let bytes = BASE64.decode(ciphertext.as_bytes()).ok()?;
+
+    if bytes.len() < 12 {
+        return None;
+    }
+
+    let (nonce, ciphertext) = bytes.split_at(12);
+    let plaintext = cipher(key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .ok()?;
+
+    String::from_utf8(plaintext).ok()
+}
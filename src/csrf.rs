@@ -0,0 +1,168 @@
+//! CSRF protection for requests authenticated with the session cookie
+//!
+//! Guppy uses double-submit tokens: every browser gets a random token in the `__Host-Csrf`
+//! cookie, which only scripts on this site can read. Cookie-authenticated `POST`, `PUT`, `PATCH`
+//! and `DELETE` requests have to send it back in the `X-CSRF-Token` header (or a `csrf_token`
+//! form field), and can't come from another origin.
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, CONTENT_TYPE, ORIGIN, REFERER, SET_COOKIE};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage, HttpRequest, HttpResponse};
+
+use crate::db::{AppData, DefaultReturn};
+
+/// The name of the cookie holding the token
+pub const COOKIE: &str = "__Host-Csrf";
+
+/// The header scripts send the token in
+pub const HEADER: &str = "X-CSRF-Token";
+
+/// The form field forms send the token in
+pub const FIELD: &str = "csrf_token";
+
+/// A token created for a request that didn't have one, set as the cookie by [`csrf`]
+#[derive(Clone)]
+struct NewToken(String);
+
+/// Get the CSRF token of a request, for forms that are submitted without a script
+pub fn csrf_token(req: &HttpRequest) -> String {
+    match req.cookie(COOKIE) {
+        Some(c) => c.value().to_string(),
+        None => req
+            .extensions()
+            .get::<NewToken>()
+            .map(|t| t.0.clone())
+            .unwrap_or_default(),
+    }
+}
+
+/// Check if the `Origin` (or `Referer`, for browsers that don't send `Origin`) of a request is
+/// this site. Requests with neither are allowed, the token is still checked
+///
+/// # Arguments:
+/// * `req` - the request
+/// * `public_url` - the [`public_url`](crate::config::Config::public_url) setting
+fn same_origin(req: &HttpRequest, public_url: &str) -> bool {
+    let source = match req.headers().get(ORIGIN).or(req.headers().get(REFERER)) {
+        Some(h) => h.to_str().unwrap_or_default(),
+        None => return true,
+    };
+
+    let source = match url::Url::parse(source) {
+        Ok(u) => u,
+        // includes "null", sent by sandboxed frames and some redirects
+        Err(_) => return false,
+    };
+
+    let host = match (source.host_str(), source.port()) {
+        (Some(h), Some(p)) => format!("{h}:{p}"),
+        (Some(h), None) => h.to_string(),
+        (None, _) => return false,
+    };
+
+    if host == req.connection_info().host() {
+        return true;
+    }
+
+    url::Url::parse(public_url).is_ok_and(|u| u.origin() == source.origin())
+}
+
+/// The response sent to requests that fail the CSRF check
+fn forbidden(message: &str) -> HttpResponse {
+    HttpResponse::Forbidden()
+        .append_header(("Content-Type", "application/json"))
+        .body(
+            serde_json::to_string(&DefaultReturn::<Option<String>> {
+                success: false,
+                message: message.to_string(),
+                payload: None,
+            })
+            .unwrap(),
+        )
+}
+
+/// Give every browser a CSRF token, and reject cookie-authenticated `POST`, `PUT`, `PATCH` and
+/// `DELETE` requests that come from another origin or don't send the token back
+///
+/// Requests without the session cookie (like API token requests) aren't checked.
+pub async fn csrf(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let cookie = req.cookie(COOKIE).map(|c| c.value().to_string());
+
+    let mutating =
+        [Method::POST, Method::PUT, Method::PATCH, Method::DELETE].contains(req.method());
+    let bearer = crate::pages::base::bearer_token(req.request()).is_some();
+
+    if mutating && !bearer && req.cookie("__Secure-Token").is_some() {
+        let data = req.app_data::<web::Data<AppData>>().unwrap().clone();
+
+        if !same_origin(req.request(), &data.config.public_url) {
+            return Ok(req
+                .into_response(forbidden("Cross-origin requests aren't allowed"))
+                .map_into_right_body());
+        }
+
+        let mut sent = req
+            .headers()
+            .get(HEADER)
+            .and_then(|h| h.to_str().ok())
+            .map(|h| h.to_string());
+
+        // forms send the token in their body, read it then put the body back for the endpoint
+        let is_form = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|h| h.to_str().ok())
+            .is_some_and(|h| h.starts_with("application/x-www-form-urlencoded"));
+
+        if sent.is_none() && is_form {
+            let body = req.extract::<web::Bytes>().await?;
+            req.set_payload(Payload::from(body.clone()));
+
+            sent = url::form_urlencoded::parse(&body)
+                .find(|(name, _)| name == FIELD)
+                .map(|(_, value)| value.to_string());
+        }
+
+        let valid = match (&cookie, &sent) {
+            (Some(c), Some(s)) => {
+                !c.is_empty() && crate::crypto::constant_time_eq(c.as_bytes(), s.as_bytes())
+            }
+            _ => false,
+        };
+
+        if !valid {
+            return Ok(req
+                .into_response(forbidden(
+                    "Invalid CSRF token, please reload the page and try again",
+                ))
+                .map_into_right_body());
+        }
+    }
+
+    // give the browser a token
+    let new_token = if cookie.is_none() {
+        let token = dorsal::utility::random_id();
+        req.extensions_mut().insert(NewToken(token.clone()));
+        Some(token)
+    } else {
+        None
+    };
+
+    let mut res = next.call(req).await?;
+
+    if let Some(token) = new_token {
+        if let Ok(value) = HeaderValue::from_str(&format!(
+            "{COOKIE}={token}; SameSite=Strict; Secure; Path=/; Max-Age={}",
+            60 * 60 * 24 * 365
+        )) {
+            res.headers_mut().append(SET_COOKIE, value);
+        }
+    }
+
+    Ok(res.map_into_left_body())
+}
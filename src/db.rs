@@ -1,14 +1,17 @@
 use dorsal::query as sqlquery;
+use redis::Commands;
 use serde::{Deserialize, Serialize};
+use sqlx::Row;
 
 #[derive(Clone)]
 pub struct AppData {
     pub db: Database,
     pub http_client: awc::Client,
+    pub config: crate::config::Config,
 }
 
 pub use dorsal::db::special::auth_db::{
-    FullUser, RoleLevel, RoleLevelLog, UserMetadata, UserState, Result,
+    AuthError, FullUser, RoleLevel, RoleLevelLog, UserMetadata, UserState, Result,
 };
 
 pub use dorsal::db::special::log_db::{Log, LogIdentifier};
@@ -20,9 +23,14 @@ pub struct UserFollow {
     pub is_following: String, // use user that `user` is following
 }
 
-#[allow(dead_code)]
-pub fn deserialize_userfollow(input: String) -> UserFollow {
-    serde_json::from_str::<UserFollow>(&input).unwrap()
+/// A row in the `gup_follows` table
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Follow {
+    /// The username of the user that is following `following`
+    pub follower: String,
+    /// The username of the user that `follower` is following
+    pub following: String,
+    pub timestamp: u128,
 }
 
 // activity feed
@@ -38,6 +46,254 @@ pub struct ActivityPost {
     pub timestamp: u128,
 }
 
+/// A previous version of an [`ActivityPost`]'s content
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PostRevision {
+    pub id: String,
+    /// The ID of the post this is a revision of
+    pub post: String,
+    pub content: String,
+    pub content_html: String,
+    /// When this revision was replaced by an edit
+    pub timestamp: u128,
+}
+
+/// A row in the `gup_favorites` table
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PostFavorite {
+    /// the username of the user that favorited the post
+    pub username: String,
+    /// the id of the post that was favorited
+    pub post: String,
+    pub timestamp: u128,
+}
+
+/// A row in the `gup_mentions` table
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Mention {
+    /// the id of the post the user was mentioned in
+    pub post: String,
+    /// the username of the user that was mentioned
+    pub username: String,
+    /// the username of the author of the post
+    pub author: String,
+    pub timestamp: u128,
+}
+
+/// A row in the `gup_tags` table
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PostTag {
+    /// the tag, without the leading `#`
+    pub tag: String,
+    /// the id of the post that used the tag
+    pub post: String,
+    /// the username of the author of the post
+    pub author: String,
+    /// the timestamp of the post
+    pub timestamp: u128,
+}
+
+/// The number of posts that used a tag
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// A user returned by [`Database::search`]
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserSearchResult {
+    pub username: String,
+    pub nickname: String,
+    pub about: String,
+}
+
+/// The results of [`Database::search`]
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SearchResults {
+    pub users: Vec<UserSearchResult>,
+    pub posts: Vec<(ActivityPost, Vec<ActivityPost>, i32)>,
+}
+
+/// A row in the `gup_notifications` table
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Notification {
+    pub id: String,
+    /// the username of the user the notification is for
+    pub recipient: String,
+    /// what happened (`follow`, `reply`, `favorite` or `mention`)
+    pub kind: String,
+    /// the username of the user that caused the notification
+    pub actor: String,
+    /// the id of the post the notification is about (empty for follows)
+    pub post: String,
+    pub read: bool,
+    pub timestamp: u128,
+}
+
+/// A row in the `gup_invites` table
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Invite {
+    pub code: String,
+    /// the username of the user that created the invite
+    pub author: String,
+    /// how many accounts can be registered with this invite
+    pub max_uses: usize,
+    /// how many accounts have been registered with this invite
+    pub uses: usize,
+    /// when the invite stops working (`0` if it never expires)
+    pub expires: u128,
+    pub timestamp: u128,
+}
+
+/// A row in the `gup_invite_uses` table
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InviteUse {
+    /// the invite that was used
+    pub code: String,
+    /// the username of the account registered with the invite
+    pub username: String,
+    pub timestamp: u128,
+}
+
+/// A row in the `gup_sessions` table
+///
+/// The session's token is only stored hashed, and is never part of this struct.
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Session {
+    pub id: String,
+    /// the username of the user the session is for
+    pub username: String,
+    /// the `User-Agent` of the client that created the session
+    pub user_agent: String,
+    pub created: u128,
+    /// when the session was last used, only updated every [`SESSION_SEEN_INTERVAL`]
+    pub last_seen: u128,
+    /// if this is the session making the request (not stored)
+    #[serde(default)]
+    pub current: bool,
+}
+
+/// How long (in ms) a session can go unused before it stops working
+pub const SESSION_MAX_AGE: u128 = 60 * 60 * 24 * 365 * 1000;
+
+/// How often (in ms) the `last_seen` time of a session is updated
+pub const SESSION_SEEN_INTERVAL: u128 = 60 * 5 * 1000;
+
+/// A row in the `gup_totp` table
+///
+/// The secret is stored encrypted (see [`crate::crypto::encrypt`]).
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Totp {
+    /// the username of the user the secret is for
+    pub username: String,
+    pub secret: String,
+    /// if the user confirmed they can create codes (`false` while enrollment isn't finished)
+    pub enabled: bool,
+    /// the step of the last accepted code, so codes can't be used twice
+    pub last_step: u64,
+    pub timestamp: u128,
+}
+
+/// A row in the `gup_api_tokens` table
+///
+/// Like sessions, the token itself is only stored hashed and is never part of this struct.
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiToken {
+    pub id: String,
+    /// the username of the user the token acts as
+    pub username: String,
+    /// a name to recognize the token by
+    pub name: String,
+    /// what the token can be used for, see [`API_TOKEN_SCOPES`]
+    pub scopes: Vec<String>,
+    /// when the token stops working (`0` if it never expires)
+    pub expires: u128,
+    /// when the token was last used (`0` if it never was), only updated every
+    /// [`SESSION_SEEN_INTERVAL`]
+    pub last_used: u128,
+    pub created: u128,
+}
+
+/// Every scope an [`ApiToken`] can have
+///
+/// * `read` - read the timeline, mentions, notifications and invites
+/// * `activity:write` - create, edit, delete and favorite posts
+/// * `follows:write` - follow and unfollow users
+/// * `profile:write` - edit the user's about and profile fields
+/// * `notifications:write` - mark notifications as read
+///
+/// Everything else (sessions, passwords, tokens, moderation...) can't be done with a token.
+pub const API_TOKEN_SCOPES: &[&str] = &[
+    "read",
+    "activity:write",
+    "follows:write",
+    "profile:write",
+    "notifications:write",
+];
+
+/// How many API tokens each user can have
+pub const API_TOKEN_LIMIT: usize = 25;
+
+/// A row in the `gup_oauth_clients` table
+///
+/// The client secret is only stored hashed, and is never part of this struct.
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OAuthClient {
+    /// the `client_id`
+    pub id: String,
+    pub name: String,
+    /// the username of the user that registered the client
+    pub owner: String,
+    /// the only URIs users can be sent back to after authorizing
+    pub redirect_uris: Vec<String>,
+    /// if the client has no secret (and has to use PKCE instead)
+    pub public: bool,
+    pub created: u128,
+}
+
+/// An authorization code waiting to be exchanged for tokens, stored in the cache for
+/// [`crate::oauth::CODE_MAX_AGE`]
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OAuthCode {
+    /// the `client_id` of the client the code was issued to
+    pub client: String,
+    /// the username of the user that authorized the client
+    pub username: String,
+    pub redirect_uri: String,
+    pub scope: String,
+    /// sent back in the ID token
+    pub nonce: String,
+    /// the PKCE `code_challenge` (`S256`), empty if none was sent
+    pub code_challenge: String,
+    pub auth_time: u128,
+}
+
+/// An access token issued to an OAuth client, stored in the cache for
+/// [`crate::oauth::TOKEN_MAX_AGE`]
+#[derive(Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OAuthAccessToken {
+    /// the `client_id` of the client the token was issued to
+    pub client: String,
+    /// the username of the user that authorized the client
+    pub username: String,
+    pub scope: String,
+}
+
+/// The shortest password users can set
+pub const PASSWORD_MIN_LENGTH: usize = 8;
+
+/// The longest password users can set, so hashing can't be used to slow the server down
+pub const PASSWORD_MAX_LENGTH: usize = 256;
+
+/// How long (in seconds) users have to enter their code after signing in with two-factor
+/// authentication enabled
+pub const TOTP_CHALLENGE_MAX_AGE: u64 = 60 * 5;
+
+/// How many wrong codes can be entered for a challenge before the user has to sign in again
+pub const TOTP_CHALLENGE_ATTEMPTS: i64 = 5;
+
+/// The content of `post_favorite` logs created by older versions
 #[derive(Clone, Serialize, Deserialize)]
 pub struct PostFavoriteLog {
     /// the username of the user that favorited the post
@@ -54,6 +310,11 @@ pub struct PCreatePost {
     #[serde(default)]
     pub reply: String,
 }
+
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub struct PEditPost {
+    pub content: String,
+}
 // server
 #[derive(Clone)]
 pub struct Database {
@@ -82,44 +343,371 @@ impl Database {
         }
     }
 
-    pub async fn init(&self) {
+    /// Apply pending migrations, then move data stored by older versions into its own tables
+    pub async fn init(&self) -> DefaultReturn<Vec<i64>> {
+        let res = self.migrate().await;
+
+        if !res.success {
+            return res;
+        }
+
+        // move follows and favorites from the "Logs" table
+        self.migrate_follow_logs().await;
+        self.migrate_favorite_logs().await;
+
+        // fill search tables created after users or posts already existed
+        self.rebuild_search_index().await;
+
+        res
+    }
+
+    // migrations
+
+    // GET
+    /// Get the version of the newest migration that was applied, `0` if none were
+    pub async fn get_schema_version(&self) -> DefaultReturn<i64> {
         let c = &self.base.db.client;
+        let res = sqlquery("SELECT MAX(\"version\") FROM \"gup_schema_version\"")
+            .fetch_one(c)
+            .await;
 
-        let _ = sqlquery(
-            "CREATE TABLE IF NOT EXISTS \"Users\" (
-                username VARCHAR(1000000),
-                id_hashed VARCHAR(1000000),
-                role VARCHAR(1000000),
-                timestamp VARCHAR(1000000),
-                metadata VARCHAR(1000000)
-            )",
-        )
-        .execute(c)
-        .await;
+        match res {
+            Ok(row) => DefaultReturn {
+                success: true,
+                message: String::from("Schema version exists"),
+                payload: row.get::<Option<i64>, _>(0).unwrap_or(0),
+            },
+            Err(e) => DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: 0,
+            },
+        }
+    }
 
-        let _ = sqlquery(
-            "CREATE TABLE IF NOT EXISTS \"Logs\" (
-                id VARCHAR(1000000),
-                logtype VARCHAR(1000000),
-                timestamp  VARCHAR(1000000),
-                content VARCHAR(1000000)
-            )",
-        )
-        .execute(c)
-        .await;
+    // SET
+    /// Apply every migration in [`crate::migrations::MIGRATIONS`] that hasn't been applied yet.
+    /// Returns the versions that were applied
+    ///
+    /// Each migration runs in its own transaction and migrating stops at the first failure. mysql
+    /// commits every `CREATE`, `ALTER` and `DROP` statement as it runs, so a failed migration can
+    /// be partly applied there and every step has to be safe to run again.
+    pub async fn migrate(&self) -> DefaultReturn<Vec<i64>> {
+        use crate::migrations::{Step, MIGRATIONS};
+        let c = &self.base.db.client;
 
-        let _ = sqlquery(
-            "CREATE TABLE IF NOT EXISTS \"gup_posts\" (
-                id VARCHAR(1000000),
-                author VARCHAR(1000000),
-                content VARCHAR(1000000),
-                content_html VARCHAR(1000000),
-                reply VARCHAR(1000000),
-                timestamp VARCHAR(1000000)
+        let res = sqlquery(
+            "CREATE TABLE IF NOT EXISTS \"gup_schema_version\" (
+                version BIGINT,
+                name VARCHAR(500),
+                timestamp VARCHAR(100)
             )",
         )
         .execute(c)
         .await;
+
+        if let Err(e) = res {
+            return DefaultReturn {
+                success: false,
+                message: format!("Failed to create schema version table: {}", e),
+                payload: Vec::new(),
+            };
+        }
+
+        let current = self.get_schema_version().await;
+
+        if !current.success {
+            return DefaultReturn {
+                success: false,
+                message: current.message,
+                payload: Vec::new(),
+            };
+        }
+
+        // apply
+        let mut applied: Vec<i64> = Vec::new();
+
+        for migration in MIGRATIONS.iter().filter(|m| m.version > current.payload) {
+            let failed = |e: String| DefaultReturn {
+                success: false,
+                message: format!(
+                    "Migration {} ({}) failed: {}",
+                    migration.version, migration.name, e
+                ),
+                payload: applied.clone(),
+            };
+
+            let mut tx = match c.begin().await {
+                Ok(tx) => tx,
+                Err(e) => return failed(e.to_string()),
+            };
+
+            // steps are taken from the end, so nested steps can be pushed in order
+            let mut pending: Vec<&Step> = migration.steps.iter().rev().collect();
+
+            while let Some(step) = pending.pop() {
+                let res: std::result::Result<(), String> = match step {
+                    // skip steps for other database types
+                    Step::Only(db_type, step) => {
+                        if *db_type == self.base.db._type {
+                            pending.push(*step);
+                        }
+
+                        Ok(())
+                    }
+                    Step::IfExists(table, steps) => {
+                        let query: &str = match self.base.db._type.as_str() {
+                            "sqlite" => "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?",
+                            "mysql" => "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = DATABASE() AND table_name = ?",
+                            _ => "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = current_schema() AND table_name = $1",
+                        };
+
+                        match sqlquery(query)
+                            .bind::<&str>(table)
+                            .fetch_one(&mut *tx)
+                            .await
+                        {
+                            Ok(row) => {
+                                if row.get::<i64, _>(0) > 0 {
+                                    pending.extend(steps.iter().rev());
+                                }
+
+                                Ok(())
+                            }
+                            Err(e) => Err(e.to_string()),
+                        }
+                    }
+                    Step::Sql(sql) => sqlquery(sql)
+                        .execute(&mut *tx)
+                        .await
+                        .map(|_| ())
+                        .map_err(|e| e.to_string()),
+                    Step::Index { table, name, sql } => {
+                        if self.base.db._type == "mysql" {
+                            let existing = sqlquery(
+                                "SELECT COUNT(*) FROM information_schema.statistics WHERE table_schema = DATABASE() AND table_name = ? AND index_name = ?",
+                            )
+                            .bind::<&str>(table)
+                            .bind::<&str>(name)
+                            .fetch_one(&mut *tx)
+                            .await;
+
+                            match existing {
+                                Ok(row) if row.get::<i64, _>(0) > 0 => Ok(()),
+                                Ok(_) => sqlquery(&sql.replace(" IF NOT EXISTS", ""))
+                                    .execute(&mut *tx)
+                                    .await
+                                    .map(|_| ())
+                                    .map_err(|e| e.to_string()),
+                                Err(e) => Err(e.to_string()),
+                            }
+                        } else {
+                            sqlquery(sql)
+                                .execute(&mut *tx)
+                                .await
+                                .map(|_| ())
+                                .map_err(|e| e.to_string())
+                        }
+                    }
+                    Step::SameRows { table, copy } => {
+                        let mut counts: Vec<i64> = Vec::new();
+
+                        for t in [table, copy] {
+                            match sqlquery(&format!("SELECT COUNT(*) FROM \"{t}\""))
+                                .fetch_one(&mut *tx)
+                                .await
+                            {
+                                Ok(row) => counts.push(row.get::<i64, _>(0)),
+                                Err(e) => return failed(e.to_string()),
+                            }
+                        }
+
+                        if counts[0] == counts[1] {
+                            Ok(())
+                        } else {
+                            Err(format!(
+                                "\"{copy}\" has {} rows but \"{table}\" has {}",
+                                counts[1], counts[0]
+                            ))
+                        }
+                    }
+                };
+
+                if let Err(e) = res {
+                    return failed(e);
+                }
+            }
+
+            // record version
+            let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+            {
+                "INSERT INTO \"gup_schema_version\" VALUES (?, ?, ?)"
+            } else {
+                "INSERT INTO \"gup_schema_version\" VALUES ($1, $2, $3)"
+            };
+
+            let res = sqlquery(query)
+                .bind::<i64>(migration.version)
+                .bind::<&str>(migration.name)
+                .bind::<&String>(&dorsal::utility::unix_epoch_timestamp().to_string())
+                .execute(&mut *tx)
+                .await;
+
+            if let Err(e) = res {
+                return failed(e.to_string());
+            }
+
+            if let Err(e) = tx.commit().await {
+                return failed(e.to_string());
+            }
+
+            applied.push(migration.version);
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Database is up to date"),
+            payload: applied,
+        }
+    }
+
+    /// Move all `follow` logs from the "Logs" table into the "gup_follows" table
+    ///
+    /// Logs are deleted once they are moved, so this does nothing after the first run.
+    async fn migrate_follow_logs(&self) {
+        let c = &self.base.db.client;
+        let res = sqlquery("SELECT * FROM \"Logs\" WHERE \"logtype\" = 'follow'")
+            .fetch_all(c)
+            .await;
+
+        let rows = match res {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+
+        for row in rows {
+            let row = self.base.textify_row(row).data;
+            let id = row.get("id").unwrap().to_string();
+
+            let follow = match serde_json::from_str::<UserFollow>(row.get("content").unwrap()) {
+                Ok(f) => f,
+                Err(_) => continue,
+            };
+
+            // older versions could store the same follow more than once
+            let existing = self
+                .get_follow_by_user(follow.user.clone(), follow.is_following.clone())
+                .await;
+
+            if !existing.success {
+                let query: &str =
+                    if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+                        "INSERT INTO \"gup_follows\" VALUES (?, ?, ?)"
+                    } else {
+                        "INSERT INTO \"gup_follows\" VALUES ($1, $2, $3)"
+                    };
+
+                if sqlquery(query)
+                    .bind::<&String>(&follow.user)
+                    .bind::<&String>(&follow.is_following)
+                    .bind::<&String>(row.get("timestamp").unwrap())
+                    .execute(c)
+                    .await
+                    .is_err()
+                {
+                    // keep the log so we can try again next time
+                    continue;
+                }
+            }
+
+            let _ = self.logs.delete_log(id).await;
+        }
+    }
+
+    /// Move all `post_favorite` logs from the "Logs" table into the "gup_favorites" table
+    ///
+    /// Logs are deleted once they are moved, so this does nothing after the first run.
+    async fn migrate_favorite_logs(&self) {
+        let c = &self.base.db.client;
+        let res = sqlquery("SELECT * FROM \"Logs\" WHERE \"logtype\" = 'post_favorite'")
+            .fetch_all(c)
+            .await;
+
+        let rows = match res {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+
+        if rows.is_empty() {
+            return;
+        }
+
+        for row in rows {
+            let row = self.base.textify_row(row).data;
+            let id = row.get("id").unwrap().to_string();
+
+            let favorite =
+                match serde_json::from_str::<PostFavoriteLog>(row.get("content").unwrap()) {
+                    Ok(f) => f,
+                    Err(_) => continue,
+                };
+
+            let existing = self
+                .get_user_post_favorite(favorite.user.clone(), favorite.id.clone(), true)
+                .await;
+
+            if !existing.success {
+                let query: &str =
+                    if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+                        "INSERT INTO \"gup_favorites\" VALUES (?, ?, ?)"
+                    } else {
+                        "INSERT INTO \"gup_favorites\" VALUES ($1, $2, $3)"
+                    };
+
+                if sqlquery(query)
+                    .bind::<&String>(&favorite.user)
+                    .bind::<&String>(&favorite.id)
+                    .bind::<&String>(row.get("timestamp").unwrap())
+                    .execute(c)
+                    .await
+                    .is_err()
+                {
+                    // keep the log so we can try again next time
+                    continue;
+                }
+            }
+
+            let _ = self.logs.delete_log(id).await;
+        }
+
+        // counts were only stored in the cache before, read them from the database from now on
+        self.base
+            .cachedb
+            .remove_starting_with(String::from("social:post-favorites:*"))
+            .await;
+    }
+
+    // cache
+
+    /// Remove every key guppy stores in the cache, everything is read from the database again
+    /// the next time it's needed
+    pub async fn flush_cache(&self) {
+        for prefix in [
+            "user:",
+            "post:",
+            "post-replies:",
+            "user-posts:",
+            "user-timeline:",
+            "public-posts:",
+            "tag-posts:",
+            "social:",
+        ] {
+            self.base
+                .cachedb
+                .remove_starting_with(format!("{prefix}*"))
+                .await;
+        }
     }
 
     // users
@@ -223,21 +811,20 @@ impl Database {
         let user_id_hashed: String = dorsal::utility::hash(user_id_unhashed.clone());
         let timestamp = dorsal::utility::unix_epoch_timestamp().to_string();
 
+        let metadata = UserMetadata {
+            about: String::new(),
+            avatar_url: Option::None,
+            secondary_token: Option::None,
+            nickname: Option::Some(username.clone()),
+        };
+
         let c = &self.base.db.client;
         let res = sqlquery(query)
             .bind::<&String>(&username)
             .bind::<&String>(&user_id_hashed)
             .bind::<&String>(&String::from("member")) // default role
             .bind::<&String>(&timestamp)
-            .bind::<&String>(
-                &serde_json::to_string::<UserMetadata>(&UserMetadata {
-                    about: String::new(),
-                    avatar_url: Option::None,
-                    secondary_token: Option::None,
-                    nickname: Option::Some(username.clone()),
-                })
-                .unwrap(),
-            )
+            .bind::<&String>(&serde_json::to_string::<UserMetadata>(&metadata).unwrap())
             .execute(c)
             .await;
 
@@ -249,6 +836,9 @@ impl Database {
             };
         }
 
+        // update search
+        self.index_user(&username, &metadata).await;
+
         // return
         return DefaultReturn {
             success: true,
@@ -299,6 +889,9 @@ impl Database {
         // update cache
         self.base.cachedb.remove(format!("user:{}", name)).await;
 
+        // update search
+        self.index_user(&name, &metadata).await;
+
         // return
         return DefaultReturn {
             success: true,
@@ -307,6 +900,28 @@ impl Database {
         };
     }
 
+    /// Check if a [`UserState`] exists, reading the database directly
+    ///
+    /// Unlike [`Database::get_user_by_username`], banned users are found whether or not they're
+    /// cached.
+    ///
+    /// # Arguments:
+    /// * `name` - the username of the user
+    pub async fn user_exists(&self, name: String) -> bool {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT \"username\" FROM \"Users\" WHERE \"username\" = ?"
+        } else {
+            "SELECT \"username\" FROM \"Users\" WHERE \"username\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        sqlquery(query)
+            .bind::<&String>(&name)
+            .fetch_optional(c)
+            .await
+            .is_ok_and(|row| row.is_some())
+    }
+
     /// Ban a [`UserState`] by its `username`
     pub async fn ban_user_by_name(&self, name: String) -> DefaultReturn<Option<String>> {
         // make sure user exists
@@ -321,44 +936,131 @@ impl Database {
 
         // make sure user level elevation is 0
         let level = &existing.as_ref().ok().unwrap().level;
-        if level.elevation == 0 {
+        if level.elevation > 0 {
             return DefaultReturn {
                 success: false,
-                message: String::from("User must be of level elevation 0"),
+                message: String::from("Users with an elevated role can't be banned"),
                 payload: Option::None,
             };
         }
 
-        // update user
-        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "UPDATE \"Users\" SET \"role\" = ? WHERE \"username\" = ?"
-        } else {
-            "UPDATE \"Users\" SET (\"role\") = ($1) WHERE \"username\" = $2"
-        };
-
-        let c = &self.base.db.client;
-        let res = sqlquery(query)
-            .bind::<&str>("banned")
-            .bind::<&String>(&name)
-            .execute(c)
+        let res = self
+            .set_user_role(name.clone(), String::from("banned"))
             .await;
 
-        if res.is_err() {
-            return DefaultReturn {
-                success: false,
-                message: String::from(res.err().unwrap().to_string()),
-                payload: Option::None,
-            };
+        if !res.success {
+            return res;
         }
 
-        // update cache
-        let existing_in_cache = self.base.cachedb.get(format!("user:{}", name)).await;
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("User banned!"),
+            payload: Option::Some(name),
+        }
+    }
 
-        if existing_in_cache.is_some() {
-            let mut user =
-                serde_json::from_str::<UserState<UserMetadata>>(&existing_in_cache.unwrap())
-                    .unwrap();
-            user.role = String::from("banned"); // update role
+    /// Give a [`UserState`] a new ID, signing out every session and revoking every API token.
+    /// Returns the new unhashed ID
+    ///
+    /// The old ID stops working immediately.
+    ///
+    /// # Arguments:
+    /// * `name` - the username of the user
+    pub async fn rotate_user_id(&self, name: String) -> DefaultReturn<Option<String>> {
+        // make sure user exists
+        let existing = &self.get_user_by_username(name.clone()).await;
+        if existing.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: String::from("User does not exist!"),
+                payload: Option::None,
+            };
+        }
+
+        // update user
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "UPDATE \"Users\" SET \"id_hashed\" = ? WHERE \"username\" = ?"
+        } else {
+            "UPDATE \"Users\" SET (\"id_hashed\") = ($1) WHERE \"username\" = $2"
+        };
+
+        let user_id_unhashed: String = dorsal::utility::uuid();
+        let user_id_hashed: String = dorsal::utility::hash(user_id_unhashed.clone());
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query)
+            .bind::<&String>(&user_id_hashed)
+            .bind::<&String>(&name)
+            .execute(c)
+            .await;
+
+        if let Err(e) = res {
+            return DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            };
+        }
+
+        // update cache
+        self.base.cachedb.remove(format!("user:{}", name)).await;
+
+        // sign out everywhere, sessions and tokens could have been created with the old ID
+        self.delete_other_sessions(name.clone(), Option::None).await;
+        self.delete_user_api_tokens(name).await;
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("User ID rotated!"),
+            payload: Option::Some(user_id_unhashed),
+        }
+    }
+
+    /// Set the role of a [`UserState`] by its `username`
+    ///
+    /// # Arguments:
+    /// * `name` - the username of the user
+    /// * `role` - the new role (`member` unbans a user)
+    pub async fn set_user_role(&self, name: String, role: String) -> DefaultReturn<Option<String>> {
+        // make sure user exists (including banned users)
+        if !self.user_exists(name.clone()).await {
+            return DefaultReturn {
+                success: false,
+                message: String::from("User does not exist!"),
+                payload: Option::None,
+            };
+        }
+
+        // update user
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "UPDATE \"Users\" SET \"role\" = ? WHERE \"username\" = ?"
+        } else {
+            "UPDATE \"Users\" SET (\"role\") = ($1) WHERE \"username\" = $2"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query)
+            .bind::<&String>(&role)
+            .bind::<&String>(&name)
+            .execute(c)
+            .await;
+
+        if let Err(e) = res {
+            return DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            };
+        }
+
+        // update cache
+        let existing_in_cache = self.base.cachedb.get(format!("user:{}", name)).await;
+
+        if let Some(cached) = existing_in_cache {
+            let mut user = serde_json::from_str::<UserState<UserMetadata>>(&cached).unwrap();
+            user.role = role.clone(); // update role
 
             // update cache
             self.base
@@ -370,18 +1072,290 @@ impl Database {
                 .await;
         }
 
+        // banned users are hidden from the public feed
+        self.base
+            .cachedb
+            .remove_starting_with(String::from("public-posts:*"))
+            .await;
+
+        self.base
+            .cachedb
+            .remove_starting_with(String::from("tag-posts:*"))
+            .await;
+
         // return
-        return DefaultReturn {
+        DefaultReturn {
             success: true,
-            message: String::from("User banned!"),
+            message: format!("User role set to {role}!"),
             payload: Option::Some(name),
+        }
+    }
+
+    /// Delete a [`UserState`] by its `username`, along with everything they've created
+    ///
+    /// # Arguments:
+    /// * `name` - the username of the user
+    pub async fn delete_user_by_name(&self, name: String) -> DefaultReturn<Option<String>> {
+        // make sure user exists (including banned users)
+        if !self.user_exists(name.clone()).await {
+            return DefaultReturn {
+                success: false,
+                message: String::from("User does not exist!"),
+                payload: Option::None,
+            };
+        }
+
+        let c = &self.base.db.client;
+
+        // delete posts
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_posts\" WHERE \"author\" = ?"
+        } else {
+            "SELECT * FROM \"gup_posts\" WHERE \"author\" = $1"
+        };
+
+        let posts = match sqlquery(query).bind::<&String>(&name).fetch_all(c).await {
+            Ok(r) => r,
+            Err(e) => {
+                return DefaultReturn {
+                    success: false,
+                    message: e.to_string(),
+                    payload: Option::None,
+                }
+            }
+        };
+
+        for row in posts {
+            let res = self.remove_activity_post(post_from_row(&row)).await;
+
+            if !res.success {
+                return DefaultReturn {
+                    success: false,
+                    message: res.message,
+                    payload: Option::None,
+                };
+            }
+        }
+
+        // delete follows
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_follows\" WHERE \"follower\" = ? OR \"following\" = ?"
+        } else {
+            "SELECT * FROM \"gup_follows\" WHERE \"follower\" = $1 OR \"following\" = $2"
+        };
+
+        let follows = sqlquery(query)
+            .bind::<&String>(&name)
+            .bind::<&String>(&name)
+            .fetch_all(c)
+            .await
+            .unwrap_or_default();
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_follows\" WHERE \"follower\" = ? OR \"following\" = ?"
+        } else {
+            "DELETE FROM \"gup_follows\" WHERE \"follower\" = $1 OR \"following\" = $2"
+        };
+
+        let _ = sqlquery(query)
+            .bind::<&String>(&name)
+            .bind::<&String>(&name)
+            .execute(c)
+            .await;
+
+        for row in follows {
+            let follower = row.get::<String, _>("follower");
+            let following = row.get::<String, _>("following");
+
+            self.base
+                .cachedb
+                .remove(format!("social:following-count:{}", follower))
+                .await;
+
+            self.base
+                .cachedb
+                .remove(format!("social:follower-count:{}", following))
+                .await;
+
+            self.base
+                .cachedb
+                .remove(format!("user-timeline:{}", follower))
+                .await;
+        }
+
+        // delete favorites
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT \"post\" FROM \"gup_favorites\" WHERE \"username\" = ?"
+        } else {
+            "SELECT \"post\" FROM \"gup_favorites\" WHERE \"username\" = $1"
+        };
+
+        let favorites = sqlquery(query)
+            .bind::<&String>(&name)
+            .fetch_all(c)
+            .await
+            .unwrap_or_default();
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_favorites\" WHERE \"username\" = ?"
+        } else {
+            "DELETE FROM \"gup_favorites\" WHERE \"username\" = $1"
+        };
+
+        let _ = sqlquery(query).bind::<&String>(&name).execute(c).await;
+
+        for row in favorites {
+            self.base
+                .cachedb
+                .remove(format!(
+                    "social:post-favorites:{}",
+                    row.get::<String, _>("post")
+                ))
+                .await;
+        }
+
+        // delete mentions
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_mentions\" WHERE \"username\" = ?"
+        } else {
+            "DELETE FROM \"gup_mentions\" WHERE \"username\" = $1"
+        };
+
+        let _ = sqlquery(query).bind::<&String>(&name).execute(c).await;
+
+        // delete notifications
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT DISTINCT \"recipient\" FROM \"gup_notifications\" WHERE \"actor\" = ?"
+        } else {
+            "SELECT DISTINCT \"recipient\" FROM \"gup_notifications\" WHERE \"actor\" = $1"
+        };
+
+        let recipients = sqlquery(query)
+            .bind::<&String>(&name)
+            .fetch_all(c)
+            .await
+            .unwrap_or_default();
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_notifications\" WHERE \"recipient\" = ? OR \"actor\" = ?"
+        } else {
+            "DELETE FROM \"gup_notifications\" WHERE \"recipient\" = $1 OR \"actor\" = $2"
+        };
+
+        let _ = sqlquery(query)
+            .bind::<&String>(&name)
+            .bind::<&String>(&name)
+            .execute(c)
+            .await;
+
+        for row in recipients {
+            self.base
+                .cachedb
+                .remove(format!(
+                    "social:unread-notifications:{}",
+                    row.get::<String, _>("recipient")
+                ))
+                .await;
+        }
+
+        self.base
+            .cachedb
+            .remove(format!("social:unread-notifications:{}", name))
+            .await;
+
+        // delete sessions
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_sessions\" WHERE \"username\" = ?"
+        } else {
+            "DELETE FROM \"gup_sessions\" WHERE \"username\" = $1"
+        };
+
+        let _ = sqlquery(query).bind::<&String>(&name).execute(c).await;
+
+        // delete two-factor authentication
+        self.disable_totp(name.clone()).await;
+
+        // delete password
+        self.remove_password(name.clone()).await;
+
+        // delete api tokens
+        self.delete_user_api_tokens(name.clone()).await;
+
+        // delete oauth clients
+        for client in self
+            .get_oauth_clients(Option::Some(name.clone()))
+            .await
+            .payload
+            .unwrap_or_default()
+        {
+            self.delete_oauth_client(client.id).await;
+        }
+
+        // delete oauth subject
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_oauth_subjects\" WHERE \"username\" = ?"
+        } else {
+            "DELETE FROM \"gup_oauth_subjects\" WHERE \"username\" = $1"
+        };
+
+        let _ = sqlquery(query).bind::<&String>(&name).execute(c).await;
+
+        // delete invites
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_invites\" WHERE \"author\" = ?"
+        } else {
+            "DELETE FROM \"gup_invites\" WHERE \"author\" = $1"
+        };
+
+        let _ = sqlquery(query).bind::<&String>(&name).execute(c).await;
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_invite_uses\" WHERE \"username\" = ?"
+        } else {
+            "DELETE FROM \"gup_invite_uses\" WHERE \"username\" = $1"
+        };
+
+        let _ = sqlquery(query).bind::<&String>(&name).execute(c).await;
+
+        // delete from search
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_user_search\" WHERE \"username\" = ?"
+        } else {
+            "DELETE FROM \"gup_user_search\" WHERE \"username\" = $1"
+        };
+
+        let _ = sqlquery(query).bind::<&String>(&name).execute(c).await;
+
+        // delete user
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"Users\" WHERE \"username\" = ?"
+        } else {
+            "DELETE FROM \"Users\" WHERE \"username\" = $1"
         };
+
+        if let Err(e) = sqlquery(query).bind::<&String>(&name).execute(c).await {
+            return DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            };
+        }
+
+        // update cache
+        self.base.cachedb.remove(format!("user:{}", name)).await;
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("User deleted!"),
+            payload: Option::Some(name),
+        }
     }
 
     // follows
 
     // GET
-    /// Get a [`UserFollow`] by the username of the user following
+    /// Get a [`Follow`] by the username of the user following
     ///
     /// # Arguments:
     /// * `user` - username of user following
@@ -390,18 +1364,17 @@ impl Database {
         &self,
         user: String,
         is_following: String,
-    ) -> DefaultReturn<Option<Log>> {
+    ) -> DefaultReturn<Option<Follow>> {
         let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "SELECT * FROM \"Logs\" WHERE \"content\" LIKE ? AND \"logtype\" = 'follow'"
+            "SELECT * FROM \"gup_follows\" WHERE \"follower\" = ? AND \"following\" = ?"
         } else {
-            "SELECT * FROM \"Logs\" WHERE \"content\" LIKE $1 AND \"logtype\" = 'follow'"
+            "SELECT * FROM \"gup_follows\" WHERE \"follower\" = $1 AND \"following\" = $2"
         };
 
         let c = &self.base.db.client;
         let res = sqlquery(query)
-            .bind::<&String>(&format!(
-                "%\"user\":\"{user}\",\"is_following\":\"{is_following}\"%"
-            ))
+            .bind::<&String>(&user)
+            .bind::<&String>(&is_following)
             .fetch_one(c)
             .await;
 
@@ -421,16 +1394,15 @@ impl Database {
         return DefaultReturn {
             success: true,
             message: String::from("Follow exists"),
-            payload: Option::Some(Log {
-                id: row.get("id").unwrap().to_string(),
-                logtype: row.get("logtype").unwrap().to_string(),
+            payload: Option::Some(Follow {
+                follower: row.get("follower").unwrap().to_string(),
+                following: row.get("following").unwrap().to_string(),
                 timestamp: row.get("timestamp").unwrap().parse::<u128>().unwrap(),
-                content: row.get("content").unwrap().to_string(),
             }),
         };
     }
 
-    /// Get the [`UserFollow`]s that are following the given `user`
+    /// Get the [`Follow`]s that are following the given `user`
     ///
     /// # Arguments:
     /// * `user` - username of user to check
@@ -439,16 +1411,16 @@ impl Database {
         &self,
         user: String,
         offset: Option<i32>,
-    ) -> DefaultReturn<Option<Vec<Log>>> {
+    ) -> DefaultReturn<Option<Vec<Follow>>> {
         let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "SELECT * FROM \"Logs\" WHERE \"content\" LIKE ? AND \"logtype\" = 'follow' ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET ?"
+            "SELECT * FROM \"gup_follows\" WHERE \"following\" = ? ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET ?"
         } else {
-            "SELECT * FROM \"Logs\" WHERE \"content\" LIKE $1 AND \"logtype\" = 'follow' ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET $2"
+            "SELECT * FROM \"gup_follows\" WHERE \"following\" = $1 ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET $2"
         };
 
         let c = &self.base.db.client;
         let res = sqlquery(query)
-            .bind::<&String>(&format!("%\"is_following\":\"{user}\"%"))
+            .bind::<&String>(&user)
             .bind(if offset.is_some() { offset.unwrap() } else { 0 })
             .fetch_all(c)
             .await;
@@ -463,15 +1435,14 @@ impl Database {
 
         // ...
         let rows = res.unwrap();
-        let mut output: Vec<Log> = Vec::new();
+        let mut output: Vec<Follow> = Vec::new();
 
         for row in rows {
             let row = self.base.textify_row(row).data;
-            output.push(Log {
-                id: row.get("id").unwrap().to_string(),
-                logtype: row.get("logtype").unwrap().to_string(),
+            output.push(Follow {
+                follower: row.get("follower").unwrap().to_string(),
+                following: row.get("following").unwrap().to_string(),
                 timestamp: row.get("timestamp").unwrap().parse::<u128>().unwrap(),
-                content: row.get("content").unwrap().to_string(),
             });
         }
 
@@ -483,7 +1454,7 @@ impl Database {
         };
     }
 
-    /// Get the [`UserFollow`]s that the given `user` is following
+    /// Get the [`Follow`]s that the given `user` is following
     ///
     /// # Arguments:
     /// * `user` - username of user to check
@@ -492,16 +1463,16 @@ impl Database {
         &self,
         user: String,
         offset: Option<i32>,
-    ) -> DefaultReturn<Option<Vec<Log>>> {
+    ) -> DefaultReturn<Option<Vec<Follow>>> {
         let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "SELECT * FROM \"Logs\" WHERE \"content\" LIKE ? AND \"logtype\" = 'follow' ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET ?"
+            "SELECT * FROM \"gup_follows\" WHERE \"follower\" = ? ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET ?"
         } else {
-            "SELECT * FROM \"Logs\" WHERE \"content\" LIKE $1 AND \"logtype\" = 'follow' ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET $2"
+            "SELECT * FROM \"gup_follows\" WHERE \"follower\" = $1 ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET $2"
         };
 
         let c = &self.base.db.client;
         let res = sqlquery(query)
-            .bind::<&String>(&format!("%\"user\":\"{user}\"%"))
+            .bind::<&String>(&user)
             .bind(if offset.is_some() { offset.unwrap() } else { 0 })
             .fetch_all(c)
             .await;
@@ -516,15 +1487,14 @@ impl Database {
 
         // ...
         let rows = res.unwrap();
-        let mut output: Vec<Log> = Vec::new();
+        let mut output: Vec<Follow> = Vec::new();
 
         for row in rows {
             let row = self.base.textify_row(row).data;
-            output.push(Log {
-                id: row.get("id").unwrap().to_string(),
-                logtype: row.get("logtype").unwrap().to_string(),
+            output.push(Follow {
+                follower: row.get("follower").unwrap().to_string(),
+                following: row.get("following").unwrap().to_string(),
                 timestamp: row.get("timestamp").unwrap().parse::<u128>().unwrap(),
-                content: row.get("content").unwrap().to_string(),
             });
         }
 
@@ -541,18 +1511,31 @@ impl Database {
     /// # Arguments:
     /// * `user` - username of user to check
     pub async fn get_user_follow_count(&self, user: String) -> DefaultReturn<usize> {
-        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "SELECT * FROM \"Logs\" WHERE \"content\" LIKE ? AND \"logtype\" = 'follow'"
-        } else {
-            "SELECT * FROM \"Logs\" WHERE \"content\" LIKE $1 AND \"logtype\" = 'follow'"
-        };
-
-        let c = &self.base.db.client;
-        let res = sqlquery(query)
-            .bind::<&String>(&format!("%\"is_following\":\"{user}\"%"))
-            .fetch_all(c)
+        // check in cache
+        let cached = self
+            .base
+            .cachedb
+            .get(format!("social:follower-count:{}", user))
             .await;
 
+        if let Some(count) = cached {
+            return DefaultReturn {
+                success: true,
+                message: String::from("Follow exists (cache)"),
+                payload: count.parse::<usize>().unwrap_or(0),
+            };
+        }
+
+        // ...
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT COUNT(*) FROM \"gup_follows\" WHERE \"following\" = ?"
+        } else {
+            "SELECT COUNT(*) FROM \"gup_follows\" WHERE \"following\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query).bind::<&String>(&user).fetch_one(c).await;
+
         if res.is_err() {
             return DefaultReturn {
                 success: false,
@@ -562,14 +1545,20 @@ impl Database {
         }
 
         // ...
-        let rows = res.unwrap();
+        let count = res.unwrap().get::<i64, _>(0) as usize;
+
+        // store in cache
+        self.base
+            .cachedb
+            .set(format!("social:follower-count:{}", user), count.to_string())
+            .await;
 
         // return
-        return DefaultReturn {
+        DefaultReturn {
             success: true,
             message: String::from("Follow exists"),
-            payload: rows.len(),
-        };
+            payload: count,
+        }
     }
 
     /// Get the amount of users a user is following
@@ -577,17 +1566,30 @@ impl Database {
     /// # Arguments:
     /// * `user` - username of user to check
     pub async fn get_user_following_count(&self, user: String) -> DefaultReturn<usize> {
+        // check in cache
+        let cached = self
+            .base
+            .cachedb
+            .get(format!("social:following-count:{}", user))
+            .await;
+
+        if let Some(count) = cached {
+            return DefaultReturn {
+                success: true,
+                message: String::from("Follow exists (cache)"),
+                payload: count.parse::<usize>().unwrap_or(0),
+            };
+        }
+
+        // ...
         let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "SELECT * FROM \"Logs\" WHERE \"content\" LIKE ? AND \"logtype\" = 'follow'"
+            "SELECT COUNT(*) FROM \"gup_follows\" WHERE \"follower\" = ?"
         } else {
-            "SELECT * FROM \"Logs\" WHERE \"content\" LIKE $1 AND \"logtype\" = 'follow'"
+            "SELECT COUNT(*) FROM \"gup_follows\" WHERE \"follower\" = $1"
         };
 
         let c = &self.base.db.client;
-        let res = sqlquery(query)
-            .bind::<&String>(&format!("%\"user\":\"{user}\"%"))
-            .fetch_all(c)
-            .await;
+        let res = sqlquery(query).bind::<&String>(&user).fetch_one(c).await;
 
         if res.is_err() {
             return DefaultReturn {
@@ -598,14 +1600,81 @@ impl Database {
         }
 
         // ...
-        let rows = res.unwrap();
+        let count = res.unwrap().get::<i64, _>(0) as usize;
+
+        // store in cache
+        self.base
+            .cachedb
+            .set(
+                format!("social:following-count:{}", user),
+                count.to_string(),
+            )
+            .await;
 
         // return
-        return DefaultReturn {
+        DefaultReturn {
             success: true,
             message: String::from("Follow exists"),
-            payload: rows.len(),
-        };
+            payload: count,
+        }
+    }
+
+    /// Recompute every cached follower and following count from the "gup_follows" table
+    ///
+    /// Returns the number of counters that were written.
+    pub async fn repair_follow_counts(&self) -> DefaultReturn<usize> {
+        // clear existing counters, users with no follows will be counted again when they're viewed
+        self.base
+            .cachedb
+            .remove_starting_with(String::from("social:follower-count:*"))
+            .await;
+
+        self.base
+            .cachedb
+            .remove_starting_with(String::from("social:following-count:*"))
+            .await;
+
+        // ...
+        let c = &self.base.db.client;
+        let mut written: usize = 0;
+
+        for (column, prefix) in [
+            ("following", "social:follower-count"),
+            ("follower", "social:following-count"),
+        ] {
+            let res = sqlquery(&format!(
+                "SELECT \"{column}\", COUNT(*) FROM \"gup_follows\" GROUP BY \"{column}\""
+            ))
+            .fetch_all(c)
+            .await;
+
+            if res.is_err() {
+                return DefaultReturn {
+                    success: false,
+                    message: res.err().unwrap().to_string(),
+                    payload: written,
+                };
+            }
+
+            for row in res.unwrap() {
+                let user = row.get::<String, _>(0);
+                let count = row.get::<i64, _>(1);
+
+                self.base
+                    .cachedb
+                    .set(format!("{prefix}:{user}"), count.to_string())
+                    .await;
+
+                written += 1;
+            }
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Follow counts repaired"),
+            payload: written,
+        }
     }
 
     // SET
@@ -651,32 +1720,125 @@ impl Database {
         }
 
         // check if follow exists
-        let existing: DefaultReturn<Option<Log>> = self
+        let existing: DefaultReturn<Option<Follow>> = self
             .get_follow_by_user(p.user.to_owned(), p.is_following.to_owned())
             .await;
 
+        let c = &self.base.db.client;
+
         if existing.success {
-            // delete log and return
-            if let Err(e) = self.logs.delete_log(existing.payload.unwrap().id).await {
-                return e.into();
+            // delete follow and return
+            let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+            {
+                "DELETE FROM \"gup_follows\" WHERE \"follower\" = ? AND \"following\" = ?"
+            } else {
+                "DELETE FROM \"gup_follows\" WHERE \"follower\" = $1 AND \"following\" = $2"
+            };
+
+            let res = sqlquery(query)
+                .bind::<&String>(&p.user)
+                .bind::<&String>(&p.is_following)
+                .execute(c)
+                .await;
+
+            if res.is_err() {
+                return DefaultReturn {
+                    success: false,
+                    message: res.err().unwrap().to_string(),
+                    payload: Option::None,
+                };
             }
-        }
 
-        // return
-        match self
-            .logs
-            .create_log(
-                String::from("follow"),
-                serde_json::to_string::<UserFollow>(&p).unwrap(),
-            )
-            .await
-        {
-            Ok(_) => DefaultReturn {
+            // update counters and the follower's timeline
+            self.update_follow_counts(&p.user, &p.is_following, false)
+                .await;
+
+            self.base
+                .cachedb
+                .remove(format!("user-timeline:{}", p.user))
+                .await;
+
+            // following again shouldn't notify the user twice
+            self.delete_unread_notification(&p.is_following, "follow", &p.user, "")
+                .await;
+
+            return DefaultReturn {
                 success: true,
-                message: String::from("Log created"),
+                message: String::from("User unfollowed"),
                 payload: Some(String::new()),
-            },
-            Err(e) => e.into(),
+            };
+        }
+
+        // create follow
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_follows\" VALUES (?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_follows\" VALUES ($1, $2, $3)"
+        };
+
+        let res = sqlquery(query)
+            .bind::<&String>(&p.user)
+            .bind::<&String>(&p.is_following)
+            .bind::<&String>(&dorsal::utility::unix_epoch_timestamp().to_string())
+            .execute(c)
+            .await;
+
+        if res.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: res.err().unwrap().to_string(),
+                payload: Option::None,
+            };
+        }
+
+        // update counters and the follower's timeline
+        self.update_follow_counts(&p.user, &p.is_following, true)
+            .await;
+
+        self.base
+            .cachedb
+            .remove(format!("user-timeline:{}", p.user))
+            .await;
+
+        // notify
+        self.create_notification(
+            p.is_following.clone(),
+            String::from("follow"),
+            p.user.clone(),
+            String::new(),
+        )
+        .await;
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("User followed"),
+            payload: Some(String::new()),
+        }
+    }
+
+    /// Update the cached follow counters of both users in a follow
+    ///
+    /// Counters that aren't cached yet are left alone, they'll be counted from the database when they're next read.
+    ///
+    /// # Arguments:
+    /// * `follower` - username of the user following
+    /// * `following` - username of the user being followed
+    /// * `followed` - if the follow was created (`false` if it was removed)
+    async fn update_follow_counts(&self, follower: &String, following: &String, followed: bool) {
+        for key in [
+            format!("social:following-count:{}", follower),
+            format!("social:follower-count:{}", following),
+        ] {
+            if self.base.cachedb.get(key.clone()).await.is_none() {
+                continue;
+            }
+
+            if followed {
+                self.base.cachedb.incr(key).await;
+            } else {
+                self.base.cachedb.decr(key).await;
+            }
         }
     }
 
@@ -782,15 +1944,7 @@ impl Database {
         let mut output: Vec<ActivityPost> = Vec::new();
 
         for row in rows {
-            let row = self.base.textify_row(row).data;
-            output.push(ActivityPost {
-                id: row.get("id").unwrap().to_string(),
-                content: row.get("content").unwrap().to_string(),
-                content_html: row.get("content_html").unwrap().to_string(),
-                author: row.get("author").unwrap().to_string(),
-                reply: row.get("reply").unwrap().to_string(),
-                timestamp: row.get("timestamp").unwrap().parse::<u128>().unwrap(),
-            });
+            output.push(post_from_row(&row));
         }
 
         // store in cache
@@ -832,60 +1986,49 @@ impl Database {
         };
     }
 
-    /// Get all posts replying to another post by the `id` of the original post
+    /// Get the latest posts from every user
+    ///
+    /// Posts by banned users are not included.
     ///
     /// # Arguments:
-    /// * `id` - post id
-    /// * `run_existing_check` - if we should check that the log exists first
-    pub async fn get_post_replies(
+    /// * `offset` - optional value representing the SQL fetch offset
+    pub async fn get_public_activity(
         &self,
-        id: String,
-        run_existing_check: bool,
-    ) -> DefaultReturn<Option<Vec<ActivityPost>>> {
-        // make sure post exists
-        if run_existing_check != false {
-            let existing: DefaultReturn<Option<ActivityPost>> =
-                self.get_post_by_id(id.to_owned()).await;
-
-            if existing.success == false {
-                return DefaultReturn {
-                    success: false,
-                    message: String::from("Post does not exist"),
-                    payload: Option::None,
-                };
-            }
-        }
+        offset: Option<i32>,
+    ) -> DefaultReturn<Option<Vec<(ActivityPost, Vec<ActivityPost>, i32)>>> {
+        let offset = offset.unwrap_or(0);
 
         // check in cache
-        let cached = self.base.cachedb.get(format!("post-replies:{}", id)).await;
+        let cached = self
+            .base
+            .cachedb
+            .get(format!("public-posts:offset{}", offset))
+            .await;
 
-        if cached.is_some() {
-            // ...
-            let posts =
-                serde_json::from_str::<Vec<ActivityPost>>(cached.unwrap().as_str()).unwrap();
+        if let Some(cached) = cached {
+            let posts = serde_json::from_str::<Vec<ActivityPost>>(cached.as_str()).unwrap();
 
-            // ...
             return DefaultReturn {
                 success: true,
                 message: String::from("Successfully fetched posts"),
-                payload: Option::Some(posts),
+                payload: Option::Some(self.expand_posts(posts).await),
             };
         }
 
         // ...
         let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "SELECT * FROM \"gup_posts\" WHERE \"reply\" = ? ORDER BY \"timestamp\" DESC LIMIT 100"
+            "SELECT * FROM \"gup_posts\" WHERE \"reply\" = '' AND \"author\" NOT IN (SELECT \"username\" FROM \"Users\" WHERE \"role\" = 'banned') ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET ?"
         } else {
-            "SELECT * FROM \"gup_posts\" WHERE \"reply\" = $1 ORDER BY \"timestamp\" DESC LIMIT 100"
+            "SELECT * FROM \"gup_posts\" WHERE \"reply\" = '' AND \"author\" NOT IN (SELECT \"username\" FROM \"Users\" WHERE \"role\" = 'banned') ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET $1"
         };
 
         let c = &self.base.db.client;
-        let res = sqlquery(query).bind::<&String>(&id).fetch_all(c).await;
+        let res = sqlquery(query).bind(offset).fetch_all(c).await;
 
         if res.is_err() {
             return DefaultReturn {
                 success: false,
-                message: String::from("Failed to fetch replies"),
+                message: String::from("Failed to fetch posts"),
                 payload: Option::None,
             };
         }
@@ -895,74 +2038,295 @@ impl Database {
         let mut output: Vec<ActivityPost> = Vec::new();
 
         for row in rows {
-            let row = self.base.textify_row(row).data;
-            output.push(ActivityPost {
-                id: row.get("id").unwrap().to_string(),
-                content: row.get("content").unwrap().to_string(),
-                content_html: row.get("content_html").unwrap().to_string(),
-                author: row.get("author").unwrap().to_string(),
-                reply: row.get("reply").unwrap().to_string(),
-                timestamp: row.get("timestamp").unwrap().parse::<u128>().unwrap(),
-            });
+            output.push(post_from_row(&row));
         }
 
         // store in cache
         self.base
             .cachedb
             .set(
-                format!("post-replies:{}", id),
+                format!("public-posts:offset{}", offset),
                 serde_json::to_string::<Vec<ActivityPost>>(&output).unwrap(),
             )
             .await;
 
         // return
-        return DefaultReturn {
+        DefaultReturn {
             success: true,
-            message: String::from("Successfully fetched replies"),
-            payload: Option::Some(output),
-        };
+            message: String::from("Successfully fetched posts"),
+            payload: Option::Some(self.expand_posts(output).await),
+        }
     }
 
-    /// Get all posts replying to another post by the `id` of the original post
+    /// Get the timeline of a user, made of the posts of every user they follow
     ///
     /// # Arguments:
-    /// * `id` - post id
-    /// * `run_existing_check` - if we should check that the log exists first
-    pub async fn get_post_replies_full(
+    /// * `username` - [`String`]
+    /// * `before` - optional timestamp, only posts created before this are returned
+    /// * `before_id` - optional post ID, also returns posts created at `before` with a lower ID
+    ///   (the last post of the previous page, so posts sharing its timestamp aren't skipped)
+    pub async fn get_user_timeline(
         &self,
-        id: String,
-        run_existing_check: bool,
+        username: String,
+        before: Option<u64>,
+        before_id: Option<String>,
     ) -> DefaultReturn<Option<Vec<(ActivityPost, Vec<ActivityPost>, i32)>>> {
-        // make sure post exists
-        if run_existing_check != false {
-            let existing: DefaultReturn<Option<ActivityPost>> =
-                self.get_post_by_id(id.to_owned()).await;
+        let before_id = before_id.unwrap_or_default();
 
-            if existing.success == false {
-                return DefaultReturn {
-                    success: false,
-                    message: String::from("Post does not exist"),
-                    payload: Option::None,
-                };
-            }
-        }
+        // check in cache, every page of a timeline is a field of the same hash so the whole
+        // timeline can be cleared with a single DEL
+        let cache_key = format!("user-timeline:{}", username);
+        let cache_field = format!("before{}:{}", before.unwrap_or(0), before_id);
 
-        // check in cache
-        let cached = self.base.cachedb.get(format!("post-replies:{}", id)).await;
+        let cached: Option<String> = match self.base.cachedb.client.get_connection() {
+            Ok(mut con) => con.hget(&cache_key, &cache_field).unwrap_or(None),
+            Err(_) => None,
+        };
 
-        if cached.is_some() {
-            // ...
-            let posts =
-                serde_json::from_str::<Vec<ActivityPost>>(cached.unwrap().as_str()).unwrap();
+        if let Some(cached) = cached {
+            let posts = serde_json::from_str::<Vec<ActivityPost>>(cached.as_str()).unwrap();
 
-            // get replies
-            let mut true_output: Vec<(ActivityPost, Vec<ActivityPost>, i32)> = Vec::new();
-            for post in posts {
-                let mut replies_out = Vec::new();
-                let post_id = post.clone().id;
+            return DefaultReturn {
+                success: true,
+                message: String::from("Successfully fetched posts"),
+                payload: Option::Some(self.expand_posts(posts).await),
+            };
+        }
 
-                // get replies
-                let replies = &self.get_post_replies(post_id.clone(), false).await;
+        // ...
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_posts\" WHERE \"reply\" = '' AND (\"timestamp\" < ? OR (\"timestamp\" = ? AND \"id\" < ?)) AND \"author\" IN (SELECT \"following\" FROM \"gup_follows\" WHERE \"follower\" = ?) ORDER BY \"timestamp\" DESC, \"id\" DESC LIMIT 50"
+        } else {
+            "SELECT * FROM \"gup_posts\" WHERE \"reply\" = '' AND (\"timestamp\" < $1 OR (\"timestamp\" = $2 AND \"id\" < $3)) AND \"author\" IN (SELECT \"following\" FROM \"gup_follows\" WHERE \"follower\" = $4) ORDER BY \"timestamp\" DESC, \"id\" DESC LIMIT 50"
+        };
+
+        let before = match before {
+            Some(b) => b as i64,
+            None => dorsal::utility::unix_epoch_timestamp() as i64 + 1,
+        };
+
+        // no ID is lower than an empty one, so without `before_id` only `before` is checked
+        let c = &self.base.db.client;
+        let res = sqlquery(query)
+            .bind::<i64>(before)
+            .bind::<i64>(before)
+            .bind::<&String>(&before_id)
+            .bind::<&String>(&username)
+            .fetch_all(c)
+            .await;
+
+        if res.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Failed to fetch posts"),
+                payload: Option::None,
+            };
+        }
+
+        // ...
+        let rows = res.unwrap();
+        let mut output: Vec<ActivityPost> = Vec::new();
+
+        for row in rows {
+            output.push(post_from_row(&row));
+        }
+
+        // store in cache
+        if let Ok(mut con) = self.base.cachedb.client.get_connection() {
+            let _: redis::RedisResult<i64> = con.hset(
+                cache_key,
+                cache_field,
+                serde_json::to_string::<Vec<ActivityPost>>(&output).unwrap(),
+            );
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Successfully fetched posts"),
+            payload: Option::Some(self.expand_posts(output).await),
+        }
+    }
+
+    /// Get the replies and favorites count of each post in `posts`
+    ///
+    /// Only posts are stored in feed caches because replies and favorites are cached elsewhere.
+    async fn expand_posts(
+        &self,
+        posts: Vec<ActivityPost>,
+    ) -> Vec<(ActivityPost, Vec<ActivityPost>, i32)> {
+        let mut output: Vec<(ActivityPost, Vec<ActivityPost>, i32)> = Vec::new();
+
+        for post in posts {
+            let replies = self
+                .get_post_replies(post.id.clone(), false)
+                .await
+                .payload
+                .unwrap_or_default();
+
+            let favorites = self.get_post_favorites(post.id.clone()).await.payload;
+            output.push((post, replies, favorites));
+        }
+
+        output
+    }
+
+    /// Clear the cached timeline of every user following `author`
+    ///
+    /// # Arguments:
+    /// * `author` - username of the user that created or deleted a post
+    async fn clear_follower_timelines(&self, author: &String) {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT \"follower\" FROM \"gup_follows\" WHERE \"following\" = ?"
+        } else {
+            "SELECT \"follower\" FROM \"gup_follows\" WHERE \"following\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let rows = match sqlquery(query).bind::<&String>(author).fetch_all(c).await {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+
+        if rows.is_empty() {
+            return;
+        }
+
+        // one DEL for every follower, each timeline is a single hash
+        let mut cmd = redis::cmd("DEL");
+
+        for row in rows {
+            cmd.arg(format!("user-timeline:{}", row.get::<String, _>(0)));
+        }
+
+        if let Ok(mut con) = self.base.cachedb.client.get_connection() {
+            let _: redis::RedisResult<i64> = cmd.query(&mut con);
+        }
+    }
+
+    /// Get all posts replying to another post by the `id` of the original post
+    ///
+    /// # Arguments:
+    /// * `id` - post id
+    /// * `run_existing_check` - if we should check that the log exists first
+    pub async fn get_post_replies(
+        &self,
+        id: String,
+        run_existing_check: bool,
+    ) -> DefaultReturn<Option<Vec<ActivityPost>>> {
+        // make sure post exists
+        if run_existing_check != false {
+            let existing: DefaultReturn<Option<ActivityPost>> =
+                self.get_post_by_id(id.to_owned()).await;
+
+            if existing.success == false {
+                return DefaultReturn {
+                    success: false,
+                    message: String::from("Post does not exist"),
+                    payload: Option::None,
+                };
+            }
+        }
+
+        // check in cache
+        let cached = self.base.cachedb.get(format!("post-replies:{}", id)).await;
+
+        if cached.is_some() {
+            // ...
+            let posts =
+                serde_json::from_str::<Vec<ActivityPost>>(cached.unwrap().as_str()).unwrap();
+
+            // ...
+            return DefaultReturn {
+                success: true,
+                message: String::from("Successfully fetched posts"),
+                payload: Option::Some(posts),
+            };
+        }
+
+        // ...
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_posts\" WHERE \"reply\" = ? ORDER BY \"timestamp\" DESC LIMIT 100"
+        } else {
+            "SELECT * FROM \"gup_posts\" WHERE \"reply\" = $1 ORDER BY \"timestamp\" DESC LIMIT 100"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query).bind::<&String>(&id).fetch_all(c).await;
+
+        if res.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Failed to fetch replies"),
+                payload: Option::None,
+            };
+        }
+
+        // ...
+        let rows = res.unwrap();
+        let mut output: Vec<ActivityPost> = Vec::new();
+
+        for row in rows {
+            output.push(post_from_row(&row));
+        }
+
+        // store in cache
+        self.base
+            .cachedb
+            .set(
+                format!("post-replies:{}", id),
+                serde_json::to_string::<Vec<ActivityPost>>(&output).unwrap(),
+            )
+            .await;
+
+        // return
+        return DefaultReturn {
+            success: true,
+            message: String::from("Successfully fetched replies"),
+            payload: Option::Some(output),
+        };
+    }
+
+    /// Get all posts replying to another post by the `id` of the original post
+    ///
+    /// # Arguments:
+    /// * `id` - post id
+    /// * `run_existing_check` - if we should check that the log exists first
+    pub async fn get_post_replies_full(
+        &self,
+        id: String,
+        run_existing_check: bool,
+    ) -> DefaultReturn<Option<Vec<(ActivityPost, Vec<ActivityPost>, i32)>>> {
+        // make sure post exists
+        if run_existing_check != false {
+            let existing: DefaultReturn<Option<ActivityPost>> =
+                self.get_post_by_id(id.to_owned()).await;
+
+            if existing.success == false {
+                return DefaultReturn {
+                    success: false,
+                    message: String::from("Post does not exist"),
+                    payload: Option::None,
+                };
+            }
+        }
+
+        // check in cache
+        let cached = self.base.cachedb.get(format!("post-replies:{}", id)).await;
+
+        if cached.is_some() {
+            // ...
+            let posts =
+                serde_json::from_str::<Vec<ActivityPost>>(cached.unwrap().as_str()).unwrap();
+
+            // get replies
+            let mut true_output: Vec<(ActivityPost, Vec<ActivityPost>, i32)> = Vec::new();
+            for post in posts {
+                let mut replies_out = Vec::new();
+                let post_id = post.clone().id;
+
+                // get replies
+                let replies = &self.get_post_replies(post_id.clone(), false).await;
 
                 if replies.payload.is_some() {
                     for reply in replies.payload.clone().unwrap() {
@@ -1009,15 +2373,7 @@ impl Database {
         let mut output: Vec<ActivityPost> = Vec::new();
 
         for row in rows {
-            let row = self.base.textify_row(row).data;
-            output.push(ActivityPost {
-                id: row.get("id").unwrap().to_string(),
-                content: row.get("content").unwrap().to_string(),
-                content_html: row.get("content_html").unwrap().to_string(),
-                author: row.get("author").unwrap().to_string(),
-                reply: row.get("reply").unwrap().to_string(),
-                timestamp: row.get("timestamp").unwrap().parse::<u128>().unwrap(),
-            });
+            output.push(post_from_row(&row));
         }
 
         // store in cache
@@ -1098,18 +2454,9 @@ impl Database {
         }
 
         // ...
-        let row = res.unwrap();
-        let row = self.base.textify_row(row).data;
+        let post = post_from_row(&res.unwrap());
 
         // store in cache
-        let post = ActivityPost {
-            id: row.get("id").unwrap().to_string(),
-            content: row.get("content").unwrap().to_string(),
-            content_html: row.get("content_html").unwrap().to_string(),
-            author: row.get("author").unwrap().to_string(),
-            reply: row.get("reply").unwrap().to_string(),
-            timestamp: row.get("timestamp").unwrap().parse::<u128>().unwrap(),
-        };
 
         self.base
             .cachedb
@@ -1127,6 +2474,50 @@ impl Database {
         };
     }
 
+    /// Get all [`PostRevision`]s of an [`ActivityPost`], newest first
+    ///
+    /// # Arguments:
+    /// * `id` - post id
+    pub async fn get_post_revisions(&self, id: String) -> DefaultReturn<Option<Vec<PostRevision>>> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_post_revisions\" WHERE \"post\" = ? ORDER BY \"timestamp\" DESC"
+        } else {
+            "SELECT * FROM \"gup_post_revisions\" WHERE \"post\" = $1 ORDER BY \"timestamp\" DESC"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query).bind::<&String>(&id).fetch_all(c).await;
+
+        if res.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Failed to fetch revisions"),
+                payload: Option::None,
+            };
+        }
+
+        // ...
+        let rows = res.unwrap();
+        let mut output: Vec<PostRevision> = Vec::new();
+
+        for row in rows {
+            output.push(PostRevision {
+                id: row.get("id"),
+                post: row.get("post"),
+                content: row.get("content"),
+                content_html: row.get("content_html"),
+                timestamp: row.get::<i64, _>("timestamp") as u128,
+            });
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Successfully fetched revisions"),
+            payload: Option::Some(output),
+        }
+    }
+
     // SET
     /// Create a new [`ActivityPost`]
     ///
@@ -1163,16 +2554,19 @@ impl Database {
         }
 
         // create post
+        let mentions = self.resolve_mentions(&p.content).await;
         let post = ActivityPost {
             id: dorsal::utility::random_id(),
             author: p.author.clone(), // posts can only be created by user accounts
             content: p.content.clone(),
-            content_html: crate::markup::render(&p.content),
+            content_html: crate::markup::render_post(&p.content, &mentions),
             reply: p.reply.clone(),
             timestamp: dorsal::utility::unix_epoch_timestamp(),
         };
 
         // update cache
+        let mut replying_to_author = String::new();
+
         if p.reply.is_empty() {
             // clear author activity feed
             self.base
@@ -1187,6 +2581,8 @@ impl Database {
                 return replying_to;
             }
 
+            replying_to_author = replying_to.payload.unwrap().author;
+
             self.base
                 .cachedb
                 .remove(format!("post-replies:{}", p.reply))
@@ -1207,7 +2603,7 @@ impl Database {
             .bind::<&String>(&post.content)
             .bind::<&String>(&post.content_html)
             .bind::<&String>(&post.reply)
-            .bind::<&String>(&post.timestamp.to_string())
+            .bind::<i64>(post.timestamp as i64)
             .execute(c)
             .await;
 
@@ -1219,6 +2615,47 @@ impl Database {
             };
         }
 
+        // store mentions and tags
+        let mentioned = self.sync_post_mentions(&post, &mentions).await;
+        self.sync_post_tags(&post).await;
+        self.index_post(&post).await;
+
+        // notify
+        if !replying_to_author.is_empty() {
+            self.create_notification(
+                replying_to_author.clone(),
+                String::from("reply"),
+                post.author.clone(),
+                post.id.clone(),
+            )
+            .await;
+        }
+
+        for username in mentioned {
+            // the author of the post being replied to was already notified
+            if username == replying_to_author {
+                continue;
+            }
+
+            self.create_notification(
+                username,
+                String::from("mention"),
+                post.author.clone(),
+                post.id.clone(),
+            )
+            .await;
+        }
+
+        // clear the public feed and the timelines of everyone following the author
+        if post.reply.is_empty() {
+            self.base
+                .cachedb
+                .remove_starting_with(String::from("public-posts:*"))
+                .await;
+
+            self.clear_follower_timelines(&post.author).await;
+        }
+
         // return
         return DefaultReturn {
             success: true,
@@ -1227,16 +2664,176 @@ impl Database {
         };
     }
 
-    /// Delete an existing [`ActivityPost`]
+    /// Edit an existing [`ActivityPost`]
+    ///
+    /// The previous content of the post is kept as a [`PostRevision`].
     ///
     /// # Arguments:
     /// * `id` - post id
-    /// * `as_user` - The username of the user creating the post
-    pub async fn delete_activity_post(
+    /// * `props` - [`PEditPost`]
+    /// * `as_user` - The username of the user editing the post
+    pub async fn edit_activity_post(
         &self,
         id: String,
+        props: PEditPost,
         as_user: Option<String>,
-    ) -> DefaultReturn<bool> {
+    ) -> DefaultReturn<Option<ActivityPost>> {
+        // (check length)
+        if (props.content.len() < 2) | (props.content.len() > 500) {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Content is invalid"),
+                payload: Option::None,
+            };
+        }
+
+        // make sure post exists
+        let existing = self.get_post_by_id(id.clone()).await;
+
+        if !existing.success {
+            return existing;
+        }
+
+        let existing = existing.payload.unwrap();
+
+        // only the author can edit a post
+        if as_user.is_none() || (as_user.unwrap() != existing.author) {
+            return DefaultReturn {
+                success: false,
+                message: String::from("You do not have permission to do this."),
+                payload: Option::None,
+            };
+        }
+
+        let mentions = self.resolve_mentions(&props.content).await;
+
+        // store revision and update post, in one transaction so an edit is never lost
+        let c = &self.base.db.client;
+        let mut tx = match c.begin().await {
+            Ok(tx) => tx,
+            Err(e) => {
+                return DefaultReturn {
+                    success: false,
+                    message: e.to_string(),
+                    payload: Option::None,
+                }
+            }
+        };
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_post_revisions\" VALUES (?, ?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_post_revisions\" VALUES ($1, $2, $3, $4, $5)"
+        };
+
+        let timestamp = dorsal::utility::unix_epoch_timestamp();
+
+        let res = sqlquery(query)
+            .bind::<&String>(&dorsal::utility::random_id())
+            .bind::<&String>(&existing.id)
+            .bind::<&String>(&existing.content)
+            .bind::<&String>(&existing.content_html)
+            .bind(timestamp as i64)
+            .execute(&mut *tx)
+            .await;
+
+        if res.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: res.err().unwrap().to_string(),
+                payload: Option::None,
+            };
+        }
+
+        let post = ActivityPost {
+            content: props.content.clone(),
+            content_html: crate::markup::render_post(&props.content, &mentions),
+            ..existing
+        };
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "UPDATE \"gup_posts\" SET \"content\" = ?, \"content_html\" = ? WHERE \"id\" = ?"
+        } else {
+            "UPDATE \"gup_posts\" SET (\"content\", \"content_html\") = ($1, $2) WHERE \"id\" = $3"
+        };
+
+        let res = sqlquery(query)
+            .bind::<&String>(&post.content)
+            .bind::<&String>(&post.content_html)
+            .bind::<&String>(&post.id)
+            .execute(&mut *tx)
+            .await;
+
+        if res.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: res.err().unwrap().to_string(),
+                payload: Option::None,
+            };
+        }
+
+        if let Err(e) = tx.commit().await {
+            return DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            };
+        }
+
+        // update tags, mentions and search
+        self.sync_post_tags(&post).await;
+        self.index_post(&post).await;
+
+        for username in self.sync_post_mentions(&post, &mentions).await {
+            self.create_notification(
+                username,
+                String::from("mention"),
+                post.author.clone(),
+                post.id.clone(),
+            )
+            .await;
+        }
+
+        // update cache
+        self.base.cachedb.remove(format!("post:{}", id)).await;
+
+        if post.reply.is_empty() {
+            self.base
+                .cachedb
+                .remove_starting_with(format!("user-posts:{}:offset*", post.author.to_lowercase()))
+                .await;
+
+            self.base
+                .cachedb
+                .remove_starting_with(String::from("public-posts:*"))
+                .await;
+
+            self.clear_follower_timelines(&post.author).await;
+        } else {
+            self.base
+                .cachedb
+                .remove(format!("post-replies:{}", post.reply))
+                .await;
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Post edited"),
+            payload: Option::Some(post),
+        }
+    }
+
+    /// Delete an existing [`ActivityPost`]
+    ///
+    /// # Arguments:
+    /// * `id` - post id
+    /// * `as_user` - The username of the user creating the post
+    pub async fn delete_activity_post(
+        &self,
+        id: String,
+        as_user: Option<String>,
+    ) -> DefaultReturn<bool> {
         // make sure post exists
         let existing = self.get_post_by_id(id.clone()).await;
 
@@ -1283,6 +2880,16 @@ impl Database {
             }
         }
 
+        self.remove_activity_post(existing).await
+    }
+
+    /// Delete an existing [`ActivityPost`] without checking who is deleting it
+    ///
+    /// # Arguments:
+    /// * `existing` - the post to delete
+    pub async fn remove_activity_post(&self, existing: ActivityPost) -> DefaultReturn<bool> {
+        let id = existing.id.clone();
+
         // delete
         let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
             "DELETE FROM \"gup_posts\" WHERE \"id\" = ?"
@@ -1301,8 +2908,46 @@ impl Database {
             };
         }
 
+        // delete favorites
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_favorites\" WHERE \"post\" = ?"
+        } else {
+            "DELETE FROM \"gup_favorites\" WHERE \"post\" = $1"
+        };
+
+        let _ = sqlquery(query).bind::<&String>(&id).execute(c).await;
+
+        // delete revisions
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_post_revisions\" WHERE \"post\" = ?"
+        } else {
+            "DELETE FROM \"gup_post_revisions\" WHERE \"post\" = $1"
+        };
+
+        let _ = sqlquery(query).bind::<&String>(&id).execute(c).await;
+
+        // delete mentions
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_mentions\" WHERE \"post\" = ?"
+        } else {
+            "DELETE FROM \"gup_mentions\" WHERE \"post\" = $1"
+        };
+
+        let _ = sqlquery(query).bind::<&String>(&id).execute(c).await;
+
+        // delete tags
+        self.clear_post_tags(&id).await;
+        self.unindex_post(&id).await;
+
+        // delete notifications
+        self.delete_post_notifications(&id).await;
+
         // update cache
         self.base.cachedb.remove(format!("post:{}", id)).await;
+        self.base
+            .cachedb
+            .remove(format!("social:post-favorites:{}", id))
+            .await;
 
         match existing.reply.is_empty() {
             true => {
@@ -1311,6 +2956,13 @@ impl Database {
                     .cachedb
                     .remove_starting_with(format!("user-posts:{}:offset:*", existing.author))
                     .await;
+
+                self.base
+                    .cachedb
+                    .remove_starting_with(String::from("public-posts:*"))
+                    .await;
+
+                self.clear_follower_timelines(&existing.author).await;
             }
             false => {
                 // clear post replies
@@ -1329,184 +2981,3484 @@ impl Database {
         };
     }
 
-    // post favorites
+    // tags
 
     // GET
-    /// Get the number of [`PostFavoriteLog`]s an [`ActivityPost`] has
-    pub async fn get_post_favorites(&self, id: String) -> DefaultReturn<i32> {
-        // get post
-        let existing = self.get_post_by_id(id.clone()).await;
-
-        if existing.success == false {
-            return DefaultReturn {
-                success: false,
-                message: String::from("Post does not exist!"),
-                payload: 0,
-            };
-        }
+    /// Get the [`ActivityPost`]s that used the given tag, newest first
+    ///
+    /// # Arguments:
+    /// * `tag` - the tag, without the leading `#`
+    /// * `offset` - optional value representing the SQL fetch offset
+    pub async fn get_tagged_posts(
+        &self,
+        tag: String,
+        offset: Option<i32>,
+    ) -> DefaultReturn<Option<Vec<(ActivityPost, Vec<ActivityPost>, i32)>>> {
+        let tag = tag.to_lowercase();
+        let offset = offset.unwrap_or(0);
 
-        // get favorites
-        DefaultReturn {
-            success: true,
-            message: id.clone(),
-            // favorites are stored in the "Logs" table AS WELL AS an incremented value in the cache,
-            // we read the value from cache when checking the paste's favorites, but read the cache value when fetching number
-            payload: self
-                .base
-                .cachedb
-                .get(format!("social:post-favorites:{}", id))
-                .await
-                .unwrap_or(String::from("0"))
-                .parse::<i32>()
-                .unwrap(),
-        }
-    }
+        // check in cache
+        let cached = self
+            .base
+            .cachedb
+            .get(format!("tag-posts:{}:offset{}", tag, offset))
+            .await;
 
-    /// Check if a user has favorited a post
-    pub async fn get_user_post_favorite(
-        &self,
-        user: String,
-        post_id: String,
-        skip_existing_check: bool,
-    ) -> DefaultReturn<Option<Log>> {
-        // get paste
-        if skip_existing_check == false {
-            let existing = self.get_post_by_id(post_id.clone()).await;
+        if let Some(cached) = cached {
+            let posts = serde_json::from_str::<Vec<ActivityPost>>(cached.as_str()).unwrap();
 
-            if existing.success == false {
-                return DefaultReturn {
-                    success: false,
-                    message: String::from("Post does not exist!"),
-                    payload: Option::None,
-                };
-            }
+            return DefaultReturn {
+                success: true,
+                message: String::from("Successfully fetched posts"),
+                payload: Option::Some(self.expand_posts(posts).await),
+            };
         }
 
         // ...
         let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "SELECT * FROM \"Logs\" WHERE \"content\" = ? AND \"logtype\" = 'post_favorite'"
+            "SELECT * FROM \"gup_posts\" WHERE \"id\" IN (SELECT \"post\" FROM \"gup_tags\" WHERE \"tag\" = ?) AND \"author\" NOT IN (SELECT \"username\" FROM \"Users\" WHERE \"role\" = 'banned') ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET ?"
         } else {
-            "SELECT * FROM \"Logs\" WHERE \"content\" = $1 AND \"logtype\" = 'post_favorite'"
+            "SELECT * FROM \"gup_posts\" WHERE \"id\" IN (SELECT \"post\" FROM \"gup_tags\" WHERE \"tag\" = $1) AND \"author\" NOT IN (SELECT \"username\" FROM \"Users\" WHERE \"role\" = 'banned') ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET $2"
         };
 
         let c = &self.base.db.client;
         let res = sqlquery(query)
-            .bind::<&String>(
-                &serde_json::to_string::<PostFavoriteLog>(&PostFavoriteLog {
-                    user,
-                    id: post_id.clone(),
-                })
-                .unwrap(),
-            )
-            .fetch_one(c)
+            .bind::<&String>(&tag)
+            .bind(offset)
+            .fetch_all(c)
             .await;
 
         if res.is_err() {
             return DefaultReturn {
                 success: false,
-                message: String::from(res.err().unwrap().to_string()),
+                message: String::from("Failed to fetch posts"),
                 payload: Option::None,
             };
         }
 
         // ...
-        let row = res.unwrap();
-        let row = self.base.textify_row(row).data;
+        let rows = res.unwrap();
+        let mut output: Vec<ActivityPost> = Vec::new();
+
+        for row in rows {
+            output.push(post_from_row(&row));
+        }
+
+        // store in cache
+        self.base
+            .cachedb
+            .set(
+                format!("tag-posts:{}:offset{}", tag, offset),
+                serde_json::to_string::<Vec<ActivityPost>>(&output).unwrap(),
+            )
+            .await;
 
+        // return
         DefaultReturn {
             success: true,
-            message: post_id,
-            payload: Option::Some(Log {
-                id: row.get("id").unwrap().to_string(),
-                logtype: row.get("logtype").unwrap().to_string(),
-                timestamp: row.get("timestamp").unwrap().parse::<u128>().unwrap(),
-                content: row.get("content").unwrap().to_string(),
-            }),
+            message: String::from("Successfully fetched posts"),
+            payload: Option::Some(self.expand_posts(output).await),
         }
     }
 
-    // SET
-    /// Toggle a [`PostFavoriteLog`] on a [`ActivityPost`] by `user` and `post_id`
-    pub async fn toggle_user_post_favorite(
-        &self,
-        user: String,
-        post_id: String,
-    ) -> DefaultReturn<Option<String>> {
-        // get paste
-        let existing = self.get_post_by_id(post_id.clone()).await;
+    /// Get the most used tags of the last `hours` hours
+    ///
+    /// # Arguments:
+    /// * `hours` - how far back to count tags from
+    pub async fn get_trending_tags(&self, hours: u64) -> DefaultReturn<Vec<TagCount>> {
+        let since = dorsal::utility::unix_epoch_timestamp()
+            .saturating_sub(u128::from(hours) * 60 * 60 * 1000);
 
-        if existing.success == false {
-            return DefaultReturn {
-                success: false,
-                message: String::from("Post does not exist!"),
-                payload: Option::None,
-            };
-        }
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT \"tag\", COUNT(*) FROM \"gup_tags\" WHERE \"timestamp\" > ? GROUP BY \"tag\" ORDER BY COUNT(*) DESC LIMIT 10"
+        } else {
+            "SELECT \"tag\", COUNT(*) FROM \"gup_tags\" WHERE \"timestamp\" > $1 GROUP BY \"tag\" ORDER BY COUNT(*) DESC LIMIT 10"
+        };
 
-        // check if user is paste owner
-        let existing = existing.payload.unwrap();
+        let c = &self.base.db.client;
+        let res = sqlquery(query)
+            .bind::<&String>(&since.to_string())
+            .fetch_all(c)
+            .await;
 
-        if existing.author == user {
-            return DefaultReturn {
+        match res {
+            Ok(rows) => DefaultReturn {
+                success: true,
+                message: String::from("Successfully fetched tags"),
+                payload: rows
+                    .iter()
+                    .map(|r| TagCount {
+                        tag: r.get::<String, _>(0),
+                        count: r.get::<i64, _>(1) as usize,
+                    })
+                    .collect(),
+            },
+            Err(e) => DefaultReturn {
                 success: false,
-                message: String::from("You're the post author!"),
-                payload: Option::None,
-            };
+                message: e.to_string(),
+                payload: Vec::new(),
+            },
         }
+    }
 
-        // attempt to get the user's existing favorite
-        let existing_favorite = self
-            .get_user_post_favorite(user.clone(), post_id.clone(), true)
-            .await;
-
-        // delete existing
-        if existing_favorite.success == true {
-            let payload = existing_favorite.payload.unwrap();
+    // SET
+    /// Replace the stored [`PostTag`]s of a post with the tags it currently uses
+    ///
+    /// # Arguments:
+    /// * `post` - the [`ActivityPost`] that was created or edited
+    async fn sync_post_tags(&self, post: &ActivityPost) {
+        self.clear_post_tags(&post.id).await;
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_tags\" VALUES (?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_tags\" VALUES ($1, $2, $3, $4)"
+        };
+
+        let c = &self.base.db.client;
+        for tag in crate::markup::find_tags(&post.content) {
+            let _ = sqlquery(query)
+                .bind::<&String>(&tag)
+                .bind::<&String>(&post.id)
+                .bind::<&String>(&post.author)
+                .bind::<&String>(&post.timestamp.to_string())
+                .execute(c)
+                .await;
 
-            // decr favorites
             self.base
                 .cachedb
-                .decr(format!("social:post-favorites:{}", post_id.clone()))
+                .remove_starting_with(format!("tag-posts:{}:*", tag))
                 .await;
-
-            // handle log
-            return match self.logs.delete_log(payload.id).await {
-                Ok(_) => DefaultReturn {
-                    success: true,
-                    message: String::from("Post unliked"),
-                    payload: Some(String::new()),
-                },
-                Err(e) => e.into(),
-            };
         }
-        // add new
-        else {
-            // incr favorites
+    }
+
+    /// Delete every [`PostTag`] of a post
+    ///
+    /// # Arguments:
+    /// * `post` - post id
+    async fn clear_post_tags(&self, post: &String) {
+        let c = &self.base.db.client;
+
+        // get the tags whose pages change
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT \"tag\" FROM \"gup_tags\" WHERE \"post\" = ?"
+        } else {
+            "SELECT \"tag\" FROM \"gup_tags\" WHERE \"post\" = $1"
+        };
+
+        let tags: Vec<String> = match sqlquery(query).bind::<&String>(post).fetch_all(c).await {
+            Ok(rows) => rows.iter().map(|r| r.get::<String, _>(0)).collect(),
+            Err(_) => Vec::new(),
+        };
+
+        // delete
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_tags\" WHERE \"post\" = ?"
+        } else {
+            "DELETE FROM \"gup_tags\" WHERE \"post\" = $1"
+        };
+
+        let _ = sqlquery(query).bind::<&String>(post).execute(c).await;
+
+        // update cache
+        for tag in tags {
             self.base
                 .cachedb
-                .incr(format!("social:post-favorites:{}", post_id.clone()))
+                .remove_starting_with(format!("tag-posts:{}:*", tag))
                 .await;
+        }
+    }
 
-            // handle log
-            return match self
-                .logs
-                .create_log(
-                    String::from("post_favorite"),
-                    serde_json::to_string::<PostFavoriteLog>(&PostFavoriteLog {
-                        user,
-                        id: post_id,
-                    })
-                    .unwrap(),
+    // search
+
+    // GET
+    /// Search users and posts
+    ///
+    /// Uses FTS5 on sqlite, `tsvector` on postgres and `FULLTEXT` indexes on mysql.
+    /// Results are ordered by how well they match.
+    ///
+    /// # Arguments:
+    /// * `query` - the text to search for
+    /// * `offset` - optional value representing the SQL fetch offset
+    pub async fn search(
+        &self,
+        query: String,
+        offset: Option<i32>,
+    ) -> DefaultReturn<Option<SearchResults>> {
+        let query = query.trim().to_string();
+
+        if query.is_empty() | (query.len() > 200) {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Query is invalid"),
+                payload: Option::None,
+            };
+        }
+
+        let users = self.search_users(&query, offset.unwrap_or(0)).await;
+
+        if !users.success {
+            return DefaultReturn {
+                success: false,
+                message: users.message,
+                payload: Option::None,
+            };
+        }
+
+        let posts = self.search_posts(&query, offset.unwrap_or(0)).await;
+
+        if !posts.success {
+            return DefaultReturn {
+                success: false,
+                message: posts.message,
+                payload: Option::None,
+            };
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Search complete"),
+            payload: Option::Some(SearchResults {
+                users: users.payload,
+                posts: self.expand_posts(posts.payload).await,
+            }),
+        }
+    }
+
+    /// Search the usernames, nicknames and abouts of users who aren't banned
+    async fn search_users(&self, query: &str, offset: i32) -> DefaultReturn<Vec<UserSearchResult>> {
+        let sql: &str = match self.base.db._type.as_str() {
+            "sqlite" => "SELECT \"username\", \"nickname\", \"about\" FROM \"gup_user_search\" WHERE \"gup_user_search\" MATCH ? AND \"username\" NOT IN (SELECT \"username\" FROM \"Users\" WHERE \"role\" = 'banned') ORDER BY \"rank\" LIMIT 50 OFFSET ?",
+            "mysql" => "SELECT \"username\", \"nickname\", \"about\" FROM \"gup_user_search\" WHERE MATCH (\"username\", \"nickname\", \"about\") AGAINST (? IN NATURAL LANGUAGE MODE) AND \"username\" NOT IN (SELECT \"username\" FROM \"Users\" WHERE \"role\" = 'banned') ORDER BY MATCH (\"username\", \"nickname\", \"about\") AGAINST (? IN NATURAL LANGUAGE MODE) DESC LIMIT 50 OFFSET ?",
+            _ => "SELECT \"username\", \"nickname\", \"about\" FROM \"gup_user_search\" WHERE to_tsvector('simple', \"username\" || ' ' || \"nickname\" || ' ' || \"about\") @@ websearch_to_tsquery('simple', $1) AND \"username\" NOT IN (SELECT \"username\" FROM \"Users\" WHERE \"role\" = 'banned') ORDER BY ts_rank(to_tsvector('simple', \"username\" || ' ' || \"nickname\" || ' ' || \"about\"), websearch_to_tsquery('simple', $1)) DESC LIMIT 50 OFFSET $2",
+        };
+
+        let query = if self.base.db._type == "sqlite" {
+            fts5_query(query)
+        } else {
+            query.to_string()
+        };
+
+        let mut sql = sqlquery(sql).bind::<&String>(&query);
+
+        if self.base.db._type == "mysql" {
+            // the query is used twice, once for filtering and once for ranking
+            sql = sql.bind::<&String>(&query);
+        }
+
+        let c = &self.base.db.client;
+        let res = sql.bind(offset).fetch_all(c).await;
+
+        if res.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Failed to search users"),
+                payload: Vec::new(),
+            };
+        }
+
+        // ...
+        let rows = res.unwrap();
+        let mut output: Vec<UserSearchResult> = Vec::new();
+
+        for row in rows {
+            let row = self.base.textify_row(row).data;
+            output.push(UserSearchResult {
+                username: row.get("username").unwrap().to_string(),
+                nickname: row.get("nickname").unwrap().to_string(),
+                about: row.get("about").unwrap().to_string(),
+            });
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Successfully searched users"),
+            payload: output,
+        }
+    }
+
+    /// Search the content of posts by users who aren't banned
+    async fn search_posts(&self, query: &str, offset: i32) -> DefaultReturn<Vec<ActivityPost>> {
+        let sql: &str = match self.base.db._type.as_str() {
+            "sqlite" => "SELECT \"gup_posts\".* FROM \"gup_posts_search\" JOIN \"gup_posts\" ON \"gup_posts\".\"id\" = \"gup_posts_search\".\"id\" WHERE \"gup_posts_search\" MATCH ? AND \"gup_posts\".\"author\" NOT IN (SELECT \"username\" FROM \"Users\" WHERE \"role\" = 'banned') ORDER BY \"gup_posts_search\".\"rank\" LIMIT 50 OFFSET ?",
+            "mysql" => "SELECT * FROM \"gup_posts\" WHERE MATCH (\"content\") AGAINST (? IN NATURAL LANGUAGE MODE) AND \"author\" NOT IN (SELECT \"username\" FROM \"Users\" WHERE \"role\" = 'banned') ORDER BY MATCH (\"content\") AGAINST (? IN NATURAL LANGUAGE MODE) DESC LIMIT 50 OFFSET ?",
+            _ => "SELECT * FROM \"gup_posts\" WHERE to_tsvector('simple', \"content\") @@ websearch_to_tsquery('simple', $1) AND \"author\" NOT IN (SELECT \"username\" FROM \"Users\" WHERE \"role\" = 'banned') ORDER BY ts_rank(to_tsvector('simple', \"content\"), websearch_to_tsquery('simple', $1)) DESC LIMIT 50 OFFSET $2",
+        };
+
+        let query = if self.base.db._type == "sqlite" {
+            fts5_query(query)
+        } else {
+            query.to_string()
+        };
+
+        let mut sql = sqlquery(sql).bind::<&String>(&query);
+
+        if self.base.db._type == "mysql" {
+            // the query is used twice, once for filtering and once for ranking
+            sql = sql.bind::<&String>(&query);
+        }
+
+        let c = &self.base.db.client;
+        let res = sql.bind(offset).fetch_all(c).await;
+
+        if res.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Failed to search posts"),
+                payload: Vec::new(),
+            };
+        }
+
+        // ...
+        let rows = res.unwrap();
+        let mut output: Vec<ActivityPost> = Vec::new();
+
+        for row in rows {
+            output.push(post_from_row(&row));
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Successfully searched posts"),
+            payload: output,
+        }
+    }
+
+    // SET
+    /// Update the searchable fields of a user
+    ///
+    /// # Arguments:
+    /// * `username` - the user's `username`
+    /// * `metadata` - the user's current [`UserMetadata`]
+    async fn index_user(&self, username: &String, metadata: &UserMetadata) {
+        let c = &self.base.db.client;
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_user_search\" WHERE \"username\" = ?"
+        } else {
+            "DELETE FROM \"gup_user_search\" WHERE \"username\" = $1"
+        };
+
+        let _ = sqlquery(query).bind::<&String>(username).execute(c).await;
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_user_search\" VALUES (?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_user_search\" VALUES ($1, $2, $3)"
+        };
+
+        let _ = sqlquery(query)
+            .bind::<&String>(username)
+            .bind::<&String>(&metadata.nickname.clone().unwrap_or_default())
+            .bind::<&String>(&metadata.about)
+            .execute(c)
+            .await;
+    }
+
+    /// Update the searchable content of a post
+    ///
+    /// Only sqlite keeps a separate table for this, the other databases index `gup_posts` directly.
+    ///
+    /// # Arguments:
+    /// * `post` - the [`ActivityPost`] that was created or edited
+    async fn index_post(&self, post: &ActivityPost) {
+        if self.base.db._type != "sqlite" {
+            return;
+        }
+
+        self.unindex_post(&post.id).await;
+
+        let c = &self.base.db.client;
+        let _ = sqlquery("INSERT INTO \"gup_posts_search\" VALUES (?, ?)")
+            .bind::<&String>(&post.id)
+            .bind::<&String>(&post.content)
+            .execute(c)
+            .await;
+    }
+
+    /// Remove a post from search
+    ///
+    /// # Arguments:
+    /// * `id` - post id
+    async fn unindex_post(&self, id: &String) {
+        if self.base.db._type != "sqlite" {
+            return;
+        }
+
+        let c = &self.base.db.client;
+        let _ = sqlquery("DELETE FROM \"gup_posts_search\" WHERE \"id\" = ?")
+            .bind::<&String>(id)
+            .execute(c)
+            .await;
+    }
+
+    /// Fill the search tables again if they're missing users or posts
+    async fn rebuild_search_index(&self) {
+        let c = &self.base.db.client;
+
+        // users
+        let users = sqlquery("SELECT COUNT(*) FROM \"Users\"")
+            .fetch_one(c)
+            .await;
+        let indexed = sqlquery("SELECT COUNT(*) FROM \"gup_user_search\"")
+            .fetch_one(c)
+            .await;
+
+        if let (Ok(users), Ok(indexed)) = (users, indexed) {
+            if users.get::<i64, _>(0) != indexed.get::<i64, _>(0) {
+                let _ = sqlquery("DELETE FROM \"gup_user_search\"").execute(c).await;
+
+                if let Ok(rows) = sqlquery("SELECT \"username\", \"metadata\" FROM \"Users\"")
+                    .fetch_all(c)
+                    .await
+                {
+                    for row in rows {
+                        let username = row.get::<String, _>(0);
+
+                        if let Ok(metadata) =
+                            serde_json::from_str::<UserMetadata>(&row.get::<String, _>(1))
+                        {
+                            self.index_user(&username, &metadata).await;
+                        }
+                    }
+                }
+            }
+        }
+
+        // posts
+        if self.base.db._type != "sqlite" {
+            return;
+        }
+
+        let posts = sqlquery("SELECT COUNT(*) FROM \"gup_posts\"")
+            .fetch_one(c)
+            .await;
+        let indexed = sqlquery("SELECT COUNT(*) FROM \"gup_posts_search\"")
+            .fetch_one(c)
+            .await;
+
+        if let (Ok(posts), Ok(indexed)) = (posts, indexed) {
+            if posts.get::<i64, _>(0) != indexed.get::<i64, _>(0) {
+                let _ = sqlquery("DELETE FROM \"gup_posts_search\"")
+                    .execute(c)
+                    .await;
+                let _ = sqlquery(
+                    "INSERT INTO \"gup_posts_search\" SELECT \"id\", \"content\" FROM \"gup_posts\"",
                 )
-                .await
-            {
-                Ok(_) => DefaultReturn {
-                    success: true,
-                    message: String::from("Post liked"),
-                    payload: Some(String::new()),
-                },
-                Err(e) => e.into(),
+                .execute(c)
+                .await;
+            }
+        }
+    }
+
+    // mentions
+
+    // GET
+    /// Get the [`Mention`]s of the given `user`, newest first
+    ///
+    /// # Arguments:
+    /// * `user` - username of the mentioned user
+    /// * `offset` - optional value representing the SQL fetch offset
+    pub async fn get_user_mentions(
+        &self,
+        user: String,
+        offset: Option<i32>,
+    ) -> DefaultReturn<Option<Vec<Mention>>> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_mentions\" WHERE \"username\" = ? ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET ?"
+        } else {
+            "SELECT * FROM \"gup_mentions\" WHERE \"username\" = $1 ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET $2"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query)
+            .bind::<&String>(&user.to_lowercase())
+            .bind(offset.unwrap_or(0))
+            .fetch_all(c)
+            .await;
+
+        if res.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Failed to fetch mentions"),
+                payload: Option::None,
             };
         }
+
+        // ...
+        let rows = res.unwrap();
+        let mut output: Vec<Mention> = Vec::new();
+
+        for row in rows {
+            let row = self.base.textify_row(row).data;
+            output.push(Mention {
+                post: row.get("post").unwrap().to_string(),
+                username: row.get("username").unwrap().to_string(),
+                author: row.get("author").unwrap().to_string(),
+                timestamp: row.get("timestamp").unwrap().parse::<u128>().unwrap(),
+            });
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Mentions exist"),
+            payload: Option::Some(output),
+        }
+    }
+
+    /// Get the usernames mentioned in the given content that belong to existing users
+    ///
+    /// # Arguments:
+    /// * `content` - the raw content of a post
+    async fn resolve_mentions(&self, content: &str) -> Vec<String> {
+        let mut out: Vec<String> = Vec::new();
+
+        for username in crate::markup::find_mentions(content) {
+            if self.get_user_by_username(username.clone()).await.is_ok() {
+                out.push(username);
+            }
+        }
+
+        out
+    }
+
+    // SET
+    /// Make the stored [`Mention`]s of a post match the users it currently mentions
+    ///
+    /// Returns the usernames that weren't mentioned by the post before.
+    ///
+    /// # Arguments:
+    /// * `post` - the [`ActivityPost`] that was created or edited
+    /// * `users` - the users mentioned in the post, from `resolve_mentions`
+    async fn sync_post_mentions(&self, post: &ActivityPost, users: &[String]) -> Vec<String> {
+        let c = &self.base.db.client;
+
+        // get existing mentions
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT \"username\" FROM \"gup_mentions\" WHERE \"post\" = ?"
+        } else {
+            "SELECT \"username\" FROM \"gup_mentions\" WHERE \"post\" = $1"
+        };
+
+        let existing: Vec<String> =
+            match sqlquery(query).bind::<&String>(&post.id).fetch_all(c).await {
+                Ok(rows) => rows.iter().map(|r| r.get::<String, _>(0)).collect(),
+                Err(_) => Vec::new(),
+            };
+
+        // remove users that are no longer mentioned
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_mentions\" WHERE \"post\" = ? AND \"username\" = ?"
+        } else {
+            "DELETE FROM \"gup_mentions\" WHERE \"post\" = $1 AND \"username\" = $2"
+        };
+
+        for username in existing.iter().filter(|u| !users.contains(u)) {
+            let _ = sqlquery(query)
+                .bind::<&String>(&post.id)
+                .bind::<&String>(username)
+                .execute(c)
+                .await;
+        }
+
+        // add new mentions (users mentioning themselves aren't stored)
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_mentions\" VALUES (?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_mentions\" VALUES ($1, $2, $3, $4)"
+        };
+
+        let timestamp = dorsal::utility::unix_epoch_timestamp().to_string();
+        let mut added: Vec<String> = Vec::new();
+
+        for username in users {
+            if existing.contains(username) | (username == &post.author) {
+                continue;
+            }
+
+            let res = sqlquery(query)
+                .bind::<&String>(&post.id)
+                .bind::<&String>(username)
+                .bind::<&String>(&post.author)
+                .bind::<&String>(&timestamp)
+                .execute(c)
+                .await;
+
+            if res.is_ok() {
+                added.push(username.clone());
+            }
+        }
+
+        added
+    }
+
+    // post favorites
+
+    // GET
+    /// Get the number of [`PostFavorite`]s an [`ActivityPost`] has
+    pub async fn get_post_favorites(&self, id: String) -> DefaultReturn<i32> {
+        // get post
+        let existing = self.get_post_by_id(id.clone()).await;
+
+        if existing.success == false {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Post does not exist!"),
+                payload: 0,
+            };
+        }
+
+        // check in cache
+        let cached = self
+            .base
+            .cachedb
+            .get(format!("social:post-favorites:{}", id))
+            .await;
+
+        if let Some(count) = cached {
+            return DefaultReturn {
+                success: true,
+                message: id,
+                payload: count.parse::<i32>().unwrap_or(0),
+            };
+        }
+
+        // ...
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT COUNT(*) FROM \"gup_favorites\" WHERE \"post\" = ?"
+        } else {
+            "SELECT COUNT(*) FROM \"gup_favorites\" WHERE \"post\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query).bind::<&String>(&id).fetch_one(c).await;
+
+        if res.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Failed to fetch favorites"),
+                payload: 0,
+            };
+        }
+
+        // ...
+        let count = res.unwrap().get::<i64, _>(0) as i32;
+
+        // store in cache
+        self.base
+            .cachedb
+            .set(format!("social:post-favorites:{}", id), count.to_string())
+            .await;
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: id,
+            payload: count,
+        }
+    }
+
+    /// Check if a user has favorited a post
+    pub async fn get_user_post_favorite(
+        &self,
+        user: String,
+        post_id: String,
+        skip_existing_check: bool,
+    ) -> DefaultReturn<Option<PostFavorite>> {
+        // get paste
+        if skip_existing_check == false {
+            let existing = self.get_post_by_id(post_id.clone()).await;
+
+            if existing.success == false {
+                return DefaultReturn {
+                    success: false,
+                    message: String::from("Post does not exist!"),
+                    payload: Option::None,
+                };
+            }
+        }
+
+        // ...
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_favorites\" WHERE \"post\" = ? AND \"username\" = ?"
+        } else {
+            "SELECT * FROM \"gup_favorites\" WHERE \"post\" = $1 AND \"username\" = $2"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query)
+            .bind::<&String>(&post_id)
+            .bind::<&String>(&user)
+            .fetch_one(c)
+            .await;
+
+        if res.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: res.err().unwrap().to_string(),
+                payload: Option::None,
+            };
+        }
+
+        // ...
+        let row = res.unwrap();
+        let row = self.base.textify_row(row).data;
+
+        DefaultReturn {
+            success: true,
+            message: post_id,
+            payload: Option::Some(PostFavorite {
+                username: row.get("username").unwrap().to_string(),
+                post: row.get("post").unwrap().to_string(),
+                timestamp: row.get("timestamp").unwrap().parse::<u128>().unwrap(),
+            }),
+        }
+    }
+
+    // SET
+    /// Toggle a [`PostFavorite`] on a [`ActivityPost`] by `user` and `post_id`
+    pub async fn toggle_user_post_favorite(
+        &self,
+        user: String,
+        post_id: String,
+    ) -> DefaultReturn<Option<String>> {
+        // get paste
+        let existing = self.get_post_by_id(post_id.clone()).await;
+
+        if existing.success == false {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Post does not exist!"),
+                payload: Option::None,
+            };
+        }
+
+        // check if user is paste owner
+        let existing = existing.payload.unwrap();
+
+        if existing.author == user {
+            return DefaultReturn {
+                success: false,
+                message: String::from("You're the post author!"),
+                payload: Option::None,
+            };
+        }
+
+        // attempt to get the user's existing favorite
+        let existing_favorite = self
+            .get_user_post_favorite(user.clone(), post_id.clone(), true)
+            .await;
+
+        let c = &self.base.db.client;
+
+        // delete existing
+        if existing_favorite.success {
+            let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+            {
+                "DELETE FROM \"gup_favorites\" WHERE \"post\" = ? AND \"username\" = ?"
+            } else {
+                "DELETE FROM \"gup_favorites\" WHERE \"post\" = $1 AND \"username\" = $2"
+            };
+
+            let res = sqlquery(query)
+                .bind::<&String>(&post_id)
+                .bind::<&String>(&user)
+                .execute(c)
+                .await;
+
+            if res.is_err() {
+                return DefaultReturn {
+                    success: false,
+                    message: res.err().unwrap().to_string(),
+                    payload: Option::None,
+                };
+            }
+
+            // update cache, the count is read from the database again next time it's needed
+            self.base
+                .cachedb
+                .remove(format!("social:post-favorites:{}", post_id))
+                .await;
+
+            // favoriting again shouldn't notify the author twice
+            self.delete_unread_notification(&existing.author, "favorite", &user, &post_id)
+                .await;
+
+            return DefaultReturn {
+                success: true,
+                message: String::from("Post unliked"),
+                payload: Some(String::new()),
+            };
+        }
+
+        // add new
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_favorites\" VALUES (?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_favorites\" VALUES ($1, $2, $3)"
+        };
+
+        let res = sqlquery(query)
+            .bind::<&String>(&user)
+            .bind::<&String>(&post_id)
+            .bind::<&String>(&dorsal::utility::unix_epoch_timestamp().to_string())
+            .execute(c)
+            .await;
+
+        if res.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: res.err().unwrap().to_string(),
+                payload: Option::None,
+            };
+        }
+
+        // update cache
+        self.base
+            .cachedb
+            .remove(format!("social:post-favorites:{}", post_id))
+            .await;
+
+        // notify
+        self.create_notification(existing.author, String::from("favorite"), user, post_id)
+            .await;
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Post liked"),
+            payload: Some(String::new()),
+        }
+    }
+
+    // notifications
+
+    // GET
+    /// Get the [`Notification`]s of the given `user`, newest first
+    ///
+    /// # Arguments:
+    /// * `user` - username of the user the notifications are for
+    /// * `offset` - optional value representing the SQL fetch offset
+    pub async fn get_user_notifications(
+        &self,
+        user: String,
+        offset: Option<i32>,
+    ) -> DefaultReturn<Option<Vec<Notification>>> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_notifications\" WHERE \"recipient\" = ? ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET ?"
+        } else {
+            "SELECT * FROM \"gup_notifications\" WHERE \"recipient\" = $1 ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET $2"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query)
+            .bind::<&String>(&user)
+            .bind(offset.unwrap_or(0))
+            .fetch_all(c)
+            .await;
+
+        if res.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Failed to fetch notifications"),
+                payload: Option::None,
+            };
+        }
+
+        // ...
+        let rows = res.unwrap();
+        let mut output: Vec<Notification> = Vec::new();
+
+        for row in rows {
+            output.push(Notification {
+                id: row.get("id"),
+                recipient: row.get("recipient"),
+                kind: row.get("kind"),
+                actor: row.get("actor"),
+                post: row.get("post"),
+                read: row.get::<String, _>("is_read") == "true",
+                timestamp: row.get::<i64, _>("timestamp") as u128,
+            });
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Notifications exist"),
+            payload: Option::Some(output),
+        }
+    }
+
+    /// Get the number of unread [`Notification`]s the given `user` has
+    ///
+    /// # Arguments:
+    /// * `user` - username of the user the notifications are for
+    pub async fn get_unread_notification_count(&self, user: String) -> DefaultReturn<usize> {
+        // attempt to fetch from cache
+        let cached = self
+            .base
+            .cachedb
+            .get(format!("social:unread-notifications:{}", user))
+            .await;
+
+        if let Some(count) = cached {
+            return DefaultReturn {
+                success: true,
+                message: String::from("Notifications counted (cache)"),
+                payload: count.parse::<usize>().unwrap_or(0),
+            };
+        }
+
+        // count
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT COUNT(*) FROM \"gup_notifications\" WHERE \"recipient\" = ? AND \"is_read\" = 'false'"
+        } else {
+            "SELECT COUNT(*) FROM \"gup_notifications\" WHERE \"recipient\" = $1 AND \"is_read\" = 'false'"
+        };
+
+        let c = &self.base.db.client;
+        let count = match sqlquery(query).bind::<&String>(&user).fetch_one(c).await {
+            Ok(row) => row.get::<i64, _>(0) as usize,
+            Err(_) => {
+                return DefaultReturn {
+                    success: false,
+                    message: String::from("Failed to count notifications"),
+                    payload: 0,
+                }
+            }
+        };
+
+        // store in cache
+        self.base
+            .cachedb
+            .set(
+                format!("social:unread-notifications:{}", user),
+                count.to_string(),
+            )
+            .await;
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Notifications counted"),
+            payload: count,
+        }
+    }
+
+    // SET
+    /// Create a new [`Notification`]
+    ///
+    /// Users are never notified about their own actions.
+    ///
+    /// # Arguments:
+    /// * `recipient` - username of the user the notification is for
+    /// * `kind` - what happened (`follow`, `reply`, `favorite` or `mention`)
+    /// * `actor` - username of the user that caused the notification
+    /// * `post` - the id of the post the notification is about, or an empty string
+    pub async fn create_notification(
+        &self,
+        recipient: String,
+        kind: String,
+        actor: String,
+        post: String,
+    ) -> DefaultReturn<Option<Notification>> {
+        if recipient == actor {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Users can't notify themselves"),
+                payload: Option::None,
+            };
+        }
+
+        let notification = Notification {
+            id: dorsal::utility::random_id(),
+            recipient,
+            kind,
+            actor,
+            post,
+            read: false,
+            timestamp: dorsal::utility::unix_epoch_timestamp(),
+        };
+
+        // create
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_notifications\" VALUES (?, ?, ?, ?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_notifications\" VALUES ($1, $2, $3, $4, $5, $6, $7)"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query)
+            .bind::<&String>(&notification.id)
+            .bind::<&String>(&notification.recipient)
+            .bind::<&String>(&notification.kind)
+            .bind::<&String>(&notification.actor)
+            .bind::<&String>(&notification.post)
+            .bind::<&str>("false")
+            .bind(notification.timestamp as i64)
+            .execute(c)
+            .await;
+
+        if res.is_err() {
+            return DefaultReturn {
+                success: false,
+                message: res.err().unwrap().to_string(),
+                payload: Option::None,
+            };
+        }
+
+        // update cache
+        self.base
+            .cachedb
+            .remove(format!(
+                "social:unread-notifications:{}",
+                notification.recipient
+            ))
+            .await;
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Notification created"),
+            payload: Option::Some(notification),
+        }
+    }
+
+    /// Mark [`Notification`]s of the given `user` as read. Returns the number of notifications updated
+    ///
+    /// # Arguments:
+    /// * `user` - username of the user the notifications are for
+    /// * `ids` - the ids of the notifications to mark as read, every notification is marked if empty
+    pub async fn mark_notifications_read(
+        &self,
+        user: String,
+        ids: Vec<String>,
+    ) -> DefaultReturn<usize> {
+        let c = &self.base.db.client;
+        let mut updated: usize = 0;
+
+        if ids.is_empty() {
+            let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+            {
+                "UPDATE \"gup_notifications\" SET \"is_read\" = 'true' WHERE \"recipient\" = ? AND \"is_read\" = 'false'"
+            } else {
+                "UPDATE \"gup_notifications\" SET \"is_read\" = 'true' WHERE \"recipient\" = $1 AND \"is_read\" = 'false'"
+            };
+
+            match sqlquery(query).bind::<&String>(&user).execute(c).await {
+                Ok(r) => updated += r.rows_affected() as usize,
+                Err(e) => {
+                    return DefaultReturn {
+                        success: false,
+                        message: e.to_string(),
+                        payload: 0,
+                    }
+                }
+            }
+        } else {
+            let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+            {
+                "UPDATE \"gup_notifications\" SET \"is_read\" = 'true' WHERE \"recipient\" = ? AND \"id\" = ? AND \"is_read\" = 'false'"
+            } else {
+                "UPDATE \"gup_notifications\" SET \"is_read\" = 'true' WHERE \"recipient\" = $1 AND \"id\" = $2 AND \"is_read\" = 'false'"
+            };
+
+            for id in ids {
+                match sqlquery(query)
+                    .bind::<&String>(&user)
+                    .bind::<&String>(&id)
+                    .execute(c)
+                    .await
+                {
+                    Ok(r) => updated += r.rows_affected() as usize,
+                    Err(e) => {
+                        return DefaultReturn {
+                            success: false,
+                            message: e.to_string(),
+                            payload: updated,
+                        }
+                    }
+                }
+            }
+        }
+
+        // update cache
+        self.base
+            .cachedb
+            .remove(format!("social:unread-notifications:{}", user))
+            .await;
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Notifications marked as read"),
+            payload: updated,
+        }
+    }
+
+    /// Delete the unread [`Notification`] an action created, once the action is undone
+    ///
+    /// # Arguments:
+    /// * `recipient` - username of the user the notification is for
+    /// * `kind` - what happened (`follow` or `favorite`)
+    /// * `actor` - username of the user that caused the notification
+    /// * `post` - the id of the post the notification is about, or an empty string
+    async fn delete_unread_notification(
+        &self,
+        recipient: &String,
+        kind: &str,
+        actor: &String,
+        post: &str,
+    ) {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_notifications\" WHERE \"recipient\" = ? AND \"kind\" = ? AND \"actor\" = ? AND \"post\" = ? AND \"is_read\" = 'false'"
+        } else {
+            "DELETE FROM \"gup_notifications\" WHERE \"recipient\" = $1 AND \"kind\" = $2 AND \"actor\" = $3 AND \"post\" = $4 AND \"is_read\" = 'false'"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query)
+            .bind::<&String>(recipient)
+            .bind::<&str>(kind)
+            .bind::<&String>(actor)
+            .bind::<&str>(post)
+            .execute(c)
+            .await;
+
+        // update cache
+        if res.is_ok_and(|r| r.rows_affected() > 0) {
+            self.base
+                .cachedb
+                .remove(format!("social:unread-notifications:{}", recipient))
+                .await;
+        }
+    }
+
+    /// Delete every [`Notification`] about the given post
+    ///
+    /// # Arguments:
+    /// * `post` - post id
+    async fn delete_post_notifications(&self, post: &String) {
+        let c = &self.base.db.client;
+
+        // get the users whose unread count changes
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT DISTINCT \"recipient\" FROM \"gup_notifications\" WHERE \"post\" = ? AND \"is_read\" = 'false'"
+        } else {
+            "SELECT DISTINCT \"recipient\" FROM \"gup_notifications\" WHERE \"post\" = $1 AND \"is_read\" = 'false'"
+        };
+
+        let recipients: Vec<String> = match sqlquery(query).bind::<&String>(post).fetch_all(c).await
+        {
+            Ok(rows) => rows.iter().map(|r| r.get::<String, _>(0)).collect(),
+            Err(_) => Vec::new(),
+        };
+
+        // delete
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_notifications\" WHERE \"post\" = ?"
+        } else {
+            "DELETE FROM \"gup_notifications\" WHERE \"post\" = $1"
+        };
+
+        let _ = sqlquery(query).bind::<&String>(post).execute(c).await;
+
+        // update cache
+        for recipient in recipients {
+            self.base
+                .cachedb
+                .remove(format!("social:unread-notifications:{}", recipient))
+                .await;
+        }
+    }
+
+    // invites
+
+    // GET
+    /// Get an [`Invite`] by its `code`
+    ///
+    /// # Arguments:
+    /// * `code` - the invite code
+    pub async fn get_invite(&self, code: String) -> DefaultReturn<Option<Invite>> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_invites\" WHERE \"code\" = ?"
+        } else {
+            "SELECT * FROM \"gup_invites\" WHERE \"code\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&String>(&code).fetch_one(c).await {
+            Ok(row) => DefaultReturn {
+                success: true,
+                message: String::from("Invite exists"),
+                payload: Option::Some(invite_from_row(&row)),
+            },
+            Err(_) => DefaultReturn {
+                success: false,
+                message: String::from("Invite does not exist"),
+                payload: Option::None,
+            },
+        }
+    }
+
+    /// Get [`Invite`]s, newest first
+    ///
+    /// # Arguments:
+    /// * `author` - only get invites created by this user, every invite is returned if `None`
+    /// * `offset` - optional value representing the SQL fetch offset
+    pub async fn get_invites(
+        &self,
+        author: Option<String>,
+        offset: Option<i32>,
+    ) -> DefaultReturn<Option<Vec<Invite>>> {
+        let query: &str = match (author.is_some(), self.base.db._type.as_str()) {
+            (true, "sqlite") | (true, "mysql") => {
+                "SELECT * FROM \"gup_invites\" WHERE \"author\" = ? ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET ?"
+            }
+            (true, _) => {
+                "SELECT * FROM \"gup_invites\" WHERE \"author\" = $1 ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET $2"
+            }
+            (false, "sqlite") | (false, "mysql") => {
+                "SELECT * FROM \"gup_invites\" ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET ?"
+            }
+            (false, _) => {
+                "SELECT * FROM \"gup_invites\" ORDER BY \"timestamp\" DESC LIMIT 50 OFFSET $1"
+            }
+        };
+
+        let c = &self.base.db.client;
+        let mut q = sqlquery(query);
+
+        if let Some(ref author) = author {
+            q = q.bind::<&String>(author);
+        }
+
+        let res = q.bind(offset.unwrap_or(0)).fetch_all(c).await;
+
+        match res {
+            Ok(rows) => DefaultReturn {
+                success: true,
+                message: String::from("Invites exist"),
+                payload: Option::Some(rows.iter().map(invite_from_row).collect()),
+            },
+            Err(_) => DefaultReturn {
+                success: false,
+                message: String::from("Failed to fetch invites"),
+                payload: Option::None,
+            },
+        }
+    }
+
+    /// Get every [`InviteUse`] of an [`Invite`], oldest first
+    ///
+    /// # Arguments:
+    /// * `code` - the invite code
+    pub async fn get_invite_uses(&self, code: String) -> DefaultReturn<Option<Vec<InviteUse>>> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_invite_uses\" WHERE \"code\" = ? ORDER BY \"timestamp\" ASC"
+        } else {
+            "SELECT * FROM \"gup_invite_uses\" WHERE \"code\" = $1 ORDER BY \"timestamp\" ASC"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&String>(&code).fetch_all(c).await {
+            Ok(rows) => DefaultReturn {
+                success: true,
+                message: String::from("Invite uses exist"),
+                payload: Option::Some(
+                    rows.iter()
+                        .map(|row| InviteUse {
+                            code: row.get("code"),
+                            username: row.get("username"),
+                            timestamp: row.get::<i64, _>("timestamp") as u128,
+                        })
+                        .collect(),
+                ),
+            },
+            Err(_) => DefaultReturn {
+                success: false,
+                message: String::from("Failed to fetch invite uses"),
+                payload: Option::None,
+            },
+        }
+    }
+
+    /// Get the number of [`Invite`]s the given `user` has created
+    ///
+    /// # Arguments:
+    /// * `user` - username of the user that created the invites
+    pub async fn get_user_invite_count(&self, user: String) -> DefaultReturn<usize> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT COUNT(*) FROM \"gup_invites\" WHERE \"author\" = ?"
+        } else {
+            "SELECT COUNT(*) FROM \"gup_invites\" WHERE \"author\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let count = match sqlquery(query).bind::<&String>(&user).fetch_one(c).await {
+            Ok(row) => row.get::<i64, _>(0) as usize,
+            Err(_) => 0,
+        };
+
+        DefaultReturn {
+            success: true,
+            message: String::from("Invites exist"),
+            payload: count,
+        }
+    }
+
+    // SET
+    /// Create a new [`Invite`]
+    ///
+    /// # Arguments:
+    /// * `author` - the username of the user creating the invite
+    /// * `max_uses` - how many accounts can be registered with the invite
+    /// * `expires` - when the invite stops working (`0` if it never expires)
+    pub async fn create_invite(
+        &self,
+        author: String,
+        max_uses: usize,
+        expires: u128,
+    ) -> DefaultReturn<Option<Invite>> {
+        if max_uses == 0 {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Invites must have at least one use"),
+                payload: Option::None,
+            };
+        }
+
+        let timestamp = dorsal::utility::unix_epoch_timestamp();
+
+        if (expires != 0) && (expires <= timestamp) {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Invites can't expire in the past"),
+                payload: Option::None,
+            };
+        }
+
+        let invite = Invite {
+            code: dorsal::utility::random_id().chars().take(16).collect(),
+            author,
+            max_uses,
+            uses: 0,
+            expires,
+            timestamp,
+        };
+
+        // create invite
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_invites\" VALUES (?, ?, ?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_invites\" VALUES ($1, $2, $3, $4, $5, $6)"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query)
+            .bind::<&String>(&invite.code)
+            .bind::<&String>(&invite.author)
+            .bind(invite.max_uses as i64)
+            .bind(0_i64)
+            .bind(invite.expires as i64)
+            .bind(invite.timestamp as i64)
+            .execute(c)
+            .await;
+
+        if let Err(e) = res {
+            return DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            };
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: invite.code.clone(),
+            payload: Option::Some(invite),
+        }
+    }
+
+    /// Revoke an [`Invite`] so it can't be used anymore
+    ///
+    /// The accounts registered with the invite are still recorded in `gup_invite_uses`.
+    ///
+    /// # Arguments:
+    /// * `code` - the invite code
+    pub async fn revoke_invite(&self, code: String) -> DefaultReturn<Option<String>> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_invites\" WHERE \"code\" = ?"
+        } else {
+            "DELETE FROM \"gup_invites\" WHERE \"code\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&String>(&code).execute(c).await {
+            Ok(r) if r.rows_affected() > 0 => DefaultReturn {
+                success: true,
+                message: String::from("Invite revoked!"),
+                payload: Option::Some(code),
+            },
+            Ok(_) => DefaultReturn {
+                success: false,
+                message: String::from("Invite does not exist"),
+                payload: Option::None,
+            },
+            Err(e) => DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            },
+        }
+    }
+
+    /// Create a new user with an [`Invite`], see [`Database::create_user`]
+    ///
+    /// A use of the invite is claimed before the user is created, so an invite can't be used
+    /// more than `max_uses` times by registrations happening at the same time.
+    ///
+    /// # Arguments:
+    /// * `username` - `String` of the user's `username`
+    /// * `code` - the invite code
+    pub async fn create_user_with_invite(
+        &self,
+        username: String,
+        code: String,
+    ) -> DefaultReturn<Option<String>> {
+        let c = &self.base.db.client;
+
+        // claim a use
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "UPDATE \"gup_invites\" SET \"uses\" = \"uses\" + 1 WHERE \"code\" = ? AND \"uses\" < \"max_uses\" AND (\"expires\" = 0 OR \"expires\" > ?)"
+        } else {
+            "UPDATE \"gup_invites\" SET \"uses\" = \"uses\" + 1 WHERE \"code\" = $1 AND \"uses\" < \"max_uses\" AND (\"expires\" = 0 OR \"expires\" > $2)"
+        };
+
+        let claimed = sqlquery(query)
+            .bind::<&String>(&code)
+            .bind(dorsal::utility::unix_epoch_timestamp() as i64)
+            .execute(c)
+            .await;
+
+        if !claimed.is_ok_and(|r| r.rows_affected() == 1) {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Invalid invite code."),
+                payload: Option::None,
+            };
+        }
+
+        // create user
+        let res = self.create_user(username.clone()).await;
+
+        if !res.success {
+            // give the use back
+            let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+            {
+                "UPDATE \"gup_invites\" SET \"uses\" = \"uses\" - 1 WHERE \"code\" = ?"
+            } else {
+                "UPDATE \"gup_invites\" SET \"uses\" = \"uses\" - 1 WHERE \"code\" = $1"
+            };
+
+            let _ = sqlquery(query).bind::<&String>(&code).execute(c).await;
+            return res;
+        }
+
+        // record use
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_invite_uses\" VALUES (?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_invite_uses\" VALUES ($1, $2, $3)"
+        };
+
+        let _ = sqlquery(query)
+            .bind::<&String>(&code)
+            .bind::<&String>(&username)
+            .bind(dorsal::utility::unix_epoch_timestamp() as i64)
+            .execute(c)
+            .await;
+
+        // return
+        res
+    }
+
+    // sessions
+
+    // GET
+    /// Get the [`Session`] a session token belongs to
+    ///
+    /// Sessions that haven't been used for [`SESSION_MAX_AGE`] are deleted instead.
+    ///
+    /// # Arguments:
+    /// * `token` - the unhashed session token
+    pub async fn get_session(&self, token: String) -> DefaultReturn<Option<Session>> {
+        let hashed = dorsal::utility::hash(token);
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_sessions\" WHERE \"token\" = ?"
+        } else {
+            "SELECT * FROM \"gup_sessions\" WHERE \"token\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let session = match sqlquery(query).bind::<&String>(&hashed).fetch_one(c).await {
+            Ok(row) => session_from_row(&row),
+            Err(_) => {
+                return DefaultReturn {
+                    success: false,
+                    message: String::from("Session does not exist"),
+                    payload: Option::None,
+                }
+            }
+        };
+
+        let now = dorsal::utility::unix_epoch_timestamp();
+
+        if now.saturating_sub(session.last_seen) > SESSION_MAX_AGE {
+            self.delete_session_by_id(session.id).await;
+
+            return DefaultReturn {
+                success: false,
+                message: String::from("Session expired"),
+                payload: Option::None,
+            };
+        }
+
+        // update last seen
+        if now.saturating_sub(session.last_seen) > SESSION_SEEN_INTERVAL {
+            let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+            {
+                "UPDATE \"gup_sessions\" SET \"last_seen\" = ? WHERE \"id\" = ?"
+            } else {
+                "UPDATE \"gup_sessions\" SET \"last_seen\" = $1 WHERE \"id\" = $2"
+            };
+
+            let _ = sqlquery(query)
+                .bind(now as i64)
+                .bind::<&String>(&session.id)
+                .execute(c)
+                .await;
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Session exists"),
+            payload: Option::Some(session),
+        }
+    }
+
+    /// Get every [`Session`] of the given `user`, most recently used first
+    ///
+    /// # Arguments:
+    /// * `user` - username of the user the sessions are for
+    pub async fn get_user_sessions(&self, user: String) -> DefaultReturn<Option<Vec<Session>>> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_sessions\" WHERE \"username\" = ? ORDER BY \"last_seen\" DESC"
+        } else {
+            "SELECT * FROM \"gup_sessions\" WHERE \"username\" = $1 ORDER BY \"last_seen\" DESC"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&String>(&user).fetch_all(c).await {
+            Ok(rows) => DefaultReturn {
+                success: true,
+                message: String::from("Sessions exist"),
+                payload: Option::Some(rows.iter().map(session_from_row).collect()),
+            },
+            Err(_) => DefaultReturn {
+                success: false,
+                message: String::from("Failed to fetch sessions"),
+                payload: Option::None,
+            },
+        }
+    }
+
+    /// Get the user a session token belongs to
+    ///
+    /// # Arguments:
+    /// * `token` - the unhashed session token
+    pub async fn get_user_by_session(&self, token: String) -> Result<FullUser<UserMetadata>> {
+        match self.get_session(token).await.payload {
+            Some(session) => self.get_user_by_username(session.username).await,
+            None => Err(AuthError::NotFound),
+        }
+    }
+
+    // SET
+    /// Create a new [`Session`] for a user. Returns the unhashed session token
+    ///
+    /// # Arguments:
+    /// * `username` - the username of the user signing in
+    /// * `user_agent` - the `User-Agent` of the client signing in
+    pub async fn create_session(
+        &self,
+        username: String,
+        user_agent: String,
+    ) -> DefaultReturn<Option<String>> {
+        let token = dorsal::utility::random_id();
+        let timestamp = dorsal::utility::unix_epoch_timestamp() as i64;
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_sessions\" VALUES (?, ?, ?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_sessions\" VALUES ($1, $2, $3, $4, $5, $6)"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query)
+            .bind::<&String>(&dorsal::utility::uuid())
+            .bind::<&String>(&dorsal::utility::hash(token.clone()))
+            .bind::<&String>(&username)
+            .bind::<&String>(&user_agent.chars().take(500).collect::<String>())
+            .bind(timestamp)
+            .bind(timestamp)
+            .execute(c)
+            .await;
+
+        if let Err(e) = res {
+            return DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            };
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Session created"),
+            payload: Option::Some(token),
+        }
+    }
+
+    /// Delete the [`Session`] a session token belongs to, signing it out
+    ///
+    /// # Arguments:
+    /// * `token` - the unhashed session token
+    pub async fn delete_session(&self, token: String) -> DefaultReturn<bool> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_sessions\" WHERE \"token\" = ?"
+        } else {
+            "DELETE FROM \"gup_sessions\" WHERE \"token\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&dorsal::utility::hash(token))
+            .execute(c)
+            .await
+        {
+            Ok(r) if r.rows_affected() > 0 => DefaultReturn {
+                success: true,
+                message: String::from("Session deleted"),
+                payload: true,
+            },
+            _ => DefaultReturn {
+                success: false,
+                message: String::from("Session does not exist"),
+                payload: false,
+            },
+        }
+    }
+
+    /// Delete one of a user's [`Session`]s by its `id`
+    ///
+    /// # Arguments:
+    /// * `user` - username of the user the session is for
+    /// * `id` - the id of the session
+    pub async fn delete_user_session(&self, user: String, id: String) -> DefaultReturn<bool> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_sessions\" WHERE \"username\" = ? AND \"id\" = ?"
+        } else {
+            "DELETE FROM \"gup_sessions\" WHERE \"username\" = $1 AND \"id\" = $2"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&user)
+            .bind::<&String>(&id)
+            .execute(c)
+            .await
+        {
+            Ok(r) if r.rows_affected() > 0 => DefaultReturn {
+                success: true,
+                message: String::from("Session deleted"),
+                payload: true,
+            },
+            _ => DefaultReturn {
+                success: false,
+                message: String::from("Session does not exist"),
+                payload: false,
+            },
+        }
+    }
+
+    /// Delete every [`Session`] of a user except one. Returns the number of deleted sessions
+    ///
+    /// # Arguments:
+    /// * `user` - username of the user the sessions are for
+    /// * `keep` - the id of the session to keep, every session is deleted if this is `None`
+    pub async fn delete_other_sessions(
+        &self,
+        user: String,
+        keep: Option<String>,
+    ) -> DefaultReturn<usize> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_sessions\" WHERE \"username\" = ? AND \"id\" != ?"
+        } else {
+            "DELETE FROM \"gup_sessions\" WHERE \"username\" = $1 AND \"id\" != $2"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&user)
+            .bind::<&String>(&keep.unwrap_or_default())
+            .execute(c)
+            .await
+        {
+            Ok(r) => DefaultReturn {
+                success: true,
+                message: format!("{} session(s) signed out", r.rows_affected()),
+                payload: r.rows_affected() as usize,
+            },
+            Err(e) => DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: 0,
+            },
+        }
+    }
+
+    /// Delete a [`Session`] by its `id`
+    ///
+    /// # Arguments:
+    /// * `id` - the id of the session
+    async fn delete_session_by_id(&self, id: String) {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_sessions\" WHERE \"id\" = ?"
+        } else {
+            "DELETE FROM \"gup_sessions\" WHERE \"id\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let _ = sqlquery(query).bind::<&String>(&id).execute(c).await;
+    }
+
+    // rate limits
+
+    // GET
+    /// Get how many seconds are left of a lockout, `None` if the key isn't locked out
+    ///
+    /// # Arguments:
+    /// * `key` - what's locked out, like `ip:<address>` or `user:<username>`
+    pub async fn get_login_lockout(&self, key: &str) -> Option<u64> {
+        let mut con = self.base.cachedb.client.get_connection().ok()?;
+        let ttl: redis::RedisResult<i64> = con.ttl(format!("login-lockout:{}", key));
+
+        match ttl {
+            Ok(secs) if secs > 0 => Some(secs as u64),
+            _ => None,
+        }
+    }
+
+    // SET
+    /// Record a failed sign in. Returns how long (in seconds) the key is now locked out for
+    ///
+    /// Failures are forgotten `window` seconds after the last one. Once there are `attempts`
+    /// failures, every failure locks the key out for twice as long as the last one, starting at
+    /// `lockout` and up to `max_lockout` seconds.
+    ///
+    /// # Arguments:
+    /// * `key` - what failed to sign in, like `ip:<address>` or `user:<username>`
+    /// * `attempts` - how many failures are allowed before the key is locked out
+    /// * `window` - how long (in seconds) failures are remembered
+    /// * `lockout` - how long (in seconds) the first lockout lasts
+    /// * `max_lockout` - how long (in seconds) lockouts can last
+    pub async fn record_login_failure(
+        &self,
+        key: &str,
+        attempts: u64,
+        window: u64,
+        lockout: u64,
+        max_lockout: u64,
+    ) -> Option<u64> {
+        let mut con = self.base.cachedb.client.get_connection().ok()?;
+        let failures: u64 = redis::pipe()
+            .incr(format!("login-failures:{}", key), 1)
+            .expire(format!("login-failures:{}", key), window as i64)
+            .ignore()
+            .query::<(u64,)>(&mut con)
+            .map(|r| r.0)
+            .ok()?;
+
+        if failures < attempts {
+            return None;
+        }
+
+        // exponential backoff
+        let secs = u32::try_from(failures - attempts)
+            .ok()
+            .and_then(|exp| 2u64.checked_pow(exp))
+            .and_then(|x| x.checked_mul(lockout))
+            .unwrap_or(max_lockout)
+            .min(max_lockout);
+
+        let res: redis::RedisResult<String> =
+            con.set_ex(format!("login-lockout:{}", key), "1", secs);
+
+        res.ok().map(|_| secs)
+    }
+
+    /// Forget the failed sign ins of a key
+    ///
+    /// # Arguments:
+    /// * `key` - what signed in, like `user:<username>`
+    pub async fn clear_login_failures(&self, key: &str) {
+        self.base
+            .cachedb
+            .remove(format!("login-failures:{}", key))
+            .await;
+    }
+
+    /// Take a token from a rate limit bucket. Returns if a token was taken, and how many
+    /// tokens are left (in 1/60000ths of a token, the amount added every millisecond at one
+    /// token per minute). `None` if the cache can't be reached
+    ///
+    /// # Arguments:
+    /// * `key` - the bucket, like `<route group>:ip:<address>`
+    /// * `capacity` - how many tokens the bucket holds
+    /// * `per_minute` - how many tokens are added every minute
+    pub async fn take_rate_limit_token(
+        &self,
+        key: &str,
+        capacity: u64,
+        per_minute: u64,
+    ) -> Option<(bool, u64)> {
+        // refill and take in one step, so concurrent requests can't take the same token
+        let script = redis::Script::new(
+            r"
+            local capacity = tonumber(ARGV[1])
+            local now = tonumber(ARGV[3])
+            local state = redis.call('HMGET', KEYS[1], 'tokens', 'at')
+            local tokens = tonumber(state[1]) or capacity
+            local at = tonumber(state[2]) or now
+
+            tokens = math.min(capacity, tokens + math.max(0, now - at) * tonumber(ARGV[2]))
+
+            local taken = 0
+            if tokens >= 60000 then
+                tokens = tokens - 60000
+                taken = 1
+            end
+
+            redis.call('HSET', KEYS[1], 'tokens', tokens, 'at', now)
+            redis.call('PEXPIRE', KEYS[1], ARGV[4])
+            return {taken, tokens}
+            ",
+        );
+
+        let capacity = capacity * 60000;
+
+        let mut con = self.base.cachedb.client.get_connection().ok()?;
+        let res: redis::RedisResult<(i64, i64)> = script
+            .key(format!("rate-limit:{}", key))
+            .arg(capacity)
+            .arg(per_minute)
+            .arg(dorsal::utility::unix_epoch_timestamp() as u64)
+            // forget the bucket once it would be full again
+            .arg(capacity / per_minute + 1)
+            .invoke(&mut con);
+
+        res.ok()
+            .map(|(taken, tokens)| (taken == 1, tokens.max(0) as u64))
+    }
+
+    // api tokens
+
+    // GET
+    /// Get the [`ApiToken`] a token belongs to
+    ///
+    /// Expired tokens are deleted instead.
+    ///
+    /// # Arguments:
+    /// * `token` - the unhashed token
+    pub async fn get_api_token(&self, token: String) -> DefaultReturn<Option<ApiToken>> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_api_tokens\" WHERE \"token\" = ?"
+        } else {
+            "SELECT * FROM \"gup_api_tokens\" WHERE \"token\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let api_token = match sqlquery(query)
+            .bind::<&String>(&dorsal::utility::hash(token))
+            .fetch_one(c)
+            .await
+        {
+            Ok(row) => api_token_from_row(&row),
+            Err(_) => {
+                return DefaultReturn {
+                    success: false,
+                    message: String::from("Token does not exist"),
+                    payload: Option::None,
+                }
+            }
+        };
+
+        let now = dorsal::utility::unix_epoch_timestamp();
+
+        if (api_token.expires != 0) && (now > api_token.expires) {
+            self.delete_user_api_token(api_token.username, api_token.id)
+                .await;
+
+            return DefaultReturn {
+                success: false,
+                message: String::from("Token expired"),
+                payload: Option::None,
+            };
+        }
+
+        // update last used
+        if now.saturating_sub(api_token.last_used) > SESSION_SEEN_INTERVAL {
+            let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+            {
+                "UPDATE \"gup_api_tokens\" SET \"last_used\" = ? WHERE \"id\" = ?"
+            } else {
+                "UPDATE \"gup_api_tokens\" SET \"last_used\" = $1 WHERE \"id\" = $2"
+            };
+
+            let _ = sqlquery(query)
+                .bind(now as i64)
+                .bind::<&String>(&api_token.id)
+                .execute(c)
+                .await;
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Token exists"),
+            payload: Option::Some(api_token),
+        }
+    }
+
+    /// Get the user an API token belongs to, if the token has the given `scope`
+    ///
+    /// # Arguments:
+    /// * `token` - the unhashed token
+    /// * `scope` - the scope the token needs, see [`API_TOKEN_SCOPES`]
+    pub async fn get_user_by_api_token(
+        &self,
+        token: String,
+        scope: &str,
+    ) -> Result<FullUser<UserMetadata>> {
+        match self.get_api_token(token).await.payload {
+            Some(t) if t.scopes.iter().any(|s| s == scope) => {
+                self.get_user_by_username(t.username).await
+            }
+            Some(_) => Err(AuthError::ValueError),
+            None => Err(AuthError::NotFound),
+        }
+    }
+
+    /// Get every [`ApiToken`] of the given `user`, newest first
+    ///
+    /// # Arguments:
+    /// * `user` - username of the user the tokens are for
+    pub async fn get_user_api_tokens(&self, user: String) -> DefaultReturn<Option<Vec<ApiToken>>> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_api_tokens\" WHERE \"username\" = ? ORDER BY \"created\" DESC"
+        } else {
+            "SELECT * FROM \"gup_api_tokens\" WHERE \"username\" = $1 ORDER BY \"created\" DESC"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&String>(&user).fetch_all(c).await {
+            Ok(rows) => DefaultReturn {
+                success: true,
+                message: String::from("Tokens exist"),
+                payload: Option::Some(rows.iter().map(api_token_from_row).collect()),
+            },
+            Err(_) => DefaultReturn {
+                success: false,
+                message: String::from("Failed to fetch tokens"),
+                payload: Option::None,
+            },
+        }
+    }
+
+    // SET
+    /// Create a new [`ApiToken`] for a user. Returns the unhashed token
+    ///
+    /// # Arguments:
+    /// * `username` - the username of the user the token acts as
+    /// * `name` - a name to recognize the token by
+    /// * `scopes` - what the token can be used for, see [`API_TOKEN_SCOPES`]
+    /// * `expires` - when the token stops working (`0` for never)
+    pub async fn create_api_token(
+        &self,
+        username: String,
+        name: String,
+        mut scopes: Vec<String>,
+        expires: u128,
+    ) -> DefaultReturn<Option<String>> {
+        let name = name.trim().to_string();
+
+        if name.is_empty() | (name.len() > 64) {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Token names must be between 1 and 64 characters"),
+                payload: Option::None,
+            };
+        }
+
+        scopes.sort();
+        scopes.dedup();
+
+        if scopes.is_empty() {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Tokens need at least one scope"),
+                payload: Option::None,
+            };
+        }
+
+        if let Some(scope) = scopes
+            .iter()
+            .find(|s| !API_TOKEN_SCOPES.contains(&s.as_str()))
+        {
+            return DefaultReturn {
+                success: false,
+                message: format!("Unknown scope \"{scope}\""),
+                payload: Option::None,
+            };
+        }
+
+        let existing = self
+            .get_user_api_tokens(username.clone())
+            .await
+            .payload
+            .unwrap_or_default();
+
+        if existing.len() >= API_TOKEN_LIMIT {
+            return DefaultReturn {
+                success: false,
+                message: format!("Users can't have more than {API_TOKEN_LIMIT} tokens"),
+                payload: Option::None,
+            };
+        }
+
+        // create
+        let token = format!("gup_{}", dorsal::utility::random_id());
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_api_tokens\" VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_api_tokens\" VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query)
+            .bind::<&String>(&dorsal::utility::uuid())
+            .bind::<&String>(&dorsal::utility::hash(token.clone()))
+            .bind::<&String>(&username)
+            .bind::<&String>(&name)
+            .bind::<&String>(&scopes.join(" "))
+            .bind(expires as i64)
+            .bind(0_i64)
+            .bind(dorsal::utility::unix_epoch_timestamp() as i64)
+            .execute(c)
+            .await;
+
+        if let Err(e) = res {
+            return DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            };
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Token created"),
+            payload: Option::Some(token),
+        }
+    }
+
+    /// Delete one of a user's [`ApiToken`]s by its `id`
+    ///
+    /// # Arguments:
+    /// * `user` - username of the user the token is for
+    /// * `id` - the id of the token
+    pub async fn delete_user_api_token(&self, user: String, id: String) -> DefaultReturn<bool> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_api_tokens\" WHERE \"username\" = ? AND \"id\" = ?"
+        } else {
+            "DELETE FROM \"gup_api_tokens\" WHERE \"username\" = $1 AND \"id\" = $2"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&user)
+            .bind::<&String>(&id)
+            .execute(c)
+            .await
+        {
+            Ok(r) if r.rows_affected() > 0 => DefaultReturn {
+                success: true,
+                message: String::from("Token deleted"),
+                payload: true,
+            },
+            _ => DefaultReturn {
+                success: false,
+                message: String::from("Token does not exist"),
+                payload: false,
+            },
+        }
+    }
+
+    /// Delete every [`ApiToken`] of a user
+    ///
+    /// # Arguments:
+    /// * `user` - username of the user the tokens are for
+    pub async fn delete_user_api_tokens(&self, user: String) {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_api_tokens\" WHERE \"username\" = ?"
+        } else {
+            "DELETE FROM \"gup_api_tokens\" WHERE \"username\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let _ = sqlquery(query).bind::<&String>(&user).execute(c).await;
+    }
+
+    // oauth
+
+    // GET
+    /// Get an [`OAuthClient`] by its `id`
+    ///
+    /// # Arguments:
+    /// * `id` - the `client_id` of the client
+    pub async fn get_oauth_client(&self, id: String) -> DefaultReturn<Option<OAuthClient>> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_oauth_clients\" WHERE \"id\" = ?"
+        } else {
+            "SELECT * FROM \"gup_oauth_clients\" WHERE \"id\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&String>(&id).fetch_one(c).await {
+            Ok(row) => DefaultReturn {
+                success: true,
+                message: String::from("Client exists"),
+                payload: Option::Some(oauth_client_from_row(&row)),
+            },
+            Err(_) => DefaultReturn {
+                success: false,
+                message: String::from("Client does not exist"),
+                payload: Option::None,
+            },
+        }
+    }
+
+    /// Get every [`OAuthClient`], newest first
+    ///
+    /// # Arguments:
+    /// * `owner` - only get the clients registered by this user
+    pub async fn get_oauth_clients(
+        &self,
+        owner: Option<String>,
+    ) -> DefaultReturn<Option<Vec<OAuthClient>>> {
+        let query: &str = match (owner.is_some(), self.base.db._type.as_str()) {
+            (true, "sqlite" | "mysql") => {
+                "SELECT * FROM \"gup_oauth_clients\" WHERE \"owner\" = ? ORDER BY \"created\" DESC"
+            }
+            (true, _) => {
+                "SELECT * FROM \"gup_oauth_clients\" WHERE \"owner\" = $1 ORDER BY \"created\" DESC"
+            }
+            (false, _) => "SELECT * FROM \"gup_oauth_clients\" ORDER BY \"created\" DESC",
+        };
+
+        let c = &self.base.db.client;
+        let mut q = sqlquery(query);
+
+        if let Some(ref owner) = owner {
+            q = q.bind::<&String>(owner);
+        }
+
+        match q.fetch_all(c).await {
+            Ok(rows) => DefaultReturn {
+                success: true,
+                message: String::from("Clients exist"),
+                payload: Option::Some(rows.iter().map(oauth_client_from_row).collect()),
+            },
+            Err(_) => DefaultReturn {
+                success: false,
+                message: String::from("Failed to fetch clients"),
+                payload: Option::None,
+            },
+        }
+    }
+
+    /// Check the secret of a confidential [`OAuthClient`] (always `false` for public clients)
+    ///
+    /// # Arguments:
+    /// * `id` - the `client_id` of the client
+    /// * `secret` - the unhashed `client_secret`
+    pub async fn check_oauth_client_secret(&self, id: String, secret: String) -> bool {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT \"secret\" FROM \"gup_oauth_clients\" WHERE \"id\" = ?"
+        } else {
+            "SELECT \"secret\" FROM \"gup_oauth_clients\" WHERE \"id\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&String>(&id).fetch_one(c).await {
+            Ok(row) => {
+                let hashed = row.get::<String, _>("secret");
+                !hashed.is_empty() && (hashed == dorsal::utility::hash(secret))
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Get the keys ID tokens are signed with, newest first. Returns `(kid, pem)` pairs
+    ///
+    /// Keys are created when the server starts, see [`Database::create_oauth_key`].
+    ///
+    /// # Arguments:
+    /// * `key` - the `secret_key` setting
+    pub async fn get_oauth_keys(&self, key: &str) -> DefaultReturn<Vec<(String, String)>> {
+        let query = "SELECT * FROM \"gup_oauth_keys\" ORDER BY \"created\" DESC";
+
+        let c = &self.base.db.client;
+        let keys: Vec<(String, String)> = match sqlquery(query).fetch_all(c).await {
+            Ok(rows) => rows
+                .iter()
+                .filter_map(|row| {
+                    crate::crypto::decrypt(key, &row.get::<String, _>("private_key"))
+                        .map(|pem| (row.get::<String, _>("id"), pem))
+                })
+                .collect(),
+            Err(e) => {
+                return DefaultReturn {
+                    success: false,
+                    message: e.to_string(),
+                    payload: Vec::new(),
+                }
+            }
+        };
+
+        if keys.is_empty() {
+            return DefaultReturn {
+                success: false,
+                message: String::from("No signing keys exist"),
+                payload: keys,
+            };
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Keys exist"),
+            payload: keys,
+        }
+    }
+
+    /// Get the subject identifier (`sub`) of a user, creating it the first time they authorize
+    /// a client
+    ///
+    /// Subjects are random instead of the username, so a username taken again after its account
+    /// is deleted never gets the subject of the old account.
+    ///
+    /// # Arguments:
+    /// * `username` - the username of the user
+    pub async fn get_oauth_subject(&self, username: String) -> DefaultReturn<Option<String>> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT \"subject\" FROM \"gup_oauth_subjects\" WHERE \"username\" = ?"
+        } else {
+            "SELECT \"subject\" FROM \"gup_oauth_subjects\" WHERE \"username\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        if let Ok(row) = sqlquery(query)
+            .bind::<&String>(&username)
+            .fetch_one(c)
+            .await
+        {
+            return DefaultReturn {
+                success: true,
+                message: String::from("Subject exists"),
+                payload: Option::Some(row.get::<String, _>("subject")),
+            };
+        }
+
+        // create, if another request created one first this fails and theirs is used
+        let insert: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_oauth_subjects\" VALUES (?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_oauth_subjects\" VALUES ($1, $2, $3)"
+        };
+
+        let _ = sqlquery(insert)
+            .bind::<&String>(&username)
+            .bind::<&String>(&dorsal::utility::random_id())
+            .bind(dorsal::utility::unix_epoch_timestamp() as i64)
+            .execute(c)
+            .await;
+
+        match sqlquery(query)
+            .bind::<&String>(&username)
+            .fetch_one(c)
+            .await
+        {
+            Ok(row) => DefaultReturn {
+                success: true,
+                message: String::from("Subject created"),
+                payload: Option::Some(row.get::<String, _>("subject")),
+            },
+            Err(e) => DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            },
+        }
+    }
+
+    /// Create the key ID tokens are signed with, unless one already exists
+    ///
+    /// The key is generated on a blocking thread, and only inserted if the table is still empty
+    /// so every server sharing the database signs with the same key.
+    ///
+    /// # Arguments:
+    /// * `key` - the `secret_key` setting
+    pub async fn create_oauth_key(&self, key: &str) -> DefaultReturn<bool> {
+        let c = &self.base.db.client;
+        let existing = sqlquery("SELECT COUNT(*) FROM \"gup_oauth_keys\"")
+            .fetch_one(c)
+            .await;
+
+        match existing {
+            Ok(row) if row.get::<i64, _>(0) > 0 => {
+                return DefaultReturn {
+                    success: true,
+                    message: String::from("Key exists"),
+                    payload: false,
+                }
+            }
+            Ok(_) => (),
+            Err(e) => {
+                return DefaultReturn {
+                    success: false,
+                    message: e.to_string(),
+                    payload: false,
+                }
+            }
+        }
+
+        // generating an rsa key takes a while, keep it off the async workers
+        let pem = match actix_web::rt::task::spawn_blocking(crate::oauth::generate_key).await {
+            Ok(Some(p)) => p,
+            _ => {
+                return DefaultReturn {
+                    success: false,
+                    message: String::from("Failed to generate signing key"),
+                    payload: false,
+                }
+            }
+        };
+
+        let query: &str = match self.base.db._type.as_str() {
+            "sqlite" => "INSERT INTO \"gup_oauth_keys\" SELECT ?, ?, ? WHERE NOT EXISTS (SELECT 1 FROM \"gup_oauth_keys\")",
+            "mysql" => "INSERT INTO \"gup_oauth_keys\" SELECT ?, ?, ? FROM DUAL WHERE NOT EXISTS (SELECT 1 FROM \"gup_oauth_keys\")",
+            _ => "INSERT INTO \"gup_oauth_keys\" SELECT $1, $2, CAST($3 AS BIGINT) WHERE NOT EXISTS (SELECT 1 FROM \"gup_oauth_keys\")",
+        };
+
+        match sqlquery(query)
+            .bind::<&String>(&dorsal::utility::uuid())
+            .bind::<&String>(&crate::crypto::encrypt(key, &pem))
+            .bind(dorsal::utility::unix_epoch_timestamp() as i64)
+            .execute(c)
+            .await
+        {
+            Ok(r) => DefaultReturn {
+                success: true,
+                message: String::from("Key created"),
+                payload: r.rows_affected() > 0,
+            },
+            Err(e) => DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: false,
+            },
+        }
+    }
+
+    /// Exchange an authorization code, the code can't be used again after this
+    ///
+    /// # Arguments:
+    /// * `code` - the unhashed code
+    pub async fn take_oauth_code(&self, code: String) -> DefaultReturn<Option<OAuthCode>> {
+        let mut con = match self.base.cachedb.client.get_connection() {
+            Ok(c) => c,
+            Err(e) => {
+                return DefaultReturn {
+                    success: false,
+                    message: e.to_string(),
+                    payload: Option::None,
+                }
+            }
+        };
+        let res: redis::RedisResult<Option<String>> = redis::cmd("GETDEL")
+            .arg(format!("oauth-code:{}", dorsal::utility::hash(code)))
+            .query(&mut con);
+
+        match res
+            .ok()
+            .flatten()
+            .and_then(|c| serde_json::from_str::<OAuthCode>(&c).ok())
+        {
+            Some(c) => DefaultReturn {
+                success: true,
+                message: String::from("Code exists"),
+                payload: Option::Some(c),
+            },
+            None => DefaultReturn {
+                success: false,
+                message: String::from("Code does not exist or has expired"),
+                payload: Option::None,
+            },
+        }
+    }
+
+    /// Get an [`OAuthAccessToken`]
+    ///
+    /// # Arguments:
+    /// * `token` - the unhashed access token
+    pub async fn get_oauth_access_token(
+        &self,
+        token: String,
+    ) -> DefaultReturn<Option<OAuthAccessToken>> {
+        match self
+            .base
+            .cachedb
+            .get(format!("oauth-access:{}", dorsal::utility::hash(token)))
+            .await
+            .and_then(|t| serde_json::from_str::<OAuthAccessToken>(&t).ok())
+        {
+            Some(t) => DefaultReturn {
+                success: true,
+                message: String::from("Token exists"),
+                payload: Option::Some(t),
+            },
+            None => DefaultReturn {
+                success: false,
+                message: String::from("Token does not exist or has expired"),
+                payload: Option::None,
+            },
+        }
+    }
+
+    // SET
+    /// Register a new [`OAuthClient`]. Returns the `client_id` and unhashed `client_secret`
+    /// (empty for public clients)
+    ///
+    /// # Arguments:
+    /// * `owner` - the username of the user registering the client
+    /// * `name` - the name shown to users when they authorize the client
+    /// * `redirect_uris` - the URIs users can be sent back to, see
+    ///   [`crate::oauth::valid_redirect_uri`]
+    /// * `public` - if the client can't keep a secret (and has to use PKCE instead)
+    pub async fn create_oauth_client(
+        &self,
+        owner: String,
+        name: String,
+        redirect_uris: Vec<String>,
+        public: bool,
+    ) -> DefaultReturn<Option<(String, String)>> {
+        let name = name.trim().to_string();
+
+        if name.is_empty() | (name.len() > 64) {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Client names must be between 1 and 64 characters"),
+                payload: Option::None,
+            };
+        }
+
+        if redirect_uris.is_empty() {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Clients need at least one redirect URI"),
+                payload: Option::None,
+            };
+        }
+
+        if let Some(uri) = redirect_uris
+            .iter()
+            .find(|u| !crate::oauth::valid_redirect_uri(u) | u.contains(' '))
+        {
+            return DefaultReturn {
+                success: false,
+                message: format!("Invalid redirect URI \"{uri}\""),
+                payload: Option::None,
+            };
+        }
+
+        // create
+        let id = dorsal::utility::uuid();
+        let secret = if public {
+            String::new()
+        } else {
+            dorsal::utility::random_id()
+        };
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_oauth_clients\" VALUES (?, ?, ?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_oauth_clients\" VALUES ($1, $2, $3, $4, $5, $6)"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query)
+            .bind::<&String>(&id)
+            .bind::<&String>(&if public {
+                String::new()
+            } else {
+                dorsal::utility::hash(secret.clone())
+            })
+            .bind::<&String>(&name)
+            .bind::<&String>(&owner)
+            .bind::<&String>(&redirect_uris.join(" "))
+            .bind(dorsal::utility::unix_epoch_timestamp() as i64)
+            .execute(c)
+            .await;
+
+        if let Err(e) = res {
+            return DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            };
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Client created"),
+            payload: Option::Some((id, secret)),
+        }
+    }
+
+    /// Delete an [`OAuthClient`] by its `id`
+    ///
+    /// Tokens already issued to the client keep working until they expire.
+    ///
+    /// # Arguments:
+    /// * `id` - the `client_id` of the client
+    pub async fn delete_oauth_client(&self, id: String) -> DefaultReturn<bool> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_oauth_clients\" WHERE \"id\" = ?"
+        } else {
+            "DELETE FROM \"gup_oauth_clients\" WHERE \"id\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&String>(&id).execute(c).await {
+            Ok(r) if r.rows_affected() > 0 => DefaultReturn {
+                success: true,
+                message: String::from("Client deleted"),
+                payload: true,
+            },
+            _ => DefaultReturn {
+                success: false,
+                message: String::from("Client does not exist"),
+                payload: false,
+            },
+        }
+    }
+
+    /// Create an authorization code. Returns the unhashed code
+    ///
+    /// # Arguments:
+    /// * `code` - what the code is for
+    pub async fn create_oauth_code(&self, code: OAuthCode) -> DefaultReturn<Option<String>> {
+        self.cache_oauth_secret("oauth-code", &code, crate::oauth::CODE_MAX_AGE)
+            .await
+    }
+
+    /// Create an access token. Returns the unhashed token
+    ///
+    /// # Arguments:
+    /// * `token` - what the token is for
+    pub async fn create_oauth_access_token(
+        &self,
+        token: OAuthAccessToken,
+    ) -> DefaultReturn<Option<String>> {
+        self.cache_oauth_secret("oauth-access", &token, crate::oauth::TOKEN_MAX_AGE)
+            .await
+    }
+
+    /// Store `value` in the cache under the hash of a new random secret. Returns the secret
+    async fn cache_oauth_secret<T: Serialize>(
+        &self,
+        prefix: &str,
+        value: &T,
+        max_age: u64,
+    ) -> DefaultReturn<Option<String>> {
+        let secret = dorsal::utility::random_id();
+
+        let mut con = match self.base.cachedb.client.get_connection() {
+            Ok(c) => c,
+            Err(e) => {
+                return DefaultReturn {
+                    success: false,
+                    message: e.to_string(),
+                    payload: Option::None,
+                }
+            }
+        };
+        let res: redis::RedisResult<String> = con.set_ex(
+            format!("{prefix}:{}", dorsal::utility::hash(secret.clone())),
+            serde_json::to_string(value).unwrap(),
+            max_age,
+        );
+
+        match res {
+            Ok(_) => DefaultReturn {
+                success: true,
+                message: String::from("Created"),
+                payload: Option::Some(secret),
+            },
+            Err(e) => DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            },
+        }
+    }
+
+    // passwords
+
+    // GET
+    /// Check if a user can sign in with a password
+    ///
+    /// # Arguments:
+    /// * `username` - the username of the user
+    pub async fn has_password(&self, username: String) -> bool {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT \"username\" FROM \"gup_passwords\" WHERE \"username\" = ?"
+        } else {
+            "SELECT \"username\" FROM \"gup_passwords\" WHERE \"username\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        sqlquery(query)
+            .bind::<&String>(&username)
+            .fetch_one(c)
+            .await
+            .is_ok()
+    }
+
+    /// Get a user by their username and password
+    ///
+    /// Returns [`AuthError::NotFound`] if the user doesn't exist, has no password, or the
+    /// password is wrong.
+    ///
+    /// # Arguments:
+    /// * `username` - the username of the user
+    /// * `password` - the password the user entered
+    pub async fn get_user_by_password(
+        &self,
+        username: String,
+        password: String,
+    ) -> Result<FullUser<UserMetadata>> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT \"hash\" FROM \"gup_passwords\" WHERE \"username\" = ?"
+        } else {
+            "SELECT \"hash\" FROM \"gup_passwords\" WHERE \"username\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let hash = sqlquery(query)
+            .bind::<&String>(&username)
+            .fetch_one(c)
+            .await
+            .ok()
+            .map(|row| row.get::<String, _>("hash"));
+
+        // users without a password are checked against a dummy hash, so how long this takes
+        // doesn't tell which usernames have one
+        let valid = actix_web::rt::task::spawn_blocking(move || match hash {
+            Some(hash) => verify_password(&password, &hash),
+            None => {
+                verify_password(&password, dummy_hash());
+                false
+            }
+        })
+        .await
+        .unwrap_or(false);
+
+        if !valid {
+            return Err(AuthError::NotFound);
+        }
+
+        // return
+        self.get_user_by_username(username).await
+    }
+
+    // SET
+    /// Set the password of a user, replacing their current one
+    ///
+    /// # Arguments:
+    /// * `username` - the username of the user
+    /// * `password` - the new password
+    pub async fn set_password(&self, username: String, password: String) -> DefaultReturn<bool> {
+        let length = password.chars().count();
+
+        if !(PASSWORD_MIN_LENGTH..=PASSWORD_MAX_LENGTH).contains(&length) {
+            return DefaultReturn {
+                success: false,
+                message: format!(
+                    "Passwords must be between {PASSWORD_MIN_LENGTH} and {PASSWORD_MAX_LENGTH} characters"
+                ),
+                payload: false,
+            };
+        }
+
+        let hash = match actix_web::rt::task::spawn_blocking(move || hash_password(&password))
+            .await
+            .ok()
+            .flatten()
+        {
+            Some(h) => h,
+            None => {
+                return DefaultReturn {
+                    success: false,
+                    message: String::from("Failed to hash password"),
+                    payload: false,
+                }
+            }
+        };
+
+        // replace, in one transaction so a failed insert doesn't leave the user without a password
+        let c = &self.base.db.client;
+        let mut tx = match c.begin().await {
+            Ok(tx) => tx,
+            Err(e) => {
+                return DefaultReturn {
+                    success: false,
+                    message: e.to_string(),
+                    payload: false,
+                }
+            }
+        };
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_passwords\" WHERE \"username\" = ?"
+        } else {
+            "DELETE FROM \"gup_passwords\" WHERE \"username\" = $1"
+        };
+
+        if let Err(e) = sqlquery(query)
+            .bind::<&String>(&username)
+            .execute(&mut *tx)
+            .await
+        {
+            return DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: false,
+            };
+        }
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_passwords\" VALUES (?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_passwords\" VALUES ($1, $2, $3)"
+        };
+
+        if let Err(e) = sqlquery(query)
+            .bind::<&String>(&username)
+            .bind::<&String>(&hash)
+            .bind(dorsal::utility::unix_epoch_timestamp() as i64)
+            .execute(&mut *tx)
+            .await
+        {
+            return DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: false,
+            };
+        }
+
+        if let Err(e) = tx.commit().await {
+            return DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: false,
+            };
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Password set"),
+            payload: true,
+        }
+    }
+
+    /// Remove the password of a user, so they can only sign in with their ID
+    ///
+    /// # Arguments:
+    /// * `username` - the username of the user
+    pub async fn remove_password(&self, username: String) -> DefaultReturn<bool> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_passwords\" WHERE \"username\" = ?"
+        } else {
+            "DELETE FROM \"gup_passwords\" WHERE \"username\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&String>(&username).execute(c).await {
+            Ok(r) if r.rows_affected() > 0 => DefaultReturn {
+                success: true,
+                message: String::from("Password removed"),
+                payload: true,
+            },
+            _ => DefaultReturn {
+                success: false,
+                message: String::from("User does not have a password"),
+                payload: false,
+            },
+        }
+    }
+
+    // two-factor authentication
+
+    // GET
+    /// Get the [`Totp`] of a user
+    ///
+    /// # Arguments:
+    /// * `username` - the username of the user
+    pub async fn get_totp(&self, username: String) -> DefaultReturn<Option<Totp>> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"gup_totp\" WHERE \"username\" = ?"
+        } else {
+            "SELECT * FROM \"gup_totp\" WHERE \"username\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&username)
+            .fetch_one(c)
+            .await
+        {
+            Ok(row) => DefaultReturn {
+                success: true,
+                message: String::from("Two-factor authentication exists"),
+                payload: Option::Some(totp_from_row(&row)),
+            },
+            Err(_) => DefaultReturn {
+                success: false,
+                message: String::from("Two-factor authentication is not enabled"),
+                payload: Option::None,
+            },
+        }
+    }
+
+    /// Check if a user has finished enabling two-factor authentication
+    ///
+    /// # Arguments:
+    /// * `username` - the username of the user
+    pub async fn is_totp_enabled(&self, username: String) -> bool {
+        match self.get_totp(username).await.payload {
+            Some(totp) => totp.enabled,
+            None => false,
+        }
+    }
+
+    /// Check a code from a user's authenticator app, or one of their recovery codes
+    ///
+    /// Accepted codes can't be used again.
+    ///
+    /// # Arguments:
+    /// * `username` - the username of the user
+    /// * `key` - the `secret_key` setting
+    /// * `code` - the code the user entered
+    pub async fn check_totp(
+        &self,
+        username: String,
+        key: &str,
+        code: String,
+    ) -> DefaultReturn<bool> {
+        let totp = match self.get_totp(username.clone()).await.payload {
+            Some(t) if t.enabled => t,
+            _ => {
+                return DefaultReturn {
+                    success: false,
+                    message: String::from("Two-factor authentication is not enabled"),
+                    payload: false,
+                }
+            }
+        };
+
+        let secret = match crate::crypto::decrypt(key, &totp.secret) {
+            Some(s) => s,
+            None => {
+                return DefaultReturn {
+                    success: false,
+                    message: String::from("Failed to decrypt two-factor authentication secret"),
+                    payload: false,
+                }
+            }
+        };
+
+        let now = (dorsal::utility::unix_epoch_timestamp() / 1000) as u64;
+        let c = &self.base.db.client;
+
+        // authenticator code
+        if let Some(step) = crate::totp::verify(&secret, &code, now) {
+            // only accept steps after the last accepted one, this is checked in the
+            // query so the same code can't be accepted by two requests at once
+            let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+            {
+                "UPDATE \"gup_totp\" SET \"last_step\" = ? WHERE \"username\" = ? AND \"last_step\" < ?"
+            } else {
+                "UPDATE \"gup_totp\" SET \"last_step\" = $1 WHERE \"username\" = $2 AND \"last_step\" < $3"
+            };
+
+            if let Ok(r) = sqlquery(query)
+                .bind(step as i64)
+                .bind::<&String>(&username)
+                .bind(step as i64)
+                .execute(c)
+                .await
+            {
+                if r.rows_affected() > 0 {
+                    return DefaultReturn {
+                        success: true,
+                        message: String::from("Code accepted"),
+                        payload: true,
+                    };
+                }
+            }
+
+            return DefaultReturn {
+                success: false,
+                message: String::from("Code already used"),
+                payload: false,
+            };
+        }
+
+        // recovery code
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_recovery_codes\" WHERE \"username\" = ? AND \"code\" = ?"
+        } else {
+            "DELETE FROM \"gup_recovery_codes\" WHERE \"username\" = $1 AND \"code\" = $2"
+        };
+
+        match sqlquery(query)
+            .bind::<&String>(&username)
+            .bind::<&String>(&dorsal::utility::hash(code.trim().to_lowercase()))
+            .execute(c)
+            .await
+        {
+            Ok(r) if r.rows_affected() > 0 => DefaultReturn {
+                success: true,
+                message: String::from("Recovery code accepted"),
+                payload: true,
+            },
+            _ => DefaultReturn {
+                success: false,
+                message: String::from("Invalid code"),
+                payload: false,
+            },
+        }
+    }
+
+    // SET
+    /// Start enabling two-factor authentication for a user. Returns the unencrypted secret
+    ///
+    /// Two-factor authentication isn't required until it's confirmed with [`Database::confirm_totp`].
+    ///
+    /// # Arguments:
+    /// * `username` - the username of the user
+    /// * `key` - the `secret_key` setting
+    pub async fn begin_totp(&self, username: String, key: &str) -> DefaultReturn<Option<String>> {
+        if self.is_totp_enabled(username.clone()).await {
+            return DefaultReturn {
+                success: false,
+                message: String::from("Two-factor authentication is already enabled"),
+                payload: Option::None,
+            };
+        }
+
+        // replace any unfinished enrollment
+        self.disable_totp(username.clone()).await;
+
+        let secret = crate::totp::generate_secret();
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_totp\" VALUES (?, ?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"gup_totp\" VALUES ($1, $2, $3, $4, $5)"
+        };
+
+        let c = &self.base.db.client;
+        let res = sqlquery(query)
+            .bind::<&String>(&username)
+            .bind::<&String>(&crate::crypto::encrypt(key, &secret))
+            .bind(0_i64)
+            .bind(0_i64)
+            .bind(dorsal::utility::unix_epoch_timestamp() as i64)
+            .execute(c)
+            .await;
+
+        if let Err(e) = res {
+            return DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            };
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Two-factor authentication started"),
+            payload: Option::Some(secret),
+        }
+    }
+
+    /// Finish enabling two-factor authentication for a user with a code from their
+    /// authenticator app. Returns the unhashed recovery codes
+    ///
+    /// # Arguments:
+    /// * `username` - the username of the user
+    /// * `key` - the `secret_key` setting
+    /// * `code` - the code the user entered
+    pub async fn confirm_totp(
+        &self,
+        username: String,
+        key: &str,
+        code: String,
+    ) -> DefaultReturn<Option<Vec<String>>> {
+        let totp = match self.get_totp(username.clone()).await.payload {
+            Some(t) if !t.enabled => t,
+            Some(_) => {
+                return DefaultReturn {
+                    success: false,
+                    message: String::from("Two-factor authentication is already enabled"),
+                    payload: Option::None,
+                }
+            }
+            None => {
+                return DefaultReturn {
+                    success: false,
+                    message: String::from("Two-factor authentication has not been started"),
+                    payload: Option::None,
+                }
+            }
+        };
+
+        let step = match crate::crypto::decrypt(key, &totp.secret).and_then(|secret| {
+            crate::totp::verify(
+                &secret,
+                &code,
+                (dorsal::utility::unix_epoch_timestamp() / 1000) as u64,
+            )
+        }) {
+            Some(s) => s,
+            None => {
+                return DefaultReturn {
+                    success: false,
+                    message: String::from("Invalid code"),
+                    payload: Option::None,
+                }
+            }
+        };
+
+        // enable and create recovery codes together, so users never have two-factor
+        // authentication without recovery codes
+        let codes = crate::totp::generate_recovery_codes();
+
+        let c = &self.base.db.client;
+        let mut tx = match c.begin().await {
+            Ok(tx) => tx,
+            Err(e) => {
+                return DefaultReturn {
+                    success: false,
+                    message: e.to_string(),
+                    payload: Option::None,
+                }
+            }
+        };
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "UPDATE \"gup_totp\" SET \"enabled\" = 1, \"last_step\" = ? WHERE \"username\" = ?"
+        } else {
+            "UPDATE \"gup_totp\" SET \"enabled\" = 1, \"last_step\" = $1 WHERE \"username\" = $2"
+        };
+
+        if let Err(e) = sqlquery(query)
+            .bind(step as i64)
+            .bind::<&String>(&username)
+            .execute(&mut *tx)
+            .await
+        {
+            return DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            };
+        }
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"gup_recovery_codes\" VALUES (?, ?)"
+        } else {
+            "INSERT INTO \"gup_recovery_codes\" VALUES ($1, $2)"
+        };
+
+        for code in &codes {
+            if let Err(e) = sqlquery(query)
+                .bind::<&String>(&username)
+                .bind::<&String>(&dorsal::utility::hash(code.to_owned()))
+                .execute(&mut *tx)
+                .await
+            {
+                return DefaultReturn {
+                    success: false,
+                    message: e.to_string(),
+                    payload: Option::None,
+                };
+            }
+        }
+
+        if let Err(e) = tx.commit().await {
+            return DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            };
+        }
+
+        // return
+        DefaultReturn {
+            success: true,
+            message: String::from("Two-factor authentication enabled"),
+            payload: Option::Some(codes),
+        }
+    }
+
+    /// Disable two-factor authentication for a user, deleting their secret and recovery codes
+    ///
+    /// # Arguments:
+    /// * `username` - the username of the user
+    pub async fn disable_totp(&self, username: String) -> DefaultReturn<bool> {
+        let c = &self.base.db.client;
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_recovery_codes\" WHERE \"username\" = ?"
+        } else {
+            "DELETE FROM \"gup_recovery_codes\" WHERE \"username\" = $1"
+        };
+
+        let _ = sqlquery(query).bind::<&String>(&username).execute(c).await;
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"gup_totp\" WHERE \"username\" = ?"
+        } else {
+            "DELETE FROM \"gup_totp\" WHERE \"username\" = $1"
+        };
+
+        match sqlquery(query).bind::<&String>(&username).execute(c).await {
+            Ok(r) if r.rows_affected() > 0 => DefaultReturn {
+                success: true,
+                message: String::from("Two-factor authentication disabled"),
+                payload: true,
+            },
+            _ => DefaultReturn {
+                success: false,
+                message: String::from("Two-factor authentication is not enabled"),
+                payload: false,
+            },
+        }
+    }
+
+    /// Create a challenge for a user that signed in with two-factor authentication enabled.
+    /// Returns the unhashed challenge token
+    ///
+    /// The challenge is exchanged for a session once the user enters a code (see
+    /// [`Database::get_totp_challenge`]), and expires after [`TOTP_CHALLENGE_MAX_AGE`].
+    ///
+    /// # Arguments:
+    /// * `username` - the username of the user signing in
+    pub async fn create_totp_challenge(&self, username: String) -> DefaultReturn<Option<String>> {
+        let token = dorsal::utility::random_id();
+
+        let mut con = match self.base.cachedb.client.get_connection() {
+            Ok(c) => c,
+            Err(e) => {
+                return DefaultReturn {
+                    success: false,
+                    message: e.to_string(),
+                    payload: Option::None,
+                }
+            }
+        };
+        let res: redis::RedisResult<String> = con.set_ex(
+            format!("totp-challenge:{}", dorsal::utility::hash(token.clone())),
+            username,
+            TOTP_CHALLENGE_MAX_AGE,
+        );
+
+        match res {
+            Ok(_) => DefaultReturn {
+                success: true,
+                message: String::from("Challenge created"),
+                payload: Option::Some(token),
+            },
+            Err(e) => DefaultReturn {
+                success: false,
+                message: e.to_string(),
+                payload: Option::None,
+            },
+        }
+    }
+
+    /// Get the username a challenge was created for
+    ///
+    /// # Arguments:
+    /// * `token` - the unhashed challenge token
+    pub async fn get_totp_challenge(&self, token: String) -> DefaultReturn<Option<String>> {
+        match self
+            .base
+            .cachedb
+            .get(format!("totp-challenge:{}", dorsal::utility::hash(token)))
+            .await
+        {
+            Some(username) => DefaultReturn {
+                success: true,
+                message: String::from("Challenge exists"),
+                payload: Option::Some(username),
+            },
+            None => DefaultReturn {
+                success: false,
+                message: String::from("Challenge does not exist or has expired"),
+                payload: Option::None,
+            },
+        }
+    }
+
+    /// Record a wrong code for a challenge, removing the challenge after
+    /// [`TOTP_CHALLENGE_ATTEMPTS`] wrong codes
+    ///
+    /// # Arguments:
+    /// * `token` - the unhashed challenge token
+    pub async fn fail_totp_challenge(&self, token: String) {
+        let hashed = dorsal::utility::hash(token.clone());
+
+        let mut con = match self.base.cachedb.client.get_connection() {
+            Ok(c) => c,
+            Err(_) => return,
+        };
+        let attempts: i64 = redis::pipe()
+            .incr(format!("totp-challenge-attempts:{}", hashed), 1)
+            .expire(
+                format!("totp-challenge-attempts:{}", hashed),
+                TOTP_CHALLENGE_MAX_AGE as i64,
+            )
+            .ignore()
+            .query::<(i64,)>(&mut con)
+            .map(|r| r.0)
+            .unwrap_or(TOTP_CHALLENGE_ATTEMPTS);
+
+        if attempts >= TOTP_CHALLENGE_ATTEMPTS {
+            self.remove_totp_challenge(token).await;
+        }
+    }
+
+    /// Remove a challenge so it can't be used again
+    ///
+    /// # Arguments:
+    /// * `token` - the unhashed challenge token
+    pub async fn remove_totp_challenge(&self, token: String) {
+        let hashed = dorsal::utility::hash(token);
+
+        self.base
+            .cachedb
+            .remove(format!("totp-challenge:{}", hashed))
+            .await;
+
+        self.base
+            .cachedb
+            .remove(format!("totp-challenge-attempts:{}", hashed))
+            .await;
+    }
+}
+
+/// Read an [`ApiToken`] from a row of the `gup_api_tokens` table
+fn api_token_from_row<R>(row: &R) -> ApiToken
+where
+    R: Row,
+    &'static str: sqlx::ColumnIndex<R>,
+    String: for<'r> sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+    i64: for<'r> sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+{
+    ApiToken {
+        id: row.get("id"),
+        username: row.get("username"),
+        name: row.get("name"),
+        scopes: row
+            .get::<String, _>("scopes")
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect(),
+        expires: row.get::<i64, _>("expires") as u128,
+        last_used: row.get::<i64, _>("last_used") as u128,
+        created: row.get::<i64, _>("created") as u128,
+    }
+}
+
+/// Read an [`OAuthClient`] from a row of the `gup_oauth_clients` table
+fn oauth_client_from_row<R>(row: &R) -> OAuthClient
+where
+    R: Row,
+    &'static str: sqlx::ColumnIndex<R>,
+    String: for<'r> sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+    i64: for<'r> sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+{
+    OAuthClient {
+        id: row.get("id"),
+        name: row.get("name"),
+        owner: row.get("owner"),
+        redirect_uris: row
+            .get::<String, _>("redirect_uris")
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect(),
+        public: row.get::<String, _>("secret").is_empty(),
+        created: row.get::<i64, _>("created") as u128,
+    }
+}
+
+/// Hash a password with argon2, `None` if hashing fails
+fn hash_password(password: &str) -> Option<String> {
+    use argon2::password_hash::{rand_core::OsRng, PasswordHasher, SaltString};
+
+    argon2::Argon2::default()
+        .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))
+        .map(|h| h.to_string())
+        .ok()
+}
+
+/// Check a password against a hash created by [`hash_password`]
+fn verify_password(password: &str, hash: &str) -> bool {
+    use argon2::password_hash::{PasswordHash, PasswordVerifier};
+
+    match PasswordHash::new(hash) {
+        Ok(parsed) => argon2::Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// A hash of a password nobody has, checked when a user has no password
+fn dummy_hash() -> &'static str {
+    static HASH: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    HASH.get_or_init(|| hash_password("guppy").unwrap_or_default())
+}
+
+/// Read a [`Totp`] from a row of the `gup_totp` table
+fn totp_from_row<R>(row: &R) -> Totp
+where
+    R: Row,
+    &'static str: sqlx::ColumnIndex<R>,
+    String: for<'r> sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+    i64: for<'r> sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+{
+    Totp {
+        username: row.get("username"),
+        secret: row.get("secret"),
+        enabled: row.get::<i64, _>("enabled") != 0,
+        last_step: row.get::<i64, _>("last_step") as u64,
+        timestamp: row.get::<i64, _>("timestamp") as u128,
+    }
+}
+
+/// Read a [`Session`] from a row of the `gup_sessions` table
+fn session_from_row<R>(row: &R) -> Session
+where
+    R: Row,
+    &'static str: sqlx::ColumnIndex<R>,
+    String: for<'r> sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+    i64: for<'r> sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+{
+    Session {
+        id: row.get("id"),
+        username: row.get("username"),
+        user_agent: row.get("user_agent"),
+        created: row.get::<i64, _>("created") as u128,
+        last_seen: row.get::<i64, _>("last_seen") as u128,
+        current: false,
+    }
+}
+
+/// Read an [`Invite`] from a row of the `gup_invites` table
+fn invite_from_row<R>(row: &R) -> Invite
+where
+    R: Row,
+    &'static str: sqlx::ColumnIndex<R>,
+    String: for<'r> sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+    i64: for<'r> sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+{
+    Invite {
+        code: row.get("code"),
+        author: row.get("author"),
+        max_uses: row.get::<i64, _>("max_uses") as usize,
+        uses: row.get::<i64, _>("uses") as usize,
+        expires: row.get::<i64, _>("expires") as u128,
+        timestamp: row.get::<i64, _>("timestamp") as u128,
+    }
+}
+
+/// Turn a search query into an FTS5 query that matches rows containing every word
+///
+/// Each word is quoted so characters FTS5 treats as syntax are searched for literally.
+fn fts5_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Read an [`ActivityPost`] from a row of the `gup_posts` table
+///
+/// `textify_row` can't be used for posts since their `timestamp` is a `BIGINT`.
+fn post_from_row<R>(row: &R) -> ActivityPost
+where
+    R: Row,
+    &'static str: sqlx::ColumnIndex<R>,
+    String: for<'r> sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+    i64: for<'r> sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+{
+    ActivityPost {
+        id: row.get("id"),
+        content: row.get("content"),
+        content_html: row.get("content_html"),
+        author: row.get("author"),
+        reply: row.get("reply"),
+        timestamp: row.get::<i64, _>("timestamp") as u128,
     }
 }
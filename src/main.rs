@@ -8,13 +8,20 @@ use actix_files as fs;
 use actix_web::{web, App, HttpServer};
 use dotenv;
 
+pub mod cli;
 pub mod config;
+pub mod crypto;
+pub mod csrf;
 pub mod db;
+pub mod migrations;
 
 pub mod api;
 pub mod pages;
 
 pub mod markup;
+pub mod oauth;
+pub mod ratelimit;
+pub mod totp;
 
 use crate::db::{AppData, Database};
 use dorsal::DatabaseOpts;
@@ -26,53 +33,83 @@ async fn main() -> std::io::Result<()> {
 
     // ...
     let args: Vec<String> = config::collect_arguments();
+    let command: Vec<String> = config::get_positional_arguments(&args);
 
-    let port_search: Option<String> = config::get_named_argument(&args, "port");
-    let mut port: u16 = 8080;
+    let config = match config::Config::load(&args) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+    };
 
-    if port_search.is_some() {
-        port = port_search.unwrap().parse::<u16>().unwrap();
-    }
-
-    let static_dir_flag: Option<String> = config::get_named_argument(&args, "static-dir");
+    let port: u16 = config.port;
 
     // create database
-    let db_type: Option<String> = config::get_named_argument(&args, "db-type");
-    let db_host: Option<String> = config::get_var("DB_HOST");
-    let db_user: Option<String> = config::get_var("DB_USER");
-    let db_pass: Option<String> = config::get_var("DB_PASS");
-    let db_name: Option<String> = config::get_var("DB_NAME");
-
-    let db_is_other: bool = db_type
-        .clone()
-        .is_some_and(|x| (x == String::from("postgres")) | (x == String::from("mysql")));
-
-    if db_is_other && (db_user.is_none() | db_pass.is_none() | db_name.is_none()) {
-        panic!("Missing required database config settings!");
-    }
-
     let db: Database = Database::new(DatabaseOpts {
-        _type: db_type,
-        host: db_host,
-        user: if db_is_other {
-            db_user.unwrap()
-        } else {
-            String::new()
-        },
-        pass: if db_is_other {
-            db_pass.unwrap()
-        } else {
-            String::new()
-        },
-        name: if db_is_other {
-            db_name.unwrap()
-        } else {
-            String::new()
-        },
+        _type: config.database._type.clone(),
+        host: config.database.host.clone(),
+        user: config.database.user.clone().unwrap_or_default(),
+        pass: config.database.pass.clone().unwrap_or_default(),
+        name: config.database.name.clone().unwrap_or_default(),
     })
     .await;
 
-    db.init().await;
+    // run migrations without starting the server
+    if command.first().is_some_and(|x| x == "migrate") {
+        let res = db.migrate().await;
+
+        for version in &res.payload {
+            let migration = crate::migrations::MIGRATIONS
+                .iter()
+                .find(|m| m.version == *version)
+                .unwrap();
+
+            println!(
+                "Applied migration {}: {}",
+                migration.version, migration.name
+            );
+        }
+
+        if !res.success {
+            eprintln!("error: {}", res.message);
+            std::process::exit(1);
+        }
+
+        println!(
+            "Database is at schema version {}",
+            db.get_schema_version().await.payload
+        );
+
+        return Ok(());
+    }
+
+    let res = db.init().await;
+
+    for version in &res.payload {
+        println!("Applied migration {}", version);
+    }
+
+    if !res.success {
+        eprintln!("error: Failed to migrate database: {}", res.message);
+        std::process::exit(1);
+    }
+
+    // run command instead of starting the server
+    if !command.is_empty() {
+        cli::run(&db, &command, &args).await;
+        return Ok(());
+    }
+
+    // signing keys are created once, before any request needs them
+    if config.oauth_enabled() {
+        let res = db.create_oauth_key(&config.secret_key).await;
+
+        if !res.success {
+            eprintln!("error: Failed to create OAuth signing key: {}", res.message);
+            std::process::exit(1);
+        }
+    }
 
     // start server
     println!("Starting server at: http://localhost:{port}");
@@ -83,6 +120,7 @@ async fn main() -> std::io::Result<()> {
         let data = web::Data::new(AppData {
             db: db.clone(),
             http_client: client,
+            config: config.clone(),
         });
 
         let cors = actix_cors::Cors::default().send_wildcard();
@@ -92,34 +130,58 @@ async fn main() -> std::io::Result<()> {
             // middleware
             .wrap(actix_web::middleware::Logger::default())
             .wrap(cors)
+            .wrap(actix_web::middleware::from_fn(
+                crate::ratelimit::login_limit,
+            ))
+            .wrap(actix_web::middleware::from_fn(crate::ratelimit::rate_limit))
+            // runs first, so requests it rejects aren't counted by the limits above
+            .wrap(actix_web::middleware::from_fn(crate::csrf::csrf))
             // static dir
-            .service(
-                fs::Files::new(
-                    "/static",
-                    if static_dir_flag.is_some() {
-                        static_dir_flag.as_ref().unwrap()
-                    } else {
-                        "./static"
-                    },
-                )
-                .show_files_listing(),
-            )
+            .service(fs::Files::new("/static", &config.static_dir).show_files_listing())
             // docs
             .service(fs::Files::new("/api/docs", "./target/doc").show_files_listing())
             // GET api
             .service(crate::api::auth::whoami)
+            .service(crate::api::auth::sessions_request)
+            .service(crate::api::auth::api_tokens_request)
+            .service(crate::api::auth::timeline_request)
+            .service(crate::api::auth::mentions_request)
+            .service(crate::api::auth::public_activity_request)
+            .service(crate::api::auth::search_request)
+            .service(crate::api::auth::tag_request)
+            .service(crate::api::auth::trending_tags_request)
+            .service(crate::api::auth::notifications_request)
+            .service(crate::api::auth::notification_count_request)
+            .service(crate::api::auth::invites_request)
+            .service(crate::api::auth::invite_request)
             // POST api
+            .service(crate::api::auth::mark_notifications_request)
+            .service(crate::api::auth::create_invite_request)
+            .service(crate::api::auth::create_api_token_request)
             // POST activity
             .service(crate::api::auth::post_activity_request)
+            .service(crate::api::auth::edit_activity_request)
             .service(crate::api::auth::delete_activity_request)
             .service(crate::api::auth::favorite_request)
+            // DELETE
+            .service(crate::api::auth::revoke_invite_request)
+            .service(crate::api::auth::delete_sessions_request)
+            .service(crate::api::auth::delete_session_request)
+            .service(crate::api::auth::disable_totp_request)
+            .service(crate::api::auth::remove_password_request)
+            .service(crate::api::auth::delete_api_token_request)
             // POST auth
             .service(crate::api::auth::callback_request)
             .service(crate::api::auth::register)
             .service(crate::api::auth::login)
             .service(crate::api::auth::login_secondary_token)
+            .service(crate::api::auth::login_totp)
+            .service(crate::api::auth::begin_totp_request)
+            .service(crate::api::auth::confirm_totp_request)
+            .service(crate::api::auth::set_password_request)
             .service(crate::api::auth::edit_about_request)
             .service(crate::api::auth::refresh_secondary_token_request)
+            .service(crate::api::auth::rotate_id_request)
             .service(crate::api::auth::update_request)
             .service(crate::api::auth::follow_request)
             .service(crate::api::auth::ban_request)
@@ -128,13 +190,29 @@ async fn main() -> std::io::Result<()> {
             .service(crate::api::auth::followers_request)
             .service(crate::api::auth::following_request)
             .service(crate::api::auth::level_request)
+            // oauth
+            .service(crate::api::oauth::discovery_request)
+            .service(crate::api::oauth::jwks_request)
+            .service(crate::api::oauth::authorize_request)
+            .service(crate::api::oauth::token_request)
+            .service(crate::api::oauth::userinfo_request)
+            .service(crate::api::oauth::clients_request)
+            .service(crate::api::oauth::create_client_request)
+            .service(crate::api::oauth::delete_client_request)
+            .service(crate::pages::oauth::authorize_request)
             // GET dashboard
             .service(crate::pages::auth::register_request)
             .service(crate::pages::auth::login_request)
             .service(crate::pages::auth::login_secondary_token_request)
+            .service(crate::pages::auth::login_password_request)
             // GET root
             .service(crate::api::auth::logout)
             .service(crate::pages::home::home_request)
+            .service(crate::pages::activity::public_activity_request)
+            .service(crate::pages::activity::notifications_request)
+            .service(crate::pages::activity::tag_request)
+            .service(crate::pages::activity::search_request)
+            .service(crate::pages::auth::invites_request)
             // GET users
             .service(crate::pages::auth::followers_request)
             .service(crate::pages::auth::following_request)
@@ -3,3 +3,261 @@ use sauropod::markdown::parse_markdown as shared_parse_markdown;
 pub fn render(original_in: &String) -> String {
     shared_parse_markdown(original_in.to_owned(), Vec::new())
 }
+
+/// Split the given input into the parts outside of code and the parts inside it, as
+/// `(part, is_code)`
+///
+/// Code is anything between two runs of the same number of backticks, which covers both code
+/// spans and fenced code blocks. Mentions and tags in code are left alone.
+fn split_code(input: &str) -> Vec<(&str, bool)> {
+    let bytes = input.as_bytes();
+    let mut out: Vec<(&str, bool)> = Vec::new();
+    let mut start: usize = 0;
+    let mut i: usize = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'`' {
+            i += 1;
+            continue;
+        }
+
+        // opening run
+        let open = i;
+
+        while (i < bytes.len()) && (bytes[i] == b'`') {
+            i += 1;
+        }
+
+        let ticks = &input[open..i];
+
+        // closing run, it has to be exactly as long as the opening one
+        let mut search = i;
+        let mut close: Option<usize> = None;
+
+        while let Some(found) = input[search..].find(ticks) {
+            let run = search + found;
+            let mut end = run;
+
+            while (end < bytes.len()) && (bytes[end] == b'`') {
+                end += 1;
+            }
+
+            if end - run == ticks.len() {
+                close = Some(end);
+                break;
+            }
+
+            search = end;
+        }
+
+        // a run that's never closed is just text
+        if let Some(end) = close {
+            out.push((&input[start..open], false));
+            out.push((&input[open..end], true));
+            start = end;
+            i = end;
+        }
+    }
+
+    out.push((&input[start..], false));
+    out
+}
+
+/// Build the regex used to find `@username` mentions
+///
+/// Mentions must start the input or follow a character that can't be part of a username,
+/// so email addresses aren't treated as mentions.
+fn mention_regex() -> regex::Regex {
+    regex::Regex::new("(^|[^\\w\\-\\.\\!@])@([\\w\\-\\.\\!]+)").unwrap()
+}
+
+/// Get the username a mention match refers to (trailing punctuation is dropped)
+fn mention_username(matched: &str) -> String {
+    matched.trim_end_matches(['.', '!']).to_lowercase()
+}
+
+/// Get the usernames of every `@username` mention outside of code in the given input, without
+/// duplicates
+///
+/// # Arguments:
+/// * `input` - the raw (markdown) content to search
+pub fn find_mentions(input: &str) -> Vec<String> {
+    let regex = mention_regex();
+    let mut out: Vec<String> = Vec::new();
+
+    for capture in split_code(input)
+        .into_iter()
+        .filter(|(_, is_code)| !is_code)
+        .flat_map(|(part, _)| regex.captures_iter(part))
+    {
+        let username = mention_username(capture.get(2).unwrap().as_str());
+
+        if (username.len() < 2) | out.contains(&username) {
+            continue;
+        }
+
+        out.push(username);
+    }
+
+    out
+}
+
+/// Build the regex used to find `#tag` hashtags
+///
+/// Like mentions, tags must start the input or follow a character that can't be part of a tag,
+/// so links to page anchors and HTML entities aren't treated as tags.
+fn tag_regex() -> regex::Regex {
+    regex::Regex::new("(^|[^\\w&#/])#(\\w+)").unwrap()
+}
+
+/// Get the tag a hashtag match refers to, or `None` if it isn't a valid tag
+///
+/// Tags made only of digits (like `#1`) are ignored.
+fn tag_name(matched: &str) -> Option<String> {
+    if (matched.len() > 100) | matched.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(matched.to_lowercase())
+}
+
+/// Get the tags of every `#tag` hashtag outside of code in the given input, without duplicates
+///
+/// # Arguments:
+/// * `input` - the raw (markdown) content to search
+pub fn find_tags(input: &str) -> Vec<String> {
+    let regex = tag_regex();
+    let mut out: Vec<String> = Vec::new();
+
+    for capture in split_code(input)
+        .into_iter()
+        .filter(|(_, is_code)| !is_code)
+        .flat_map(|(part, _)| regex.captures_iter(part))
+    {
+        if let Some(tag) = tag_name(capture.get(2).unwrap().as_str()) {
+            if !out.contains(&tag) {
+                out.push(tag);
+            }
+        }
+    }
+
+    out
+}
+
+/// Render the content of a post, linking every hashtag to its tag page and every mention of
+/// a user in `users` to their profile (except in code)
+///
+/// # Arguments:
+/// * `original_in` - the raw (markdown) content to render
+/// * `users` - the usernames that can be linked, from [`find_mentions`]
+pub fn render_post(original_in: &str, users: &[String]) -> String {
+    let tags = tag_regex();
+    let mentions = mention_regex();
+    let mut linked = String::new();
+
+    for (part, is_code) in split_code(original_in) {
+        if is_code {
+            linked.push_str(part);
+            continue;
+        }
+
+        let part = tags.replace_all(part, |capture: &regex::Captures| {
+            let matched = capture.get(2).unwrap().as_str();
+
+            match tag_name(matched) {
+                Some(tag) => format!(
+                    "{}[#{matched}](/tag/{tag})",
+                    capture.get(1).unwrap().as_str()
+                ),
+                None => capture.get(0).unwrap().as_str().to_string(),
+            }
+        });
+
+        let part = mentions.replace_all(&part, |capture: &regex::Captures| {
+            let matched = capture.get(2).unwrap().as_str();
+            let username = mention_username(matched);
+
+            if !users.contains(&username) {
+                return capture.get(0).unwrap().as_str().to_string();
+            }
+
+            // keep the punctuation we trimmed after the link
+            let name = matched.trim_end_matches(['.', '!']);
+            format!(
+                "{}[@{name}](/{username}){}",
+                capture.get(1).unwrap().as_str(),
+                &matched[name.len()..]
+            )
+        });
+
+        linked.push_str(&part);
+    }
+
+    render(&linked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mentions() {
+        assert_eq!(
+            find_mentions("hi @alice and @Bob, @alice again"),
+            vec!["alice", "bob"]
+        );
+
+        // trailing punctuation isn't part of the username
+        assert_eq!(
+            find_mentions("thanks @alice. and @bob!"),
+            vec!["alice", "bob"]
+        );
+
+        // email addresses and single characters aren't mentions
+        assert!(find_mentions("mail me at alice@example.com").is_empty());
+        assert!(find_mentions("@a").is_empty());
+    }
+
+    #[test]
+    fn tags() {
+        assert_eq!(find_tags("#Rust and #rust, #guppy!"), vec!["rust", "guppy"]);
+
+        // HTML entities, page anchors and numbers aren't tags
+        assert!(find_tags("&#39; and &#x27;").is_empty());
+        assert!(find_tags("see /page/#anchor or a#b").is_empty());
+        assert!(find_tags("issue #1 and #2024").is_empty());
+        assert!(find_tags(&format!("#{}", "a".repeat(101))).is_empty());
+    }
+
+    #[test]
+    fn code_is_ignored() {
+        let input = "#real `#code @alice` @alice\n```\n#fenced @bob\n```\n`` a ` #nested ``";
+
+        assert_eq!(find_tags(input), vec!["real"]);
+        assert_eq!(find_mentions(input), vec!["alice"]);
+
+        // an unclosed backtick is just text
+        assert_eq!(find_tags("`#open"), vec!["open"]);
+    }
+
+    #[test]
+    fn render_links() {
+        let html = render_post("#rust by @alice. and @bob", &[String::from("alice")]);
+
+        assert!(html.contains("<a href=\"/tag/rust\">#rust</a>"), "{html}");
+        assert!(html.contains("<a href=\"/alice\">@alice</a>."), "{html}");
+        assert!(!html.contains("href=\"/bob\""), "{html}");
+    }
+
+    #[test]
+    fn render_leaves_code_alone() {
+        let html = render_post(
+            "`#rust @alice`\n\n```\n#fenced @alice\n```",
+            &[String::from("alice")],
+        );
+
+        assert!(!html.contains("href"), "{html}");
+        assert!(html.contains("#rust @alice"), "{html}");
+        assert!(html.contains("#fenced @alice"), "{html}");
+    }
+}
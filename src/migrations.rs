@@ -0,0 +1,631 @@
+//! Versioned schema migrations
+//!
+//! Every [`Migration`] runs once, in order, and its version is stored in the `gup_schema_version`
+//! table once it succeeds. Migrations that have already been applied must never be edited, add a
+//! new one to [`MIGRATIONS`] instead.
+
+/// A single statement of a [`Migration`]
+pub enum Step {
+    /// A statement that runs on every database type
+    Sql(&'static str),
+    /// Create an index unless the table already has an index with this name
+    ///
+    /// mysql doesn't support `CREATE INDEX IF NOT EXISTS`, so `IF NOT EXISTS` is removed from
+    /// `sql` and the index is looked up before it is created.
+    Index {
+        table: &'static str,
+        name: &'static str,
+        sql: &'static str,
+    },
+    /// A step that only runs on the given database type (`sqlite`, `postgres` or `mysql`)
+    Only(&'static str, &'static Step),
+    /// Steps that only run if the given table exists when they're reached
+    IfExists(&'static str, &'static [Step]),
+    /// Fail the migration unless `copy` has as many rows as `table`, check this before dropping
+    /// a table that was copied
+    SameRows {
+        table: &'static str,
+        copy: &'static str,
+    },
+}
+
+/// A versioned change to the database schema
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub steps: &'static [Step],
+}
+
+/// Every migration, ordered by version
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create users, logs and posts",
+        steps: &[
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"Users\" (
+                    username VARCHAR(1000000),
+                    id_hashed VARCHAR(1000000),
+                    role VARCHAR(1000000),
+                    timestamp VARCHAR(1000000),
+                    metadata VARCHAR(1000000)
+                )",
+            ),
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"Logs\" (
+                    id VARCHAR(1000000),
+                    logtype VARCHAR(1000000),
+                    timestamp  VARCHAR(1000000),
+                    content VARCHAR(1000000)
+                )",
+            ),
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_posts\" (
+                    id VARCHAR(1000000),
+                    author VARCHAR(1000000),
+                    content VARCHAR(1000000),
+                    content_html VARCHAR(1000000),
+                    reply VARCHAR(1000000),
+                    timestamp VARCHAR(1000000)
+                )",
+            ),
+        ],
+    },
+    Migration {
+        version: 2,
+        name: "create follows",
+        steps: &[
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_follows\" (
+                    follower VARCHAR(255),
+                    following VARCHAR(255),
+                    timestamp VARCHAR(100)
+                )",
+            ),
+            Step::Index {
+                table: "gup_follows",
+                name: "gup_follows_pair",
+                sql: "CREATE UNIQUE INDEX IF NOT EXISTS \"gup_follows_pair\" ON \"gup_follows\" (\"follower\", \"following\")",
+            },
+            Step::Index {
+                table: "gup_follows",
+                name: "gup_follows_following",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_follows_following\" ON \"gup_follows\" (\"following\", \"timestamp\")",
+            },
+        ],
+    },
+    Migration {
+        version: 3,
+        name: "create favorites",
+        steps: &[
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_favorites\" (
+                    username VARCHAR(255),
+                    post VARCHAR(255),
+                    timestamp VARCHAR(100)
+                )",
+            ),
+            Step::Index {
+                table: "gup_favorites",
+                name: "gup_favorites_pair",
+                sql: "CREATE UNIQUE INDEX IF NOT EXISTS \"gup_favorites_pair\" ON \"gup_favorites\" (\"post\", \"username\")",
+            },
+        ],
+    },
+    Migration {
+        version: 4,
+        name: "create post revisions",
+        steps: &[
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_post_revisions\" (
+                    id VARCHAR(255) PRIMARY KEY,
+                    post VARCHAR(255) NOT NULL,
+                    content TEXT NOT NULL,
+                    content_html TEXT NOT NULL,
+                    timestamp BIGINT NOT NULL
+                )",
+            ),
+            Step::Index {
+                table: "gup_post_revisions",
+                name: "gup_post_revisions_post",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_post_revisions_post\" ON \"gup_post_revisions\" (\"post\", \"timestamp\")",
+            },
+        ],
+    },
+    Migration {
+        version: 5,
+        name: "create mentions",
+        steps: &[
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_mentions\" (
+                    post VARCHAR(255),
+                    username VARCHAR(255),
+                    author VARCHAR(255),
+                    timestamp VARCHAR(100)
+                )",
+            ),
+            Step::Index {
+                table: "gup_mentions",
+                name: "gup_mentions_pair",
+                sql: "CREATE UNIQUE INDEX IF NOT EXISTS \"gup_mentions_pair\" ON \"gup_mentions\" (\"post\", \"username\")",
+            },
+            Step::Index {
+                table: "gup_mentions",
+                name: "gup_mentions_username",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_mentions_username\" ON \"gup_mentions\" (\"username\", \"timestamp\")",
+            },
+        ],
+    },
+    Migration {
+        version: 6,
+        name: "create tags",
+        steps: &[
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_tags\" (
+                    tag VARCHAR(100),
+                    post VARCHAR(500),
+                    author VARCHAR(500),
+                    timestamp VARCHAR(100)
+                )",
+            ),
+            Step::Index {
+                table: "gup_tags",
+                name: "gup_tags_pair",
+                sql: "CREATE UNIQUE INDEX IF NOT EXISTS \"gup_tags_pair\" ON \"gup_tags\" (\"tag\", \"post\")",
+            },
+            Step::Index {
+                table: "gup_tags",
+                name: "gup_tags_timestamp",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_tags_timestamp\" ON \"gup_tags\" (\"timestamp\", \"tag\")",
+            },
+        ],
+    },
+    Migration {
+        version: 7,
+        name: "create notifications",
+        steps: &[
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_notifications\" (
+                    id VARCHAR(255) PRIMARY KEY,
+                    recipient VARCHAR(255) NOT NULL,
+                    kind VARCHAR(100) NOT NULL,
+                    actor VARCHAR(255) NOT NULL,
+                    post VARCHAR(255) NOT NULL DEFAULT '',
+                    is_read VARCHAR(10) NOT NULL,
+                    timestamp BIGINT NOT NULL
+                )",
+            ),
+            Step::Index {
+                table: "gup_notifications",
+                name: "gup_notifications_recipient",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_notifications_recipient\" ON \"gup_notifications\" (\"recipient\", \"timestamp\")",
+            },
+        ],
+    },
+    Migration {
+        version: 8,
+        name: "create search tables",
+        steps: &[
+            // sqlite searches separate FTS5 tables
+            Step::Only(
+                "sqlite",
+                &Step::Sql(
+                    "CREATE VIRTUAL TABLE IF NOT EXISTS \"gup_posts_search\" USING fts5(id UNINDEXED, content)",
+                ),
+            ),
+            Step::Only(
+                "sqlite",
+                &Step::Sql(
+                    "CREATE VIRTUAL TABLE IF NOT EXISTS \"gup_user_search\" USING fts5(username, nickname, about)",
+                ),
+            ),
+            // postgres indexes a tsvector of posts directly
+            Step::Only(
+                "postgres",
+                &Step::Sql(
+                    "CREATE TABLE IF NOT EXISTS \"gup_user_search\" (
+                        username VARCHAR(255) PRIMARY KEY,
+                        nickname TEXT NOT NULL,
+                        about TEXT NOT NULL
+                    )",
+                ),
+            ),
+            Step::Only(
+                "postgres",
+                &Step::Index {
+                    table: "gup_posts",
+                    name: "gup_posts_search",
+                    sql: "CREATE INDEX IF NOT EXISTS \"gup_posts_search\" ON \"gup_posts\" USING GIN (to_tsvector('simple', \"content\"))",
+                },
+            ),
+            Step::Only(
+                "postgres",
+                &Step::Index {
+                    table: "gup_user_search",
+                    name: "gup_user_search_text",
+                    sql: "CREATE INDEX IF NOT EXISTS \"gup_user_search_text\" ON \"gup_user_search\" USING GIN (to_tsvector('simple', \"username\" || ' ' || \"nickname\" || ' ' || \"about\"))",
+                },
+            ),
+            // mysql uses FULLTEXT indexes
+            Step::Only(
+                "mysql",
+                &Step::Sql(
+                    "CREATE TABLE IF NOT EXISTS \"gup_user_search\" (
+                        username VARCHAR(255) PRIMARY KEY,
+                        nickname TEXT NOT NULL,
+                        about TEXT NOT NULL
+                    )",
+                ),
+            ),
+            Step::Only(
+                "mysql",
+                &Step::Index {
+                    table: "gup_posts",
+                    name: "gup_posts_search",
+                    sql: "CREATE FULLTEXT INDEX IF NOT EXISTS \"gup_posts_search\" ON \"gup_posts\" (\"content\")",
+                },
+            ),
+            Step::Only(
+                "mysql",
+                &Step::Index {
+                    table: "gup_user_search",
+                    name: "gup_user_search_text",
+                    sql: "CREATE FULLTEXT INDEX IF NOT EXISTS \"gup_user_search_text\" ON \"gup_user_search\" (\"username\", \"nickname\", \"about\")",
+                },
+            ),
+        ],
+    },
+    Migration {
+        version: 9,
+        name: "type gup_posts columns and index them",
+        steps: &[
+            // copy posts into a new table with typed columns. mysql commits every statement, so
+            // this has to be safe to rerun: a copy is only dropped while the old table still
+            // exists, otherwise it holds every post and only has to be renamed
+            Step::IfExists(
+                "gup_posts",
+                &[
+                    Step::Sql("DROP TABLE IF EXISTS \"gup_posts_typed\""),
+                    Step::Sql(
+                        "CREATE TABLE \"gup_posts_typed\" (
+                            id VARCHAR(255) PRIMARY KEY,
+                            author VARCHAR(500) NOT NULL,
+                            content TEXT NOT NULL,
+                            content_html TEXT NOT NULL,
+                            reply VARCHAR(255) NOT NULL DEFAULT '',
+                            timestamp BIGINT NOT NULL
+                        )",
+                    ),
+                    Step::Only(
+                        "sqlite",
+                        &Step::Sql(
+                            "INSERT INTO \"gup_posts_typed\" SELECT \"id\", \"author\", \"content\", \"content_html\", COALESCE(\"reply\", ''), CAST(\"timestamp\" AS BIGINT) FROM \"gup_posts\"",
+                        ),
+                    ),
+                    Step::Only(
+                        "postgres",
+                        &Step::Sql(
+                            "INSERT INTO \"gup_posts_typed\" SELECT \"id\", \"author\", \"content\", \"content_html\", COALESCE(\"reply\", ''), CAST(\"timestamp\" AS BIGINT) FROM \"gup_posts\"",
+                        ),
+                    ),
+                    Step::Only(
+                        "mysql",
+                        &Step::Sql(
+                            "INSERT INTO \"gup_posts_typed\" SELECT \"id\", \"author\", \"content\", \"content_html\", COALESCE(\"reply\", ''), CAST(\"timestamp\" AS SIGNED) FROM \"gup_posts\"",
+                        ),
+                    ),
+                    Step::SameRows {
+                        table: "gup_posts",
+                        copy: "gup_posts_typed",
+                    },
+                    Step::Sql("DROP TABLE \"gup_posts\""),
+                ],
+            ),
+            Step::IfExists(
+                "gup_posts_typed",
+                &[Step::Sql(
+                    "ALTER TABLE \"gup_posts_typed\" RENAME TO \"gup_posts\"",
+                )],
+            ),
+            // indexes
+            Step::Index {
+                table: "gup_posts",
+                name: "gup_posts_author",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_posts_author\" ON \"gup_posts\" (\"author\", \"reply\", \"timestamp\")",
+            },
+            Step::Index {
+                table: "gup_posts",
+                name: "gup_posts_reply",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_posts_reply\" ON \"gup_posts\" (\"reply\", \"timestamp\")",
+            },
+            // the search indexes were dropped with the old table
+            Step::Only(
+                "postgres",
+                &Step::Index {
+                    table: "gup_posts",
+                    name: "gup_posts_search",
+                    sql: "CREATE INDEX IF NOT EXISTS \"gup_posts_search\" ON \"gup_posts\" USING GIN (to_tsvector('simple', \"content\"))",
+                },
+            ),
+            Step::Only(
+                "mysql",
+                &Step::Index {
+                    table: "gup_posts",
+                    name: "gup_posts_search",
+                    sql: "CREATE FULLTEXT INDEX IF NOT EXISTS \"gup_posts_search\" ON \"gup_posts\" (\"content\")",
+                },
+            ),
+        ],
+    },
+    Migration {
+        version: 10,
+        name: "create invites",
+        steps: &[
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_invites\" (
+                    code VARCHAR(255) PRIMARY KEY,
+                    author VARCHAR(500) NOT NULL,
+                    max_uses BIGINT NOT NULL,
+                    uses BIGINT NOT NULL DEFAULT 0,
+                    expires BIGINT NOT NULL DEFAULT 0,
+                    timestamp BIGINT NOT NULL
+                )",
+            ),
+            Step::Index {
+                table: "gup_invites",
+                name: "gup_invites_author",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_invites_author\" ON \"gup_invites\" (\"author\", \"timestamp\")",
+            },
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_invite_uses\" (
+                    code VARCHAR(255) NOT NULL,
+                    username VARCHAR(500) NOT NULL,
+                    timestamp BIGINT NOT NULL
+                )",
+            ),
+            Step::Index {
+                table: "gup_invite_uses",
+                name: "gup_invite_uses_code",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_invite_uses_code\" ON \"gup_invite_uses\" (\"code\", \"timestamp\")",
+            },
+        ],
+    },
+    Migration {
+        version: 11,
+        name: "create sessions",
+        steps: &[
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_sessions\" (
+                    id VARCHAR(255) PRIMARY KEY,
+                    token VARCHAR(255) NOT NULL,
+                    username VARCHAR(500) NOT NULL,
+                    user_agent TEXT NOT NULL,
+                    created BIGINT NOT NULL,
+                    last_seen BIGINT NOT NULL
+                )",
+            ),
+            Step::Index {
+                table: "gup_sessions",
+                name: "gup_sessions_token",
+                sql: "CREATE UNIQUE INDEX IF NOT EXISTS \"gup_sessions_token\" ON \"gup_sessions\" (\"token\")",
+            },
+            Step::Index {
+                table: "gup_sessions",
+                name: "gup_sessions_username",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_sessions_username\" ON \"gup_sessions\" (\"username\", \"last_seen\")",
+            },
+        ],
+    },
+    Migration {
+        version: 12,
+        name: "create two-factor authentication",
+        steps: &[
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_totp\" (
+                    username VARCHAR(500) PRIMARY KEY,
+                    secret TEXT NOT NULL,
+                    enabled BIGINT NOT NULL DEFAULT 0,
+                    last_step BIGINT NOT NULL DEFAULT 0,
+                    timestamp BIGINT NOT NULL
+                )",
+            ),
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_recovery_codes\" (
+                    username VARCHAR(500) NOT NULL,
+                    code VARCHAR(255) NOT NULL
+                )",
+            ),
+            Step::Index {
+                table: "gup_recovery_codes",
+                name: "gup_recovery_codes_username",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_recovery_codes_username\" ON \"gup_recovery_codes\" (\"username\", \"code\")",
+            },
+        ],
+    },
+    Migration {
+        version: 13,
+        name: "create passwords",
+        steps: &[Step::Sql(
+            "CREATE TABLE IF NOT EXISTS \"gup_passwords\" (
+                username VARCHAR(500) PRIMARY KEY,
+                hash TEXT NOT NULL,
+                timestamp BIGINT NOT NULL
+            )",
+        )],
+    },
+    Migration {
+        version: 14,
+        name: "create api tokens",
+        steps: &[
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_api_tokens\" (
+                    id VARCHAR(255) PRIMARY KEY,
+                    token VARCHAR(255) NOT NULL,
+                    username VARCHAR(500) NOT NULL,
+                    name VARCHAR(255) NOT NULL,
+                    scopes TEXT NOT NULL,
+                    expires BIGINT NOT NULL DEFAULT 0,
+                    last_used BIGINT NOT NULL DEFAULT 0,
+                    created BIGINT NOT NULL
+                )",
+            ),
+            Step::Index {
+                table: "gup_api_tokens",
+                name: "gup_api_tokens_token",
+                sql: "CREATE UNIQUE INDEX IF NOT EXISTS \"gup_api_tokens_token\" ON \"gup_api_tokens\" (\"token\")",
+            },
+            Step::Index {
+                table: "gup_api_tokens",
+                name: "gup_api_tokens_username",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_api_tokens_username\" ON \"gup_api_tokens\" (\"username\")",
+            },
+        ],
+    },
+    Migration {
+        version: 15,
+        name: "create oauth",
+        steps: &[
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_oauth_clients\" (
+                    id VARCHAR(255) PRIMARY KEY,
+                    secret VARCHAR(255) NOT NULL,
+                    name VARCHAR(255) NOT NULL,
+                    owner VARCHAR(500) NOT NULL,
+                    redirect_uris TEXT NOT NULL,
+                    created BIGINT NOT NULL
+                )",
+            ),
+            Step::Index {
+                table: "gup_oauth_clients",
+                name: "gup_oauth_clients_owner",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_oauth_clients_owner\" ON \"gup_oauth_clients\" (\"owner\")",
+            },
+            Step::Sql(
+                "CREATE TABLE IF NOT EXISTS \"gup_oauth_keys\" (
+                    id VARCHAR(255) PRIMARY KEY,
+                    private_key TEXT NOT NULL,
+                    created BIGINT NOT NULL
+                )",
+            ),
+        ],
+    },
+    Migration {
+        version: 16,
+        name: "type gup_post_revisions and gup_notifications columns",
+        steps: &[
+            // tables created before these columns were typed are copied like gup_posts was (see
+            // migration 9), newer tables are just copied as they are
+            Step::IfExists(
+                "gup_post_revisions",
+                &[
+                    Step::Sql("DROP TABLE IF EXISTS \"gup_post_revisions_typed\""),
+                    Step::Sql(
+                        "CREATE TABLE \"gup_post_revisions_typed\" (
+                            id VARCHAR(255) PRIMARY KEY,
+                            post VARCHAR(255) NOT NULL,
+                            content TEXT NOT NULL,
+                            content_html TEXT NOT NULL,
+                            timestamp BIGINT NOT NULL
+                        )",
+                    ),
+                    Step::Only(
+                        "sqlite",
+                        &Step::Sql(
+                            "INSERT INTO \"gup_post_revisions_typed\" SELECT \"id\", \"post\", \"content\", \"content_html\", CAST(\"timestamp\" AS BIGINT) FROM \"gup_post_revisions\"",
+                        ),
+                    ),
+                    Step::Only(
+                        "postgres",
+                        &Step::Sql(
+                            "INSERT INTO \"gup_post_revisions_typed\" SELECT \"id\", \"post\", \"content\", \"content_html\", CAST(\"timestamp\" AS BIGINT) FROM \"gup_post_revisions\"",
+                        ),
+                    ),
+                    Step::Only(
+                        "mysql",
+                        &Step::Sql(
+                            "INSERT INTO \"gup_post_revisions_typed\" SELECT \"id\", \"post\", \"content\", \"content_html\", CAST(\"timestamp\" AS SIGNED) FROM \"gup_post_revisions\"",
+                        ),
+                    ),
+                    Step::SameRows {
+                        table: "gup_post_revisions",
+                        copy: "gup_post_revisions_typed",
+                    },
+                    Step::Sql("DROP TABLE \"gup_post_revisions\""),
+                ],
+            ),
+            Step::IfExists(
+                "gup_post_revisions_typed",
+                &[Step::Sql(
+                    "ALTER TABLE \"gup_post_revisions_typed\" RENAME TO \"gup_post_revisions\"",
+                )],
+            ),
+            Step::IfExists(
+                "gup_notifications",
+                &[
+                    Step::Sql("DROP TABLE IF EXISTS \"gup_notifications_typed\""),
+                    Step::Sql(
+                        "CREATE TABLE \"gup_notifications_typed\" (
+                            id VARCHAR(255) PRIMARY KEY,
+                            recipient VARCHAR(255) NOT NULL,
+                            kind VARCHAR(100) NOT NULL,
+                            actor VARCHAR(255) NOT NULL,
+                            post VARCHAR(255) NOT NULL DEFAULT '',
+                            is_read VARCHAR(10) NOT NULL,
+                            timestamp BIGINT NOT NULL
+                        )",
+                    ),
+                    Step::Only(
+                        "sqlite",
+                        &Step::Sql(
+                            "INSERT INTO \"gup_notifications_typed\" SELECT \"id\", \"recipient\", \"kind\", \"actor\", COALESCE(\"post\", ''), COALESCE(\"is_read\", 'false'), CAST(\"timestamp\" AS BIGINT) FROM \"gup_notifications\"",
+                        ),
+                    ),
+                    Step::Only(
+                        "postgres",
+                        &Step::Sql(
+                            "INSERT INTO \"gup_notifications_typed\" SELECT \"id\", \"recipient\", \"kind\", \"actor\", COALESCE(\"post\", ''), COALESCE(\"is_read\", 'false'), CAST(\"timestamp\" AS BIGINT) FROM \"gup_notifications\"",
+                        ),
+                    ),
+                    Step::Only(
+                        "mysql",
+                        &Step::Sql(
+                            "INSERT INTO \"gup_notifications_typed\" SELECT \"id\", \"recipient\", \"kind\", \"actor\", COALESCE(\"post\", ''), COALESCE(\"is_read\", 'false'), CAST(\"timestamp\" AS SIGNED) FROM \"gup_notifications\"",
+                        ),
+                    ),
+                    Step::SameRows {
+                        table: "gup_notifications",
+                        copy: "gup_notifications_typed",
+                    },
+                    Step::Sql("DROP TABLE \"gup_notifications\""),
+                ],
+            ),
+            Step::IfExists(
+                "gup_notifications_typed",
+                &[Step::Sql(
+                    "ALTER TABLE \"gup_notifications_typed\" RENAME TO \"gup_notifications\"",
+                )],
+            ),
+            Step::Index {
+                table: "gup_post_revisions",
+                name: "gup_post_revisions_post",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_post_revisions_post\" ON \"gup_post_revisions\" (\"post\", \"timestamp\")",
+            },
+            Step::Index {
+                table: "gup_notifications",
+                name: "gup_notifications_recipient",
+                sql: "CREATE INDEX IF NOT EXISTS \"gup_notifications_recipient\" ON \"gup_notifications\" (\"recipient\", \"timestamp\")",
+            },
+        ],
+    },
+    Migration {
+        version: 17,
+        name: "create oauth subjects",
+        steps: &[Step::Sql(
+            "CREATE TABLE IF NOT EXISTS \"gup_oauth_subjects\" (
+                username VARCHAR(500) PRIMARY KEY,
+                subject VARCHAR(255) NOT NULL,
+                created BIGINT NOT NULL
+            )",
+        )],
+    },
+];
@@ -0,0 +1,210 @@
+//! Helpers for acting as an OAuth 2.0 authorization server and OpenID Connect provider
+//!
+//! ID tokens are JWTs signed with RS256. Signing keys are created when the server starts and
+//! stored encrypted with the `secret_key` setting (see [`crate::crypto::encrypt`]).
+use data_encoding::BASE64URL_NOPAD;
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::{DecodePrivateKey, EncodePrivateKey, LineEnding};
+use rsa::signature::{SignatureEncoding, Signer};
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPrivateKey;
+use sha2::{Digest, Sha256};
+
+/// Every scope clients can ask for
+///
+/// * `openid` - get an ID token
+/// * `profile` - get the user's username and avatar
+pub const SCOPES: &[&str] = &["openid", "profile"];
+
+/// How long (in seconds) authorization codes can be exchanged for
+pub const CODE_MAX_AGE: u64 = 60;
+
+/// How long (in seconds) access tokens and ID tokens are valid for
+pub const TOKEN_MAX_AGE: u64 = 60 * 60;
+
+/// Generate a new RSA signing key. Returns the key as a PKCS#8 PEM
+pub fn generate_key() -> Option<String> {
+    let key = RsaPrivateKey::new(&mut rand::thread_rng(), 2048).ok()?;
+
+    key.to_pkcs8_pem(LineEnding::LF)
+        .ok()
+        .map(|pem| pem.to_string())
+}
+
+/// Get the public part of a signing key as a JWK (for the JWKS endpoint)
+///
+/// # Arguments:
+/// * `kid` - the id of the key
+/// * `pem` - the key, as returned by [`generate_key`]
+pub fn jwk(kid: &str, pem: &str) -> Option<serde_json::Value> {
+    let key = RsaPrivateKey::from_pkcs8_pem(pem).ok()?;
+
+    Some(serde_json::json!({
+        "kty": "RSA",
+        "use": "sig",
+        "alg": "RS256",
+        "kid": kid,
+        "n": BASE64URL_NOPAD.encode(&key.n().to_bytes_be()),
+        "e": BASE64URL_NOPAD.encode(&key.e().to_bytes_be()),
+    }))
+}
+
+/// Create a JWT signed with RS256
+///
+/// # Arguments:
+/// * `kid` - the id of the signing key
+/// * `pem` - the signing key, as returned by [`generate_key`]
+/// * `claims` - the claims of the token
+pub fn sign_jwt(kid: &str, pem: &str, claims: &serde_json::Value) -> Option<String> {
+    let key = RsaPrivateKey::from_pkcs8_pem(pem).ok()?;
+
+    let header = serde_json::json!({
+        "alg": "RS256",
+        "typ": "JWT",
+        "kid": kid,
+    });
+
+    let input = format!(
+        "{}.{}",
+        BASE64URL_NOPAD.encode(header.to_string().as_bytes()),
+        BASE64URL_NOPAD.encode(claims.to_string().as_bytes())
+    );
+
+    let signature = SigningKey::<Sha256>::new(key).sign(input.as_bytes());
+    Some(format!(
+        "{input}.{}",
+        BASE64URL_NOPAD.encode(&signature.to_bytes())
+    ))
+}
+
+/// Check a PKCE code verifier against the `S256` challenge sent when authorizing
+///
+/// # Arguments:
+/// * `verifier` - the `code_verifier` sent to the token endpoint
+/// * `challenge` - the `code_challenge` sent to the authorization endpoint
+pub fn verify_pkce(verifier: &str, challenge: &str) -> bool {
+    // RFC 7636 section 4.1
+    if !(43..=128).contains(&verifier.len()) {
+        return false;
+    }
+
+    BASE64URL_NOPAD.encode(&Sha256::digest(verifier.as_bytes())) == challenge
+}
+
+/// Check if a redirect URI can be registered for a client
+///
+/// Redirect URIs must be absolute, use `https` (or `http` on localhost), and can't have a
+/// fragment.
+pub fn valid_redirect_uri(uri: &str) -> bool {
+    let url = match url::Url::parse(uri) {
+        Ok(u) => u,
+        Err(_) => return false,
+    };
+
+    if url.fragment().is_some() {
+        return false;
+    }
+
+    match url.scheme() {
+        "https" => url.host().is_some(),
+        "http" => matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")),
+        _ => false,
+    }
+}
+
+/// Check if a redirect URI is one of a client's registered URIs, which must match exactly
+///
+/// # Arguments:
+/// * `registered` - the client's redirect URIs
+/// * `uri` - the `redirect_uri` sent to the authorization endpoint
+pub fn redirect_uri_registered(registered: &[String], uri: &str) -> bool {
+    registered.iter().any(|r| r == uri)
+}
+
+/// Add query parameters to a redirect URI
+///
+/// # Arguments:
+/// * `uri` - the redirect URI, already checked with [`valid_redirect_uri`]
+/// * `params` - the parameters to add, empty values are skipped
+pub fn redirect_with(uri: &str, params: &[(&str, &str)]) -> String {
+    let mut url = match url::Url::parse(uri) {
+        Ok(u) => u,
+        Err(_) => return uri.to_string(),
+    };
+
+    {
+        let mut query = url.query_pairs_mut();
+
+        for (name, value) in params {
+            if !value.is_empty() {
+                query.append_pair(name, value);
+            }
+        }
+    }
+
+    url.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkce_rfc7636_vector() {
+        // RFC 7636 Appendix B
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let challenge = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+        assert!(verify_pkce(verifier, challenge));
+        assert!(!verify_pkce(
+            verifier,
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cN"
+        ));
+        assert!(!verify_pkce(&verifier[1..], challenge));
+    }
+
+    #[test]
+    fn pkce_verifier_length() {
+        let challenge = |v: &str| BASE64URL_NOPAD.encode(&Sha256::digest(v.as_bytes()));
+
+        for (len, valid) in [(42, false), (43, true), (128, true), (129, false)] {
+            let verifier = "a".repeat(len);
+            assert_eq!(
+                verify_pkce(&verifier, &challenge(&verifier)),
+                valid,
+                "{len}"
+            );
+        }
+    }
+
+    #[test]
+    fn redirect_uri_exact_match() {
+        let registered = vec![
+            String::from("https://app.example.com/callback"),
+            String::from("http://localhost:3000/cb"),
+        ];
+
+        assert!(redirect_uri_registered(
+            &registered,
+            "https://app.example.com/callback"
+        ));
+        assert!(redirect_uri_registered(
+            &registered,
+            "http://localhost:3000/cb"
+        ));
+
+        for uri in [
+            "https://app.example.com/callback/",
+            "https://app.example.com/callback?x=1",
+            "https://app.example.com/Callback",
+            "https://app.example.com/call",
+            "https://APP.example.com/callback",
+            "https://app.example.com.evil.com/callback",
+            "http://app.example.com/callback",
+            "http://localhost:3001/cb",
+            "",
+        ] {
+            assert!(!redirect_uri_registered(&registered, uri), "{uri}");
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+
+use crate::db::{self, AppData};
+
+use super::base;
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "activity/public.html")]
+struct PublicActivityTemplate {
+    activity: Vec<(db::ActivityPost, Vec<db::ActivityPost>, i32)>,
+    offset: i32,
+    // required fields (super::base)
+    info: String,
+    auth_state: bool,
+    bundlrs: String,
+    site_name: String,
+    body_embed: String,
+}
+
+#[derive(Template)]
+#[template(path = "activity/tag.html")]
+struct TagTemplate {
+    tag: String,
+    activity: Vec<(db::ActivityPost, Vec<db::ActivityPost>, i32)>,
+    trending: Vec<db::TagCount>,
+    offset: i32,
+    // required fields (super::base)
+    info: String,
+    auth_state: bool,
+    bundlrs: String,
+    site_name: String,
+    body_embed: String,
+}
+
+#[derive(Template)]
+#[template(path = "activity/search.html")]
+struct SearchTemplate {
+    query: String,
+    results: db::SearchResults,
+    offset: i32,
+    // required fields (super::base)
+    info: String,
+    auth_state: bool,
+    bundlrs: String,
+    site_name: String,
+    body_embed: String,
+}
+
+#[derive(Template)]
+#[template(path = "activity/notifications.html")]
+struct NotificationsTemplate {
+    notifications: Vec<db::Notification>,
+    offset: i32,
+    // required fields (super::base)
+    info: String,
+    auth_state: bool,
+    bundlrs: String,
+    site_name: String,
+    body_embed: String,
+}
+
+#[derive(Default, PartialEq, serde::Deserialize)]
+pub struct OffsetQueryProps {
+    pub offset: Option<i32>,
+}
+
+#[derive(Default, PartialEq, serde::Deserialize)]
+pub struct SearchQueryProps {
+    #[serde(default)]
+    pub q: String,
+    pub offset: Option<i32>,
+}
+
+#[get("/activity")]
+/// Available at "/activity"
+pub async fn public_activity_request(
+    req: HttpRequest,
+    data: web::Data<AppData>,
+    info: web::Query<OffsetQueryProps>,
+) -> impl Responder {
+    // verify auth status
+    let (set_cookie, _, token_user) =
+        base::check_auth_status(req.clone(), data.clone(), None).await;
+
+    // activity
+    let posts_res: Vec<(db::ActivityPost, Vec<db::ActivityPost>, i32)> = data
+        .db
+        .get_public_activity(info.offset)
+        .await
+        .payload
+        .unwrap_or_default();
+
+    // ...
+    let base = base::get_base_values(&data.config, token_user.is_some());
+    let props = PublicActivityTemplate {
+        activity: posts_res,
+        offset: info.offset.unwrap_or(0),
+        auth_state: base.auth_state,
+        info: base.info,
+        bundlrs: base.bundlrs,
+        site_name: base.site_name,
+        body_embed: base.body_embed,
+    };
+
+    HttpResponse::Ok()
+        .append_header(("Set-Cookie", set_cookie))
+        .append_header(("Content-Type", "text/html"))
+        .body(props.render().unwrap())
+}
+
+#[get("/tag/{name:.*}")]
+/// Available at "/tag/{name}"
+pub async fn tag_request(
+    req: HttpRequest,
+    data: web::Data<AppData>,
+    info: web::Query<OffsetQueryProps>,
+) -> impl Responder {
+    let tag: String = req.match_info().get("name").unwrap().to_lowercase();
+
+    // verify auth status
+    let (set_cookie, _, token_user) =
+        base::check_auth_status(req.clone(), data.clone(), None).await;
+
+    // activity
+    let posts_res: Vec<(db::ActivityPost, Vec<db::ActivityPost>, i32)> = data
+        .db
+        .get_tagged_posts(tag.clone(), info.offset)
+        .await
+        .payload
+        .unwrap_or_default();
+
+    let trending: Vec<db::TagCount> = data.db.get_trending_tags(24).await.payload;
+
+    // ...
+    let base = base::get_base_values(&data.config, token_user.is_some());
+    let props = TagTemplate {
+        tag,
+        activity: posts_res,
+        trending,
+        offset: info.offset.unwrap_or(0),
+        auth_state: base.auth_state,
+        info: base.info,
+        bundlrs: base.bundlrs,
+        site_name: base.site_name,
+        body_embed: base.body_embed,
+    };
+
+    HttpResponse::Ok()
+        .append_header(("Set-Cookie", set_cookie))
+        .append_header(("Content-Type", "text/html"))
+        .body(props.render().unwrap())
+}
+
+#[get("/search")]
+/// Available at "/search"
+pub async fn search_request(
+    req: HttpRequest,
+    data: web::Data<AppData>,
+    info: web::Query<SearchQueryProps>,
+) -> impl Responder {
+    // verify auth status
+    let (set_cookie, _, token_user) =
+        base::check_auth_status(req.clone(), data.clone(), None).await;
+
+    // search
+    let results: db::SearchResults = if info.q.trim().is_empty() {
+        db::SearchResults::default()
+    } else {
+        data.db
+            .search(info.q.clone(), info.offset)
+            .await
+            .payload
+            .unwrap_or_default()
+    };
+
+    // ...
+    let base = base::get_base_values(&data.config, token_user.is_some());
+    let props = SearchTemplate {
+        query: info.q.clone(),
+        results,
+        offset: info.offset.unwrap_or(0),
+        auth_state: base.auth_state,
+        info: base.info,
+        bundlrs: base.bundlrs,
+        site_name: base.site_name,
+        body_embed: base.body_embed,
+    };
+
+    HttpResponse::Ok()
+        .append_header(("Set-Cookie", set_cookie))
+        .append_header(("Content-Type", "text/html"))
+        .body(props.render().unwrap())
+}
+
+#[get("/notifications")]
+/// Available at "/notifications"
+pub async fn notifications_request(
+    req: HttpRequest,
+    data: web::Data<AppData>,
+    info: web::Query<OffsetQueryProps>,
+) -> impl Responder {
+    // verify auth status
+    let (set_cookie, _, token_user) =
+        base::check_auth_status(req.clone(), data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable().body("An account is required to do this");
+    }
+
+    let user = token_user.unwrap().ok().unwrap();
+
+    // notifications
+    let notifications: Vec<db::Notification> = data
+        .db
+        .get_user_notifications(user.user.username, info.offset)
+        .await
+        .payload
+        .unwrap_or_default();
+
+    // ...
+    let base = base::get_base_values(&data.config, true);
+    let props = NotificationsTemplate {
+        notifications,
+        offset: info.offset.unwrap_or(0),
+        auth_state: base.auth_state,
+        info: base.info,
+        bundlrs: base.bundlrs,
+        site_name: base.site_name,
+        body_embed: base.body_embed,
+    };
+
+    HttpResponse::Ok()
+        .append_header(("Set-Cookie", set_cookie))
+        .append_header(("Content-Type", "text/html"))
+        .body(props.render().unwrap())
+}
@@ -1,6 +1,6 @@
 use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
 
-use crate::db::{self, AppData, Log, UserMetadata, UserState};
+use crate::db::{self, AppData, Follow, UserMetadata, UserState};
 
 use super::base;
 use askama::Template;
@@ -47,6 +47,18 @@ struct LoginSecondaryTokenTemplate {
     body_embed: String,
 }
 
+#[derive(Template)]
+#[template(path = "auth/login_password.html")]
+struct LoginPasswordTemplate {
+    callback: String,
+    // required fields (super::base)
+    info: String,
+    auth_state: bool,
+    bundlrs: String,
+    site_name: String,
+    body_embed: String,
+}
+
 #[derive(Template)]
 #[template(path = "auth/user_profile.html")]
 struct UserProfileTemplate {
@@ -86,6 +98,7 @@ struct ViewPostTemplate {
     post: db::ActivityPost,
     replies: Vec<(db::ActivityPost, Vec<db::ActivityPost>, i32)>,
     favorites_count: i32,
+    revisions: Vec<db::PostRevision>,
     // required fields (super::base)
     info: String,
     auth_state: bool,
@@ -98,7 +111,7 @@ struct ViewPostTemplate {
 #[derive(Template)]
 #[template(path = "auth/followers.html")]
 struct FollowersTemplate {
-    followers: Vec<Log>,
+    followers: Vec<Follow>,
     user: UserState<UserMetadata>,
     offset: i32,
     // required fields (super::base)
@@ -112,7 +125,7 @@ struct FollowersTemplate {
 #[derive(Template)]
 #[template(path = "auth/following.html")]
 struct FollowingTemplate {
-    following: Vec<Log>,
+    following: Vec<Follow>,
     user: UserState<UserMetadata>,
     offset: i32,
     // required fields (super::base)
@@ -133,6 +146,34 @@ pub struct FollowersQueryProps {
 struct SettingsTemplate {
     profile: UserState<UserMetadata>,
     metadata: String,
+    /// The sessions of the user, only shown to the user themselves
+    sessions: Vec<db::Session>,
+    /// If the settings are for the user viewing them
+    is_self: bool,
+    /// If the user has two-factor authentication enabled
+    totp_enabled: bool,
+    /// If the user can sign in with a password
+    has_password: bool,
+    /// The API tokens of the user, only shown to the user themselves
+    api_tokens: Vec<db::ApiToken>,
+    /// Every scope API tokens can be given
+    api_token_scopes: &'static [&'static str],
+    // required fields (super::base)
+    info: String,
+    auth_state: bool,
+    bundlrs: String,
+    site_name: String,
+    body_embed: String,
+}
+
+#[derive(Template)]
+#[template(path = "auth/invites.html")]
+struct InvitesTemplate {
+    invites: Vec<(db::Invite, Vec<db::InviteUse>)>,
+    is_staff: bool,
+    /// How many more invites the user can create (ignored for staff)
+    invites_left: usize,
+    offset: i32,
     // required fields (super::base)
     info: String,
     auth_state: bool,
@@ -146,18 +187,17 @@ struct SettingsTemplate {
 /// Still renders even if `REGISTRATION_DISABLED` is present
 pub async fn register_request(
     req: HttpRequest,
+    data: web::Data<AppData>,
     info: web::Query<CallbackQueryProps>,
 ) -> impl Responder {
-    let invite_codes = crate::config::get_var("INVITE_CODES");
-
     // ...
-    let base = base::get_base_values(req.cookie("__Secure-Token").is_some());
+    let base = base::get_base_values(&data.config, req.cookie("__Secure-Token").is_some());
     return HttpResponse::Ok()
         .append_header(("Content-Type", "text/html"))
         .body(
             RegisterTemplate {
                 callback: info.callback.clone(),
-                invite_code_required: invite_codes.is_some(),
+                invite_code_required: data.config.requires_invite(),
                 // required fields
                 info: base.info,
                 auth_state: base.auth_state,
@@ -174,10 +214,11 @@ pub async fn register_request(
 /// Available at "/flow/auth/login"
 pub async fn login_request(
     req: HttpRequest,
+    data: web::Data<AppData>,
     info: web::Query<CallbackQueryProps>,
 ) -> impl Responder {
     // ...
-    let base = base::get_base_values(req.cookie("__Secure-Token").is_some());
+    let base = base::get_base_values(&data.config, req.cookie("__Secure-Token").is_some());
     return HttpResponse::Ok()
         .append_header(("Content-Type", "text/html"))
         .body(
@@ -199,10 +240,11 @@ pub async fn login_request(
 /// Available at "/flow/auth/login-st"
 pub async fn login_secondary_token_request(
     req: HttpRequest,
+    data: web::Data<AppData>,
     info: web::Query<CallbackQueryProps>,
 ) -> impl Responder {
     // ...
-    let base = base::get_base_values(req.cookie("__Secure-Token").is_some());
+    let base = base::get_base_values(&data.config, req.cookie("__Secure-Token").is_some());
     return HttpResponse::Ok()
         .append_header(("Content-Type", "text/html"))
         .body(
@@ -220,6 +262,32 @@ pub async fn login_secondary_token_request(
         );
 }
 
+#[get("/flow/auth/login-password")]
+/// Available at "/flow/auth/login-password"
+pub async fn login_password_request(
+    req: HttpRequest,
+    data: web::Data<AppData>,
+    info: web::Query<CallbackQueryProps>,
+) -> impl Responder {
+    // ...
+    let base = base::get_base_values(&data.config, req.cookie("__Secure-Token").is_some());
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "text/html"))
+        .body(
+            LoginPasswordTemplate {
+                callback: info.callback.clone(),
+                // required fields
+                info: base.info,
+                auth_state: base.auth_state,
+                bundlrs: base.bundlrs,
+                site_name: base.site_name,
+                body_embed: base.body_embed,
+            }
+            .render()
+            .unwrap(),
+        )
+}
+
 #[get("/{username:.*}")]
 /// Available at "/{username}"
 pub async fn profile_view_request(
@@ -242,10 +310,11 @@ pub async fn profile_view_request(
     let unwrap = user.ok().unwrap();
 
     // verify auth status
-    let (set_cookie, _, token_user) = base::check_auth_status(req.clone(), data.clone()).await;
+    let (set_cookie, _, token_user) =
+        base::check_auth_status(req.clone(), data.clone(), None).await;
 
     // ...
-    let base = base::get_base_values(req.cookie("__Secure-Token").is_some());
+    let base = base::get_base_values(&data.config, req.cookie("__Secure-Token").is_some());
 
     // ...
     let followers_res: db::DefaultReturn<usize> =
@@ -254,7 +323,7 @@ pub async fn profile_view_request(
     let following_res: db::DefaultReturn<usize> =
         data.db.get_user_following_count(username_c.clone()).await;
 
-    let is_following_res: Option<db::DefaultReturn<Option<db::Log>>> =
+    let is_following_res: Option<db::DefaultReturn<Option<db::Follow>>> =
         if token_user.is_some() && token_user.as_ref().unwrap().is_ok() {
             Option::Some(
                 data.db
@@ -363,10 +432,11 @@ pub async fn view_post_request(req: HttpRequest, data: web::Data<AppData>) -> im
     let unwrap = user.ok().unwrap();
 
     // verify auth status
-    let (set_cookie, _, token_user) = base::check_auth_status(req.clone(), data.clone()).await;
+    let (set_cookie, _, token_user) =
+        base::check_auth_status(req.clone(), data.clone(), None).await;
 
     // ...
-    let base = base::get_base_values(req.cookie("__Secure-Token").is_some());
+    let base = base::get_base_values(&data.config, req.cookie("__Secure-Token").is_some());
     let user = unwrap.clone().user;
 
     let active_user = if token_user.is_some() && token_user.as_ref().unwrap().is_ok() {
@@ -408,7 +478,13 @@ pub async fn view_post_request(req: HttpRequest, data: web::Data<AppData>) -> im
         // post
         post: post.payload.unwrap(),
         replies: posts_res,
-        favorites_count: data.db.get_post_favorites(post_id).await.payload,
+        favorites_count: data.db.get_post_favorites(post_id.clone()).await.payload,
+        revisions: data
+            .db
+            .get_post_revisions(post_id)
+            .await
+            .payload
+            .unwrap_or_default(),
         // TODO: is_favorited
     };
 
@@ -440,15 +516,15 @@ pub async fn followers_request(
     let unwrap = user.ok().unwrap();
 
     // verify auth status
-    let (set_cookie, _, _) = base::check_auth_status(req.clone(), data.clone()).await;
+    let (set_cookie, _, _) = base::check_auth_status(req.clone(), data.clone(), None).await;
 
     // ...
-    let followers_res: db::DefaultReturn<Option<Vec<db::Log>>> = data
+    let followers_res: db::DefaultReturn<Option<Vec<db::Follow>>> = data
         .db
         .get_user_followers(username_c.clone(), info.offset)
         .await;
 
-    let base = base::get_base_values(req.cookie("__Secure-Token").is_some());
+    let base = base::get_base_values(&data.config, req.cookie("__Secure-Token").is_some());
     let props = FollowersTemplate {
         user: unwrap.clone().user,
         followers: followers_res.payload.unwrap(),
@@ -492,15 +568,15 @@ pub async fn following_request(
     let unwrap = user.ok().unwrap();
 
     // verify auth status
-    let (set_cookie, _, _) = base::check_auth_status(req.clone(), data.clone()).await;
+    let (set_cookie, _, _) = base::check_auth_status(req.clone(), data.clone(), None).await;
 
     // ...
-    let following_res: db::DefaultReturn<Option<Vec<db::Log>>> = data
+    let following_res: db::DefaultReturn<Option<Vec<db::Follow>>> = data
         .db
         .get_user_following(username_c.clone(), info.offset)
         .await;
 
-    let base = base::get_base_values(req.cookie("__Secure-Token").is_some());
+    let base = base::get_base_values(&data.config, req.cookie("__Secure-Token").is_some());
     let props = FollowingTemplate {
         user: unwrap.clone().user,
         following: following_res.payload.unwrap(),
@@ -539,7 +615,7 @@ pub async fn user_settings_request(
 
     // verify auth status
     let (set_cookie, token_cookie, token_user) =
-        base::check_auth_status(req.clone(), data.clone()).await;
+        base::check_auth_status(req.clone(), data.clone(), None).await;
 
     if token_user.is_none() {
         return HttpResponse::NotAcceptable().body("An account is required to do this");
@@ -559,10 +635,55 @@ pub async fn user_settings_request(
             .body("You do not have permission to manage this user's contents.");
     }
 
+    // sessions
+    let is_self = user.user.username == profile.user.username;
+    let sessions: Vec<db::Session> = if is_self {
+        let current = data
+            .db
+            .get_session(token_cookie.as_ref().unwrap().value().to_string())
+            .await
+            .payload
+            .map(|s| s.id);
+
+        let mut sessions = data
+            .db
+            .get_user_sessions(user.user.username)
+            .await
+            .payload
+            .unwrap_or_default();
+
+        for session in &mut sessions {
+            session.current = current.as_ref() == Some(&session.id);
+        }
+
+        sessions
+    } else {
+        Vec::new()
+    };
+
+    let totp_enabled = data.db.is_totp_enabled(profile.user.username.clone()).await;
+    let has_password = data.db.has_password(profile.user.username.clone()).await;
+
+    let api_tokens = if is_self {
+        data.db
+            .get_user_api_tokens(profile.user.username.clone())
+            .await
+            .payload
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+
     // ...
-    let base = base::get_base_values(token_cookie.is_some());
+    let base = base::get_base_values(&data.config, token_cookie.is_some());
     let props = SettingsTemplate {
         profile: profile.clone().user,
+        sessions,
+        is_self,
+        totp_enabled,
+        has_password,
+        api_tokens,
+        api_token_scopes: db::API_TOKEN_SCOPES,
         metadata: serde_json::to_string(&profile.user.metadata)
             .unwrap()
             .replace("/", "\\/"),
@@ -578,3 +699,85 @@ pub async fn user_settings_request(
         .append_header(("Content-Type", "text/html"))
         .body(props.render().unwrap());
 }
+
+#[get("/invites")]
+/// Available at "/invites"
+pub async fn invites_request(
+    req: HttpRequest,
+    data: web::Data<db::AppData>,
+    info: web::Query<FollowersQueryProps>,
+) -> impl Responder {
+    // verify auth status
+    let (set_cookie, _, token_user) =
+        base::check_auth_status(req.clone(), data.clone(), None).await;
+
+    if token_user.is_none() {
+        return HttpResponse::NotAcceptable().body("An account is required to do this");
+    }
+
+    let user = token_user.unwrap().ok().unwrap();
+    let is_staff = user
+        .level
+        .permissions
+        .contains(&String::from("ManageInvites"));
+
+    // invites
+    let invites: Vec<db::Invite> = data
+        .db
+        .get_invites(
+            if is_staff {
+                Option::None
+            } else {
+                Option::Some(user.user.username.clone())
+            },
+            info.offset,
+        )
+        .await
+        .payload
+        .unwrap_or_default();
+
+    let mut invites_with_uses: Vec<(db::Invite, Vec<db::InviteUse>)> = Vec::new();
+
+    for invite in invites {
+        let uses = data
+            .db
+            .get_invite_uses(invite.code.clone())
+            .await
+            .payload
+            .unwrap_or_default();
+
+        invites_with_uses.push((invite, uses));
+    }
+
+    // users can only create invites if their role level allows it
+    let invites_left = if user.level.elevation >= data.config.user_invite_elevation {
+        let count = data
+            .db
+            .get_user_invite_count(user.user.username)
+            .await
+            .payload;
+
+        data.config.user_invites.saturating_sub(count)
+    } else {
+        0
+    };
+
+    // ...
+    let base = base::get_base_values(&data.config, true);
+    let props = InvitesTemplate {
+        invites: invites_with_uses,
+        is_staff,
+        invites_left,
+        offset: info.offset.unwrap_or(0),
+        auth_state: base.auth_state,
+        info: base.info,
+        bundlrs: base.bundlrs,
+        site_name: base.site_name,
+        body_embed: base.body_embed,
+    };
+
+    HttpResponse::Ok()
+        .append_header(("Set-Cookie", set_cookie))
+        .append_header(("Content-Type", "text/html"))
+        .body(props.render().unwrap())
+}
@@ -1,5 +1,6 @@
 use actix_web::{web::Data, HttpRequest};
 
+use crate::config::Config;
 use crate::db::AppData;
 use dorsal::db::special::auth_db::{FullUser, UserMetadata, Result};
 
@@ -12,65 +13,124 @@ pub struct BaseTemplate {
     pub body_embed: String,
 }
 
-pub fn get_base_values(token_cookie: bool) -> BaseTemplate {
-    let info_req = std::env::var("INFO");
-    let mut info: String = String::new();
-
-    if info_req.is_err() && info.is_empty() {
-        info = "/pub/info".to_string();
-    } else {
-        info = info_req.unwrap();
-    }
-
-    let body_embed_req = std::env::var("BODY_EMBED");
-    let body_embed = if body_embed_req.is_ok() {
-        body_embed_req.unwrap()
-    } else {
-        String::new()
-    };
-
-    // return
+pub fn get_base_values(config: &Config, token_cookie: bool) -> BaseTemplate {
     BaseTemplate {
-        info,
+        info: config.info.clone(),
         auth_state: token_cookie,
-        bundlrs: std::env::var("BUNDLRS_ROOT").unwrap_or(String::new()),
-        deducktive: std::env::var("DEDUCKTIVE_ROOT").unwrap_or(String::new()),
-        site_name: std::env::var("SITE_NAME").unwrap_or("Guppy".to_string()),
-        body_embed,
+        bundlrs: config.bundlrs_root.clone(),
+        deducktive: config.deducktive_root.clone(),
+        site_name: config.site_name.clone(),
+        body_embed: config.body_embed.clone(),
     }
 }
 
+/// Build the `Set-Cookie` header value that stores a session token
+///
+/// # Arguments:
+/// * `token` - the unhashed session token
+pub fn session_cookie(token: &str) -> String {
+    format!(
+        "__Secure-Token={}; SameSite=Lax; Secure; Path=/; HostOnly=true; HttpOnly=true; Max-Age={}",
+        token,
+        60 * 60 * 24 * 365
+    )
+}
+
+/// Get the `User-Agent` of a request (empty if it wasn't sent)
+pub fn user_agent(req: &HttpRequest) -> String {
+    req.headers()
+        .get("User-Agent")
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Get the token from the `Authorization: Bearer` header of a request
+pub fn bearer_token(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(|t| t.trim().to_string())
+}
+
+/// If the session cookie of a request can hold an account ID instead of a session token, like
+/// it did before sessions were added. Only pages accept these (so the cookie can be replaced
+/// with a session), and only while [`Config::legacy_id_cookies`] is set
+pub fn accepts_legacy_cookie(req: &HttpRequest, config: &Config) -> bool {
+    config.legacy_id_cookies && !req.path().starts_with("/api/")
+}
+
+/// Get the user making a request
+///
+/// Requests are authenticated with the session cookie, or with an API token sent as
+/// `Authorization: Bearer`. Tokens are only accepted if they have `scope`, and never if
+/// `scope` is `None` (pages, and anything tokens shouldn't be able to do).
+///
+/// # Arguments:
+/// * `req` - the request
+/// * `data` - the app data
+/// * `scope` - the scope an API token needs, see [`crate::db::API_TOKEN_SCOPES`]
 pub async fn check_auth_status(
     req: HttpRequest,
     data: Data<AppData>,
+    scope: Option<&str>,
 ) -> (
     String,
     Option<actix_web::cookie::Cookie<'static>>,
     Option<Result<FullUser<UserMetadata>>>,
 ) {
+    // api tokens
+    if let Some(token) = bearer_token(&req) {
+        let token_user = match scope {
+            Some(scope) => data.db.get_user_by_api_token(token, scope).await.ok(),
+            None => Option::None,
+        };
+
+        return (String::new(), Option::None, token_user.map(Ok));
+    }
+
     // verify auth status
     let token_cookie = req.cookie("__Secure-Token");
-    let mut set_cookie: &str = "";
-
-    let mut token_user: Option<Result<FullUser<UserMetadata>>> = if token_cookie.is_some() {
-        Option::Some(
-            data.db
-                .auth
-                .get_user_by_unhashed(token_cookie.as_ref().unwrap().value().to_string()) // if the user is returned, that means the ID is valid
-                .await,
-        )
-    } else {
-        Option::None
+    let mut set_cookie: String = String::new();
+
+    let mut token_user: Option<Result<FullUser<UserMetadata>>> = match token_cookie {
+        Some(ref c) => Option::Some(data.db.get_user_by_session(c.value().to_string()).await),
+        None => Option::None,
     };
 
-    if token_user.is_some() {
-        // make sure user exists, refresh token if not
-        if token_user.as_ref().unwrap().is_ok() == false {
-            set_cookie = "__Secure-Token=refresh; SameSite=Strict; Secure; Path=/; HostOnly=true; HttpOnly=true; Max-Age=0";
-            token_user = Option::None;
+    if token_user.as_ref().is_some_and(|u| u.is_err()) {
+        token_user = Option::None;
+
+        // older versions stored the user's ID in the cookie, give those a session
+        let legacy_user = if accepts_legacy_cookie(&req, &data.config) {
+            let token = token_cookie.as_ref().unwrap().value().to_string();
+            data.db.get_user_by_unhashed(token).await.ok()
+        } else {
+            Option::None
+        };
+
+        // users with two-factor authentication have to sign in again to enter a code
+        if let Some(u) = legacy_user {
+            if !data.db.is_totp_enabled(u.user.username.clone()).await {
+                let session = data
+                    .db
+                    .create_session(u.user.username.clone(), user_agent(&req))
+                    .await;
+
+                if let Some(token) = session.payload {
+                    set_cookie = session_cookie(&token);
+                    token_user = Option::Some(Ok(u));
+                }
+            }
+        }
+
+        // the cookie isn't a session (or couldn't be replaced with one), remove it
+        if token_user.is_none() {
+            set_cookie = String::from("__Secure-Token=refresh; SameSite=Strict; Secure; Path=/; HostOnly=true; HttpOnly=true; Max-Age=0");
         }
     }
 
     // return
-    (set_cookie.to_string(), token_cookie, token_user)
+    (set_cookie, token_cookie, token_user)
 }
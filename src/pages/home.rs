@@ -8,6 +8,11 @@ use askama::Template;
 #[derive(Template)]
 #[template(path = "homepage.html")]
 struct HomeTemplate {
+    // timeline stuff
+    activity: Vec<(db::ActivityPost, Vec<db::ActivityPost>, i32)>,
+    is_first_page: bool,
+    next_before: u128,
+    next_before_id: String,
     // required fields (super::base)
     info: String,
     auth_state: bool,
@@ -16,37 +21,53 @@ struct HomeTemplate {
     body_embed: String,
 }
 
+#[derive(Default, PartialEq, serde::Deserialize)]
+pub struct HomeQueryProps {
+    pub before: Option<u64>,
+    pub before_id: Option<String>,
+}
+
 #[get("/")]
-pub async fn home_request(req: HttpRequest, data: web::Data<db::AppData>) -> impl Responder {
+pub async fn home_request(
+    req: HttpRequest,
+    data: web::Data<db::AppData>,
+    info: web::Query<HomeQueryProps>,
+) -> impl Responder {
     // verify auth status
-    let token_cookie = req.cookie("__Secure-Token");
-    let mut set_cookie: &str = "";
-
-    let mut token_user = if token_cookie.is_some() {
-        Option::Some(
-            data.db
-                .get_user_by_unhashed(token_cookie.as_ref().unwrap().value().to_string()) // if the user is returned, that means the ID is valid
-                .await,
-        )
+    let (set_cookie, _, token_user) =
+        base::check_auth_status(req.clone(), data.clone(), None).await;
+
+    // get timeline
+    let activity = if let Some(Ok(ref token_user)) = token_user {
+        data.db
+            .get_user_timeline(
+                token_user.user.username.clone(),
+                info.before,
+                info.before_id.clone(),
+            )
+            .await
+            .payload
+            .unwrap_or_default()
     } else {
-        Option::None
+        Vec::new()
     };
 
-    if token_user.is_some() {
-        // make sure user exists, refresh token if not
-        if token_user.as_ref().unwrap().is_ok() == false {
-            set_cookie = "__Secure-Token=refresh; SameSite=Strict; Secure; Path=/; HostOnly=true; HttpOnly=true; Max-Age=0";
-            token_user = Option::None;
-        }
-    }
+    let (next_before, next_before_id) = match activity.last() {
+        Some(p) => (p.0.timestamp, p.0.id.clone()),
+        None => (0, String::new()),
+    };
 
     // ...
-    let base = base::get_base_values(token_user.is_some());
+    let base = base::get_base_values(&data.config, token_user.is_some());
     return HttpResponse::Ok()
         .append_header(("Set-Cookie", set_cookie))
         .append_header(("Content-Type", "text/html"))
         .body(
             HomeTemplate {
+                activity,
+                is_first_page: info.before.is_none(),
+                next_before,
+                next_before_id,
                 // required fields
                 info: base.info,
                 auth_state: base.auth_state,
@@ -0,0 +1,112 @@
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+
+use crate::api::oauth::{check_authorize, AuthorizeProps};
+use crate::db::{self, AppData};
+
+use super::base;
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "oauth/authorize.html")]
+struct AuthorizeTemplate {
+    client: db::OAuthClient,
+    /// The username of the user authorizing the client
+    username: String,
+    /// The scopes the client asked for, and what they let the client do
+    scopes: Vec<(String, &'static str)>,
+    /// The host users are sent back to
+    redirect_host: String,
+    // the request, sent back when the user answers
+    response_type: String,
+    redirect_uri: String,
+    scope: String,
+    state: String,
+    nonce: String,
+    code_challenge: String,
+    code_challenge_method: String,
+    /// See [`crate::csrf`]
+    csrf_token: String,
+    // required fields (super::base)
+    info: String,
+    auth_state: bool,
+    bundlrs: String,
+    site_name: String,
+    body_embed: String,
+}
+
+#[get("/oauth/authorize")]
+/// Available at "/oauth/authorize"
+pub async fn authorize_request(
+    req: HttpRequest,
+    data: web::Data<AppData>,
+    info: web::Query<AuthorizeProps>,
+) -> impl Responder {
+    // check request
+    let client = match check_authorize(&data, &info).await {
+        Ok(c) => c,
+        Err(e) => return e.response(&info),
+    };
+
+    // verify auth status
+    let (set_cookie, token_cookie, token_user) =
+        base::check_auth_status(req.clone(), data.clone(), None).await;
+
+    if token_user.is_none() {
+        // sign in, then come back here
+        let callback: String =
+            url::form_urlencoded::byte_serialize(req.uri().to_string().as_bytes()).collect();
+
+        return HttpResponse::Found()
+            .append_header(("Location", format!("/flow/auth/login?callback={callback}")))
+            .finish();
+    }
+
+    let user = token_user.unwrap().ok().unwrap();
+
+    // ...
+    let scopes = info
+        .scope
+        .split_whitespace()
+        .map(|s| {
+            (
+                s.to_string(),
+                match s {
+                    "openid" => "Know who you are",
+                    "profile" => "See your username and avatar",
+                    _ => "",
+                },
+            )
+        })
+        .collect();
+
+    let redirect_host = url::Url::parse(&info.redirect_uri)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+        .unwrap_or_default();
+
+    let base = base::get_base_values(&data.config, token_cookie.is_some());
+    let props = AuthorizeTemplate {
+        client,
+        username: user.user.username,
+        scopes,
+        redirect_host,
+        response_type: info.response_type.clone(),
+        redirect_uri: info.redirect_uri.clone(),
+        scope: info.scope.clone(),
+        state: info.state.clone(),
+        nonce: info.nonce.clone(),
+        code_challenge: info.code_challenge.clone(),
+        code_challenge_method: info.code_challenge_method.clone(),
+        csrf_token: crate::csrf::csrf_token(&req),
+        info: base.info,
+        auth_state: base.auth_state,
+        bundlrs: base.bundlrs,
+        site_name: base.site_name,
+        body_embed: base.body_embed,
+    };
+
+    HttpResponse::Ok()
+        .append_header(("Set-Cookie", set_cookie))
+        .append_header(("Content-Type", "text/html"))
+        .body(props.render().unwrap())
+}
@@ -0,0 +1,318 @@
+//! Rate limiting middleware, counters and buckets are kept in the cache so they're shared by every worker
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue, RETRY_AFTER};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+
+use crate::db::{AppData, DefaultReturn, FullUser, UserMetadata};
+
+/// The endpoints [`login_limit`] applies to
+pub const LOGIN_PATHS: &[&str] = &[
+    "/api/v1/auth/login",
+    "/api/v1/auth/login-st",
+    "/api/v1/auth/login/totp",
+];
+
+/// Get the IP address of the client that sent a request
+///
+/// # Arguments:
+/// * `req` - the request
+/// * `trust_proxy` - if the address can be read from proxy headers, see
+///   [`Config::trust_proxy`](crate::config::Config::trust_proxy)
+pub fn client_ip(req: &HttpRequest, trust_proxy: bool) -> String {
+    let info = req.connection_info();
+    let addr = if trust_proxy {
+        info.realip_remote_addr()
+    } else {
+        info.peer_addr()
+    }
+    .unwrap_or("unknown");
+
+    // proxies can include the port
+    match addr.parse::<std::net::SocketAddr>() {
+        Ok(a) => a.ip().to_string(),
+        Err(_) => addr.to_string(),
+    }
+}
+
+/// Get the username a sign in is for, `None` for sign ins with an account ID (anyone guessing
+/// IDs isn't targeting a specific account)
+///
+/// # Arguments:
+/// * `data` - the app data
+/// * `path` - the path of the request, one of [`LOGIN_PATHS`]
+/// * `body` - the body of the request
+async fn login_target(data: &AppData, path: &str, body: &[u8]) -> Option<String> {
+    let body: serde_json::Value = serde_json::from_slice(body).ok()?;
+
+    match path {
+        "/api/v1/auth/login" => {
+            let password = body.get("password")?.as_str()?;
+            let username = body.get("username")?.as_str()?.trim();
+
+            if password.is_empty() | username.is_empty() {
+                return None;
+            }
+
+            Some(username.to_lowercase())
+        }
+        "/api/v1/auth/login/totp" => {
+            let challenge = body.get("challenge")?.as_str()?;
+
+            data.db
+                .get_totp_challenge(challenge.to_string())
+                .await
+                .payload
+                .map(|u| u.to_lowercase())
+        }
+        _ => None,
+    }
+}
+
+/// The response sent to clients that are locked out
+fn locked_out(secs: u64) -> HttpResponse {
+    HttpResponse::TooManyRequests()
+        .append_header((RETRY_AFTER, secs))
+        .append_header(("Content-Type", "application/json"))
+        .body(
+            serde_json::to_string(&DefaultReturn::<Option<String>> {
+                success: false,
+                message: format!("Too many failed attempts, please try again in {secs} seconds"),
+                payload: None,
+            })
+            .unwrap(),
+        )
+}
+
+/// Lock out IP addresses and accounts after too many failed sign ins at [`LOGIN_PATHS`]
+///
+/// A sign in failed if the endpoint responds with `406 Not Acceptable`. Once there are too
+/// many failures (see [`Config`](crate::config::Config)'s `login_*` settings), each failure locks
+/// out the IP address or account for twice as long as the last. Locked out requests never reach
+/// the endpoint and get `429 Too Many Requests` with `Retry-After`.
+pub async fn login_limit(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    if (req.method() != Method::POST) | !LOGIN_PATHS.contains(&req.path()) {
+        return next.call(req).await.map(|r| r.map_into_left_body());
+    }
+
+    let data = req.app_data::<web::Data<AppData>>().unwrap().clone();
+    let config = &data.config;
+
+    // read the body to find the account, then put it back for the endpoint
+    let body = req.extract::<web::Bytes>().await?;
+    req.set_payload(Payload::from(body.clone()));
+
+    let mut keys: Vec<(String, u64)> = Vec::new();
+
+    if config.login_ip_attempts > 0 {
+        keys.push((
+            format!("ip:{}", client_ip(req.request(), config.trust_proxy)),
+            config.login_ip_attempts,
+        ));
+    }
+
+    if config.login_user_attempts > 0 {
+        if let Some(username) = login_target(&data, req.path(), &body).await {
+            keys.push((format!("user:{username}"), config.login_user_attempts));
+        }
+    }
+
+    // check lockouts
+    for (key, _) in &keys {
+        if let Some(secs) = data.db.get_login_lockout(key).await {
+            return Ok(req.into_response(locked_out(secs)).map_into_right_body());
+        }
+    }
+
+    let mut res = next.call(req).await?;
+
+    if res.status() == StatusCode::NOT_ACCEPTABLE {
+        let mut retry_after: Option<u64> = None;
+
+        for (key, attempts) in &keys {
+            let secs = data
+                .db
+                .record_login_failure(
+                    key,
+                    *attempts,
+                    config.login_window,
+                    config.login_lockout,
+                    config.login_max_lockout,
+                )
+                .await;
+
+            retry_after = retry_after.max(secs);
+        }
+
+        if let Some(secs) = retry_after {
+            res.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(secs));
+        }
+    } else if res.status().is_success() {
+        // only the account's failures are forgotten, otherwise signing into any account would
+        // reset the IP address's failures
+        for (key, _) in keys.iter().filter(|(k, _)| k.starts_with("user:")) {
+            data.db.clear_login_failures(key).await;
+        }
+    }
+
+    Ok(res.map_into_left_body())
+}
+
+/// Get the route group a request is rate limited in, `None` if it isn't rate limited
+///
+/// * `activity` - creating, editing and deleting posts
+/// * `favorites` - favoriting posts
+/// * `follows` - following users
+/// * `writes` - every other `POST`, `PUT` and `DELETE` request
+///
+/// # Arguments:
+/// * `method` - the method of the request
+/// * `path` - the path of the request
+pub fn route_group(method: &Method, path: &str) -> Option<&'static str> {
+    if [Method::GET, Method::HEAD, Method::OPTIONS].contains(method) {
+        return None;
+    }
+
+    if path.starts_with("/api/v1/activity/") && path.ends_with("/favorite") {
+        return Some("favorites");
+    }
+
+    if (path == "/api/v1/activity") | path.starts_with("/api/v1/activity/") {
+        return Some("activity");
+    }
+
+    if path.starts_with("/api/v1/auth/users/") && path.ends_with("/follow") {
+        return Some("follows");
+    }
+
+    Some("writes")
+}
+
+/// Get the user making a request, without checking API token scopes (endpoints do that)
+///
+/// Users are found the same way as [`check_auth_status`](crate::pages::base::check_auth_status)
+/// finds them, but legacy ID cookies aren't replaced with a session here.
+async fn request_user(req: &HttpRequest, data: &AppData) -> Option<FullUser<UserMetadata>> {
+    if let Some(token) = crate::pages::base::bearer_token(req) {
+        let token = data.db.get_api_token(token).await.payload?;
+        return data.db.get_user_by_username(token.username).await.ok();
+    }
+
+    let cookie = req.cookie("__Secure-Token")?.value().to_string();
+
+    if let Ok(ua) = data.db.get_user_by_session(cookie.clone()).await {
+        return Some(ua);
+    }
+
+    // older versions stored the user's ID in the cookie
+    if !crate::pages::base::accepts_legacy_cookie(req, &data.config) {
+        return None;
+    }
+
+    let ua = data.db.get_user_by_unhashed(cookie).await.ok()?;
+
+    // users with two-factor authentication aren't signed in by these
+    if data.db.is_totp_enabled(ua.user.username.clone()).await {
+        return None;
+    }
+
+    Some(ua)
+}
+
+/// Limit how often requests in each [route group](route_group) can be made
+///
+/// Signed in users get a token bucket for each group, everyone else shares one per IP address.
+/// Limits come from [`Config::rate_limit`](crate::config::Config::rate_limit), so they can be
+/// different for each role. Responses have `RateLimit-Limit`, `RateLimit-Remaining` and
+/// `RateLimit-Reset` headers, and limited requests get `429 Too Many Requests` with
+/// `Retry-After`.
+pub async fn rate_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let group = match route_group(req.method(), req.path()) {
+        Some(g) => g,
+        None => return next.call(req).await.map(|r| r.map_into_left_body()),
+    };
+
+    let data = req.app_data::<web::Data<AppData>>().unwrap().clone();
+    let user = request_user(req.request(), &data).await;
+
+    let (key, limit) = match user {
+        Some(ref ua) => (
+            format!("{group}:user:{}", ua.user.username),
+            data.config.rate_limit(group, Some(&ua.level.name)),
+        ),
+        None => (
+            format!(
+                "{group}:ip:{}",
+                client_ip(req.request(), data.config.trust_proxy)
+            ),
+            data.config.rate_limit(group, None),
+        ),
+    };
+
+    let limit = match limit {
+        Some(l) if l.capacity > 0 => l,
+        _ => return next.call(req).await.map(|r| r.map_into_left_body()),
+    };
+
+    // requests aren't limited if the cache can't be reached
+    let (taken, tokens) = match data
+        .db
+        .take_rate_limit_token(&key, limit.capacity, limit.per_minute)
+        .await
+    {
+        Some(r) => r,
+        None => return next.call(req).await.map(|r| r.map_into_left_body()),
+    };
+
+    // tokens are counted in 1/60000ths, and per_minute of those are added every millisecond
+    let remaining = tokens / 60000;
+    let reset = (limit.capacity * 60000 - tokens).div_ceil(limit.per_minute * 1000);
+
+    let mut res = if taken {
+        next.call(req).await?.map_into_left_body()
+    } else {
+        let retry_after = (60000 - tokens).div_ceil(limit.per_minute * 1000);
+
+        req.into_response(
+            HttpResponse::TooManyRequests()
+                .append_header((RETRY_AFTER, retry_after))
+                .append_header(("Content-Type", "application/json"))
+                .body(
+                    serde_json::to_string(&DefaultReturn::<Option<String>> {
+                        success: false,
+                        message: format!(
+                            "You're doing this too often, please try again in {retry_after} seconds"
+                        ),
+                        payload: None,
+                    })
+                    .unwrap(),
+                ),
+        )
+        .map_into_right_body()
+    };
+
+    let headers = res.headers_mut();
+    headers.insert(
+        HeaderName::from_static("ratelimit-limit"),
+        HeaderValue::from(limit.capacity),
+    );
+    headers.insert(
+        HeaderName::from_static("ratelimit-remaining"),
+        HeaderValue::from(remaining),
+    );
+    headers.insert(
+        HeaderName::from_static("ratelimit-reset"),
+        HeaderValue::from(reset),
+    );
+
+    Ok(res)
+}
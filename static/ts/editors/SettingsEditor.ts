@@ -33,6 +33,9 @@ export function user_settings(
                         `/api/v1/auth/users/${name}/secondary-token`,
                         {
                             method: "POST",
+                            headers: {
+                                "X-CSRF-Token": (globalThis as any).csrf_token(),
+                            },
                         }
                     );
 
@@ -92,6 +95,7 @@ export function user_settings(
             body: JSON.stringify(metadata),
             headers: {
                 "Content-Type": "application/json",
+                "X-CSRF-Token": (globalThis as any).csrf_token(),
             },
         });
 
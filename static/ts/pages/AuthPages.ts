@@ -15,8 +15,85 @@ const login_st_form: HTMLFormElement | null = document.getElementById(
     "login-user-st",
 ) as HTMLFormElement | null;
 
+const login_password_form: HTMLFormElement | null = document.getElementById(
+    "login-user-password",
+) as HTMLFormElement | null;
+
 const callback = document.getElementById("callback")!.innerText;
 
+/// Get where to go after signing in, other services get the ID in the URL while pages
+/// on this site (like OAuth consent) just use the session cookie
+function callback_url(uid: string): string {
+    return callback.startsWith("/") ? callback : `${callback}?uid=${uid}`;
+}
+
+/// Show the logged in message
+function logged_in(uid: string) {
+    success.style.display = "flex";
+    success.innerHTML = `<p>Successfully logged into account.</p>
+        
+        <hr />
+        <a href="${callback_url(uid)}" class="button round theme:primary">Continue</a>`;
+    forms.style.display = "none";
+
+    if (switch_button) {
+        switch_button.remove();
+    }
+}
+
+/// Ask for a two-factor authentication code, then finish logging in
+function totp_step(challenge: string, uid: string) {
+    error.style.display = "none";
+    forms.innerHTML = `<form
+        class="card more_padding secondary round w-full flex flex-col gap-2"
+        id="login-totp"
+    >
+        <label for="code"><b>Two-factor authentication code</b></label>
+
+        <input
+            type="text"
+            name="code"
+            id="code"
+            placeholder="Code from your authenticator app, or a recovery code"
+            class="w-full round"
+            autocomplete="one-time-code"
+            required="true"
+        />
+
+        <hr />
+
+        <button class="theme:primary w-full round">Continue</button>
+    </form>`;
+
+    const totp_form = document.getElementById("login-totp") as HTMLFormElement;
+    totp_form.addEventListener("submit", async (e) => {
+        e.preventDefault();
+        const res = await fetch("/api/v1/auth/login/totp", {
+            method: "POST",
+            body: JSON.stringify({
+                challenge,
+                code: totp_form.code.value,
+            }),
+            headers: {
+                "Content-Type": "application/json",
+                "X-CSRF-Token": (globalThis as any).csrf_token(),
+            },
+        });
+
+        if (!res.ok) {
+            // locked out responses are json
+            const message =
+                res.status === 429 ? (await res.json()).message : await res.text();
+
+            error.style.display = "block";
+            error.innerHTML = `<div class="mdnote-title">${message}</div>`;
+            return;
+        }
+
+        logged_in(uid);
+    });
+}
+
 if (register_form) {
     // register
     register_form.addEventListener("submit", async (e) => {
@@ -30,6 +107,7 @@ if (register_form) {
             }),
             headers: {
                 "Content-Type": "application/json",
+                "X-CSRF-Token": (globalThis as any).csrf_token(),
             },
         });
 
@@ -47,7 +125,7 @@ if (register_form) {
             <p><b>Do not lose it!</b> This code is required for you to sign into your account, <b>it cannot be reset!</b></p>
             
             <hr />
-            <a href="${callback}?uid=${json.message}" class="button round theme:primary">Continue</a>`;
+            <a href="${callback_url(json.message)}" class="button round theme:primary">Continue</a>`;
             forms.style.display = "none";
         }
     });
@@ -62,6 +140,7 @@ if (register_form) {
             }),
             headers: {
                 "Content-Type": "application/json",
+                "X-CSRF-Token": (globalThis as any).csrf_token(),
             },
         });
 
@@ -70,17 +149,10 @@ if (register_form) {
         if (json.success === false) {
             error.style.display = "block";
             error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+        } else if (json.totp_required) {
+            totp_step(json.challenge, login_form.uid.value);
         } else {
-            success.style.display = "flex";
-            success.innerHTML = `<p>Successfully logged into account.</p>
-                
-                <hr />
-                <a href="${callback}?uid=${json.message}" class="button round theme:primary">Continue</a>`;
-            forms.style.display = "none";
-
-            if (switch_button) {
-                switch_button.remove();
-            }
+            logged_in(json.message);
         }
     });
 } else if (login_st_form) {
@@ -94,6 +166,7 @@ if (register_form) {
             }),
             headers: {
                 "Content-Type": "application/json",
+                "X-CSRF-Token": (globalThis as any).csrf_token(),
             },
         });
 
@@ -102,17 +175,37 @@ if (register_form) {
         if (json.success === false) {
             error.style.display = "block";
             error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+        } else if (json.totp_required) {
+            totp_step(json.challenge, login_st_form.uid.value);
         } else {
-            success.style.display = "block";
-            success.innerHTML = `<p>Successfully logged into account.</p>
-                
-                <hr />
-                <a href="${callback}?uid=${json.message}" class="button round theme:primary">Continue</a>`;
-            forms.style.display = "none";
+            logged_in(json.message);
+        }
+    });
+} else if (login_password_form) {
+    // login (password)
+    login_password_form.addEventListener("submit", async (e) => {
+        e.preventDefault();
+        const res = await fetch("/api/v1/auth/login", {
+            method: "POST",
+            body: JSON.stringify({
+                username: login_password_form.username.value,
+                password: login_password_form.password.value,
+            }),
+            headers: {
+                "Content-Type": "application/json",
+                "X-CSRF-Token": (globalThis as any).csrf_token(),
+            },
+        });
+
+        const json = await res.json();
 
-            if (switch_button) {
-                switch_button.remove();
-            }
+        if (json.success === false || !res.ok) {
+            error.style.display = "block";
+            error.innerHTML = `<div class="mdnote-title">${res.status === 429 ? json.message : "Invalid username or password"}</div>`;
+        } else if (json.totp_required) {
+            totp_step(json.challenge, "");
+        } else {
+            logged_in("");
         }
     });
 }
@@ -1,3 +1,12 @@
+// csrf, sent with every request that changes something
+(globalThis as any).csrf_token = (): string => {
+    const cookie = document.cookie
+        .split("; ")
+        .find((c) => c.startsWith("__Host-Csrf="));
+
+    return cookie ? cookie.split("=")[1] : "";
+};
+
 // theme
 (globalThis as any).sun_icon = document.getElementById("theme_icon_sun");
 (globalThis as any).moon_icon = document.getElementById("theme_icon_moon");
@@ -290,6 +299,21 @@ window.addEventListener("click", (e: any) => {
     if (clicked_in_dialog === false) e.target.close();
 });
 
+// notifications
+const notification_count = document.getElementById("notification_count");
+
+if (notification_count) {
+    fetch("/api/v1/notifications/count").then(async (res) => {
+        if (!res.ok) return;
+        const json = await res.json();
+
+        if (json.success === true && json.payload > 0) {
+            notification_count.innerText = json.payload.toString();
+            notification_count.style.display = "inline";
+        }
+    });
+}
+
 // reports
 const report_button = document.getElementById("report_button");
 
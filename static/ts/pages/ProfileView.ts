@@ -17,6 +17,7 @@ if (edit_form) {
             }),
             headers: {
                 "Content-Type": "application/json",
+                "X-CSRF-Token": (globalThis as any).csrf_token(),
             },
         });
 
@@ -45,6 +46,7 @@ if (follow_button) {
             method: "POST",
             headers: {
                 "Content-Type": "application/json",
+                "X-CSRF-Token": (globalThis as any).csrf_token(),
             },
         });
 
@@ -78,6 +80,7 @@ if (compose_form) {
             }),
             headers: {
                 "Content-Type": "application/json",
+                "X-CSRF-Token": (globalThis as any).csrf_token(),
             },
         });
 
@@ -97,10 +100,47 @@ if (compose_form) {
     });
 }
 
+// edit activity
+const edit_activity_form: HTMLFormElement | null = document.getElementById(
+    "edit_activity",
+) as HTMLFormElement | null;
+
+if (edit_activity_form) {
+    // edit existing activity
+    edit_activity_form.addEventListener("submit", async (e) => {
+        e.preventDefault();
+        const res = await fetch(
+            edit_activity_form.getAttribute("data-endpoint")!,
+            {
+                method: "PUT",
+                body: JSON.stringify({
+                    content: edit_activity_form.content.value,
+                }),
+                headers: {
+                    "Content-Type": "application/json",
+                    "X-CSRF-Token": (globalThis as any).csrf_token(),
+                },
+            },
+        );
+
+        const json = await res.json();
+
+        if (json.success === false) {
+            error.style.display = "block";
+            error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+        } else {
+            window.location.reload();
+        }
+    });
+}
+
 // post favorites
 (globalThis as any).favorite_post = async (id: string) => {
     const res = await fetch(`/api/v1/activity/${id}/favorite`, {
         method: "POST",
+        headers: {
+            "X-CSRF-Token": (globalThis as any).csrf_token(),
+        },
     });
 
     const json = await res.json();
@@ -125,6 +165,9 @@ if (delete_post) {
         e.preventDefault();
         const res = await fetch(delete_post.getAttribute("data-endpoint")!, {
             method: "DELETE",
+            headers: {
+                "X-CSRF-Token": (globalThis as any).csrf_token(),
+            },
         });
 
         const json = await res.json();
@@ -139,5 +182,456 @@ if (delete_post) {
     });
 }
 
+// mark notifications as read
+const mark_notifications_read: HTMLButtonElement | null =
+    document.getElementById(
+        "mark_notifications_read",
+    ) as HTMLButtonElement | null;
+
+if (mark_notifications_read) {
+    // mark every notification as read
+    mark_notifications_read.addEventListener("click", async (e) => {
+        e.preventDefault();
+        const res = await fetch(
+            mark_notifications_read.getAttribute("data-endpoint")!,
+            {
+                method: "POST",
+                body: JSON.stringify({
+                    ids: [],
+                }),
+                headers: {
+                    "Content-Type": "application/json",
+                    "X-CSRF-Token": (globalThis as any).csrf_token(),
+                },
+            },
+        );
+
+        const json = await res.json();
+
+        if (json.success === false) {
+            error.style.display = "block";
+            error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+        } else {
+            window.location.reload();
+        }
+    });
+}
+
+// create invite
+const create_invite_form: HTMLFormElement | null = document.getElementById(
+    "create_invite",
+) as HTMLFormElement | null;
+
+if (create_invite_form) {
+    // create a new invite
+    create_invite_form.addEventListener("submit", async (e) => {
+        e.preventDefault();
+        const res = await fetch(
+            create_invite_form.getAttribute("data-endpoint")!,
+            {
+                method: "POST",
+                body: JSON.stringify({
+                    max_uses: create_invite_form.max_uses
+                        ? parseInt(create_invite_form.max_uses.value)
+                        : undefined,
+                    expires_in:
+                        create_invite_form.expires_in &&
+                        create_invite_form.expires_in.value
+                            ? parseInt(create_invite_form.expires_in.value)
+                            : undefined,
+                }),
+                headers: {
+                    "Content-Type": "application/json",
+                    "X-CSRF-Token": (globalThis as any).csrf_token(),
+                },
+            },
+        );
+
+        // errors that happen before the invite is created are plain text
+        if (!res.ok) {
+            error.style.display = "block";
+            error.innerHTML = `<div class="mdnote-title">${await res.text()}</div>`;
+            return;
+        }
+
+        const json = await res.json();
+
+        if (json.success === false) {
+            error.style.display = "block";
+            error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+        } else {
+            window.location.reload();
+        }
+    });
+}
+
+// revoke invite
+(globalThis as any).revoke_invite = async (code: string) => {
+    if (!confirm("Are you sure you want to revoke this invite?")) {
+        return;
+    }
+
+    const res = await fetch(`/api/v1/invites/${code}`, {
+        method: "DELETE",
+        headers: {
+            "X-CSRF-Token": (globalThis as any).csrf_token(),
+        },
+    });
+
+    if (!res.ok) {
+        error.style.display = "block";
+        error.innerHTML = `<div class="mdnote-title">${await res.text()}</div>`;
+        return;
+    }
+
+    const json = await res.json();
+
+    if (json.success === false) {
+        error.style.display = "block";
+        error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+    } else {
+        document.getElementById(`invite-${code}`)!.remove();
+    }
+};
+
+// rotate account id
+(globalThis as any).rotate_id = async (name: string) => {
+    if (
+        !confirm(
+            "Are you sure you want to rotate this account's ID? The current ID will stop working.",
+        )
+    ) {
+        return;
+    }
+
+    const res = await fetch(`/api/v1/auth/users/${name}/rotate-id`, {
+        method: "POST",
+        headers: {
+            "X-CSRF-Token": (globalThis as any).csrf_token(),
+        },
+    });
+
+    if (!res.ok) {
+        error.style.display = "block";
+        error.innerHTML = `<div class="mdnote-title">${await res.text()}</div>`;
+        return;
+    }
+
+    const json = await res.json();
+
+    if (json.success === false) {
+        error.style.display = "block";
+        error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+    } else {
+        success.style.display = "block";
+        success.innerHTML = `<p>ID rotated! You can now login using this ID:</p>
+
+        <p class="card secondary round flex justify-center align-center">${json.payload}</p>
+
+        <p><b>Do not lose it!</b> This ID is required to sign into the account, <b>it will not be shown again!</b></p>`;
+
+        // other sessions were signed out
+        document.getElementById("sessions")?.remove();
+    }
+};
+
+// revoke session
+(globalThis as any).revoke_session = async (id: string) => {
+    const res = await fetch(`/api/v1/auth/sessions/${id}`, {
+        method: "DELETE",
+        headers: {
+            "X-CSRF-Token": (globalThis as any).csrf_token(),
+        },
+    });
+
+    const json = await res.json();
+
+    if (json.success === false) {
+        error.style.display = "block";
+        error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+    } else {
+        document.getElementById(`session-${id}`)!.remove();
+    }
+};
+
+// revoke every other session
+(globalThis as any).revoke_other_sessions = async () => {
+    if (!confirm("Are you sure you want to sign out everywhere else?")) {
+        return;
+    }
+
+    const res = await fetch("/api/v1/auth/sessions", {
+        method: "DELETE",
+        headers: {
+            "X-CSRF-Token": (globalThis as any).csrf_token(),
+        },
+    });
+
+    const json = await res.json();
+
+    if (json.success === false) {
+        error.style.display = "block";
+        error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+    } else {
+        window.location.reload();
+    }
+};
+
+const set_password_form: HTMLFormElement | null = document.getElementById(
+    "set_password",
+) as HTMLFormElement | null;
+
+if (set_password_form) {
+    // add or change password
+    set_password_form.addEventListener("submit", async (e) => {
+        e.preventDefault();
+        const res = await fetch("/api/v1/auth/password", {
+            method: "POST",
+            body: JSON.stringify({
+                password: set_password_form.password.value,
+                current_password: set_password_form.current_password
+                    ? set_password_form.current_password.value
+                    : undefined,
+            }),
+            headers: {
+                "Content-Type": "application/json",
+                "X-CSRF-Token": (globalThis as any).csrf_token(),
+            },
+        });
+
+        if (!res.ok) {
+            error.style.display = "block";
+            error.innerHTML = `<div class="mdnote-title">${await res.text()}</div>`;
+            return;
+        }
+
+        const json = await res.json();
+
+        if (json.success === false) {
+            error.style.display = "block";
+            error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+        } else {
+            window.location.reload();
+        }
+    });
+}
+
+// remove password
+(globalThis as any).remove_password = async () => {
+    if (!set_password_form || !set_password_form.current_password.value) {
+        error.style.display = "block";
+        error.innerHTML = `<div class="mdnote-title">Enter your current password to remove it</div>`;
+        return;
+    }
+
+    if (!confirm("Are you sure you want to remove your password?")) {
+        return;
+    }
+
+    const res = await fetch("/api/v1/auth/password", {
+        method: "DELETE",
+        body: JSON.stringify({
+            current_password: set_password_form.current_password.value,
+        }),
+        headers: {
+            "Content-Type": "application/json",
+            "X-CSRF-Token": (globalThis as any).csrf_token(),
+        },
+    });
+
+    if (!res.ok) {
+        error.style.display = "block";
+        error.innerHTML = `<div class="mdnote-title">${await res.text()}</div>`;
+        return;
+    }
+
+    const json = await res.json();
+
+    if (json.success === false) {
+        error.style.display = "block";
+        error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+    } else {
+        window.location.reload();
+    }
+};
+
+// begin enabling two-factor authentication
+(globalThis as any).begin_totp = async () => {
+    const res = await fetch("/api/v1/auth/totp", {
+        method: "POST",
+        headers: {
+            "X-CSRF-Token": (globalThis as any).csrf_token(),
+        },
+    });
+
+    if (!res.ok) {
+        error.style.display = "block";
+        error.innerHTML = `<div class="mdnote-title">${await res.text()}</div>`;
+        return;
+    }
+
+    const json = await res.json();
+
+    if (json.success === false) {
+        error.style.display = "block";
+        error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+    } else {
+        document.getElementById("totp_qr")!.innerHTML = json.payload.qr;
+        document.getElementById("totp_secret")!.innerText =
+            json.payload.secret;
+        document.getElementById("totp_setup")!.style.display = "flex";
+    }
+};
+
+const confirm_totp_form: HTMLFormElement | null = document.getElementById(
+    "confirm_totp",
+) as HTMLFormElement | null;
+
+if (confirm_totp_form) {
+    // finish enabling two-factor authentication
+    confirm_totp_form.addEventListener("submit", async (e) => {
+        e.preventDefault();
+        const res = await fetch("/api/v1/auth/totp/confirm", {
+            method: "POST",
+            body: JSON.stringify({
+                code: confirm_totp_form.code.value,
+            }),
+            headers: {
+                "Content-Type": "application/json",
+                "X-CSRF-Token": (globalThis as any).csrf_token(),
+            },
+        });
+
+        if (!res.ok) {
+            error.style.display = "block";
+            error.innerHTML = `<div class="mdnote-title">${await res.text()}</div>`;
+            return;
+        }
+
+        const json = await res.json();
+
+        if (json.success === false) {
+            error.style.display = "block";
+            error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+        } else {
+            error.style.display = "none";
+            document.getElementById("totp_setup")!.remove();
+            document.getElementById("totp_recovery_codes")!.innerText =
+                json.payload.join("\n");
+            document.getElementById("totp_recovery")!.style.display = "flex";
+        }
+    });
+}
+
+const disable_totp_form: HTMLFormElement | null = document.getElementById(
+    "disable_totp",
+) as HTMLFormElement | null;
+
+if (disable_totp_form) {
+    // disable two-factor authentication
+    disable_totp_form.addEventListener("submit", async (e) => {
+        e.preventDefault();
+        const res = await fetch("/api/v1/auth/totp", {
+            method: "DELETE",
+            body: JSON.stringify({
+                code: disable_totp_form.code.value,
+            }),
+            headers: {
+                "Content-Type": "application/json",
+                "X-CSRF-Token": (globalThis as any).csrf_token(),
+            },
+        });
+
+        if (!res.ok) {
+            error.style.display = "block";
+            error.innerHTML = `<div class="mdnote-title">${await res.text()}</div>`;
+            return;
+        }
+
+        const json = await res.json();
+
+        if (json.success === false) {
+            error.style.display = "block";
+            error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+        } else {
+            window.location.reload();
+        }
+    });
+}
+
+const create_api_token_form: HTMLFormElement | null = document.getElementById(
+    "create_api_token",
+) as HTMLFormElement | null;
+
+if (create_api_token_form) {
+    // create an api token
+    create_api_token_form.addEventListener("submit", async (e) => {
+        e.preventDefault();
+        const res = await fetch("/api/v1/auth/tokens", {
+            method: "POST",
+            body: JSON.stringify({
+                name: create_api_token_form.token_name.value,
+                scopes: Array.from(
+                    create_api_token_form.querySelectorAll(
+                        'input[name="scopes"]:checked',
+                    ),
+                ).map((input) => (input as HTMLInputElement).value),
+                expires_in: create_api_token_form.expires_in.value
+                    ? parseInt(create_api_token_form.expires_in.value)
+                    : undefined,
+            }),
+            headers: {
+                "Content-Type": "application/json",
+                "X-CSRF-Token": (globalThis as any).csrf_token(),
+            },
+        });
+
+        if (!res.ok) {
+            error.style.display = "block";
+            error.innerHTML = `<div class="mdnote-title">${await res.text()}</div>`;
+            return;
+        }
+
+        const json = await res.json();
+
+        if (json.success === false) {
+            error.style.display = "block";
+            error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+        } else {
+            error.style.display = "none";
+            create_api_token_form.reset();
+            success.style.display = "block";
+            success.innerHTML = `<p>Token created! Use it as <code>Authorization: Bearer &lt;token&gt;</code>:</p>
+
+            <p class="card secondary round flex justify-center align-center">${json.payload}</p>
+
+            <p><b>Copy it now</b>, <b>it will not be shown again!</b> Reload the page to see it in your tokens.</p>`;
+        }
+    });
+}
+
+// revoke api token
+(globalThis as any).revoke_api_token = async (id: string) => {
+    if (!confirm("Are you sure you want to revoke this token?")) {
+        return;
+    }
+
+    const res = await fetch(`/api/v1/auth/tokens/${id}`, {
+        method: "DELETE",
+        headers: {
+            "X-CSRF-Token": (globalThis as any).csrf_token(),
+        },
+    });
+
+    const json = await res.json();
+
+    if (json.success === false) {
+        error.style.display = "block";
+        error.innerHTML = `<div class="mdnote-title">${json.message}</div>`;
+    } else {
+        document.getElementById(`api-token-${id}`)!.remove();
+    }
+};
+
 // default export
 export default {};